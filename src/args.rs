@@ -37,6 +37,44 @@ impl Parser {
     /// assert_eq!(cmd.get_one::<String>("NAME"), None);
     /// ```
     ///
+    /// Basic: use a profile from config.toml with any subcommand
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "add", "--profile", "work", "-t", "binding", "-p", "foo=bar"]);
+    /// let cmd = args.subcommand_matches("add").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("PROFILE").unwrap(), "work");
+    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "--profile", "ci", "args", "-d"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("PROFILE").unwrap(), "ci");
+    /// ```
+    ///
+    /// Basic: work with the bindings in another directory
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "-r", "/tmp/other", "delete", "-n", "my-db"]);
+    /// let cmd = args.subcommand_matches("delete").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("BINDING_ROOT").unwrap(), "/tmp/other");
+    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "list", "--binding-root", "/tmp/other"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap().subcommand_matches("list").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("BINDING_ROOT").unwrap(), "/tmp/other");
+    /// ```
+    ///
+    /// Basic: answer yes to confirmations in scripts
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "delete", "-y", "-n", "my-db"]);
+    /// let cmd = args.subcommand_matches("delete").unwrap();
+    ///
+    /// assert!(cmd.get_flag("YES"));
+    /// assert!(!cmd.get_flag("FORCE"));
+    /// ```
+    ///
     /// More Advanced: Add with multiple parameters and a name
     ///
     /// ```
@@ -60,87 +98,777 @@ impl Parser {
     /// assert_eq!(cmd.get_one::<String>("NAME").unwrap(), "binding");
     /// ```
     ///
-    /// More Advanced: Delete parts of a binding
+    /// More Advanced: Delete parts of a binding
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "delete", "-f", "-n", "better_name", "-k", "foo"]);
+    /// let cmd = args.subcommand_matches("delete").unwrap();
+    ///
+    /// let keys:Vec<_> = cmd.get_many::<String>("KEY").unwrap().collect();
+    /// assert_eq!(keys, vec!["foo"]);
+    /// assert_eq!(cmd.get_one::<String>("NAME").unwrap(), "better_name");
+    /// assert_eq!(cmd.contains_id("FORCE"), true);
+    /// ```
+    ///
+    /// Convenience: add ca-certificates
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "-f", "-n", "my-certs", "-c", "/path/to/ca.crt"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    ///
+    ///
+    /// let certs:Vec<_> = cmd.get_many::<String>("CERT").unwrap().collect();
+    /// assert_eq!(certs, vec!["/path/to/ca.crt"]);
+    /// assert_eq!(cmd.get_one::<String>("NAME").unwrap(), "my-certs");
+    /// assert_eq!(cmd.contains_id("FORCE"), true);
+    /// ```
+    ///
+    /// Convenience: add every ca-certificate in a directory
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "--cert-dir", "/usr/local/share/ca-certificates/"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    ///
+    /// let dirs:Vec<_> = cmd.get_many::<String>("CERT_DIR").unwrap().collect();
+    /// assert_eq!(dirs, vec!["/usr/local/share/ca-certificates/"]);
+    /// assert!(cmd.get_many::<String>("CERT").is_none());
+    /// ```
+    ///
+    /// Convenience: add ca-certificates downloaded from a URL
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "--from-url", "https://pki.corp/root.pem"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    ///
+    /// let urls:Vec<_> = cmd.get_many::<String>("FROM_URL").unwrap().collect();
+    /// assert_eq!(urls, vec!["https://pki.corp/root.pem"]);
+    /// assert!(cmd.get_many::<String>("CERT").is_none());
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "ca-certs", "-n", "my-certs"]);
+    /// assert!(res.is_err(), "a certificate or URL is required");
+    /// ```
+    ///
+    /// Convenience: add the CA of a proxy that intercepts TLS
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "--detect-proxy", "https://example.com"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("DETECT_PROXY").unwrap(), "https://example.com");
+    /// assert!(cmd.get_many::<String>("CERT").is_none());
+    /// ```
+    ///
+    /// Convenience: add ca-certificates from a Kubernetes Secret or ConfigMap
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "--from-k8s", "secret/corp-ca", "--namespace", "kube-system"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    ///
+    /// let resources:Vec<_> = cmd.get_many::<String>("FROM_K8S").unwrap().collect();
+    /// assert_eq!(resources, vec!["secret/corp-ca"]);
+    /// assert_eq!(cmd.get_one::<String>("NAMESPACE").unwrap(), "kube-system");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "ca-certs", "-c", "/path/to/ca.crt", "--namespace", "kube-system"]);
+    /// assert!(res.is_err(), "a namespace only applies to Kubernetes resources");
+    /// ```
+    ///
+    /// Convenience: list the certificates of ca-certificates bindings
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "list", "-n", "my-certs"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    /// let list = cmd.subcommand_matches("list").unwrap();
+    ///
+    /// assert_eq!(list.get_one::<String>("NAME").unwrap(), "my-certs");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "ca-certs", "-c", "/path/to/ca.crt", "list"]);
+    /// assert!(res.is_err(), "list doesn't add certificates");
+    /// ```
+    ///
+    /// Convenience: check certificates for a scheduled job
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "check", "--warn-days", "14"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    /// let check = cmd.subcommand_matches("check").unwrap();
+    ///
+    /// assert_eq!(check.get_one::<u32>("WARN_DAYS"), Some(&14));
+    /// ```
+    ///
+    /// Convenience: remove certificates from ca-certificates bindings
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "remove", "--fingerprint", "2a9f0c1d"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    /// let remove = cmd.subcommand_matches("remove").unwrap();
+    ///
+    /// assert_eq!(remove.get_one::<String>("FINGERPRINT").unwrap(), "2a9f0c1d");
+    /// assert!(!remove.get_flag("ALL"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "ca-certs", "remove"]);
+    /// assert!(res.is_err(), "a fingerprint or common name is required");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "ca-certs", "remove", "--fingerprint", "2a9f", "--cn", "Corp Root CA"]);
+    /// assert!(res.is_err(), "only one way of matching certificates");
+    /// ```
+    ///
+    /// Convenience: fail on certificates that expire soon
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "-c", "/path/to/ca.crt", "--strict", "--expiry-window", "90"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    ///
+    /// assert!(cmd.get_flag("STRICT"));
+    /// assert!(!cmd.get_flag("CA_ONLY"));
+    /// assert_eq!(cmd.get_one::<u32>("EXPIRY_WINDOW"), Some(&90));
+    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "-c", "/path/to/ca.crt"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    /// assert_eq!(cmd.get_one::<u32>("EXPIRY_WINDOW"), Some(&30));
+    /// ```
+    ///
+    /// Convenience: write a truststore along with the certificates
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "-c", "/path/to/ca.crt", "--truststore", "pkcs12"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("TRUSTSTORE").map(|s| s.as_str()), Some("pkcs12"));
+    /// assert_eq!(cmd.get_one::<String>("PASSWORD_KEY").map(|s| s.as_str()), Some("truststore-password"));
+    ///
+    /// let args = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "ca-certs", "-c", "/path/to/ca.crt", "--truststore", "bks"]);
+    /// assert!(args.is_err());
+    /// ```
+    ///
+    /// Convenience: reconcile a ca-certificates binding with a set of certificates
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "sync", "-c", "a.pem", "-c", "b.pem", "-f"]);
+    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    /// let sync = cmd.subcommand_matches("sync").unwrap();
+    ///
+    /// let certs:Vec<_> = sync.get_many::<String>("CERT").unwrap().collect();
+    /// assert_eq!(certs, vec!["a.pem", "b.pem"]);
+    /// assert_eq!(sync.get_one::<String>("NAME").unwrap(), "ca-certificates");
+    /// assert!(sync.get_flag("FORCE"));
+    /// assert_eq!(sync.get_one::<u32>("WARN_DAYS"), Some(&30));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "ca-certs", "sync"]);
+    /// assert!(res.is_err(), "certificates are required");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-n", "my-deps", "-t", "/path/to/file.zip"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// let files:Vec<_> = cmd.get_many::<String>("TOML").unwrap().collect();
+    /// assert_eq!(files, vec!["/path/to/file.zip"]);
+    /// assert_eq!(cmd.get_one::<String>("NAME").unwrap(), "my-deps");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings from buildpack
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1:v1.0.1", "-b", "buildpack/id-2:v2.1.0"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// let bps:Vec<_> = cmd.get_many::<String>("BUILDPACK").unwrap().collect();
+    /// assert_eq!(bps, vec!["buildpack/id-1:v1.0.1", "buildpack/id-2:v2.1.0"]);
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings from a buildpack.toml URL
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-u", "https://internal.example.com/buildpacks/java/buildpack.toml"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// let urls:Vec<_> = cmd.get_many::<String>("URL").unwrap().collect();
+    /// assert_eq!(urls, vec!["https://internal.example.com/buildpacks/java/buildpack.toml"]);
+    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-u", "https://example.com/buildpack.toml", "-t", "buildpack.toml"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    /// assert!(cmd.contains_id("URL") && cmd.contains_id("TOML"), "sources may be combined into one binding");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings offline, from the download cache
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--offline", "-t", "buildpack.toml"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert!(cmd.get_flag("OFFLINE"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "--offline", "-b", "buildpack/id-1"]);
+    /// assert!(res.is_err(), "offline needs a local buildpack.toml");
+    /// ```
+    ///
+    /// Convenience: recreate dependency-mappings from a lockfile
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--lock"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("LOCK").unwrap(), "bt-deps.lock");
+    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--from-lock"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("FROM_LOCK").unwrap(), "bt-deps.lock");
+    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--from-lock", "ci/bt-deps.lock"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("FROM_LOCK").unwrap(), "ci/bt-deps.lock");
+    /// ```
+    ///
+    /// Convenience: repair the binaries of dependency-mappings
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--refresh"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert!(cmd.get_flag("REFRESH"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "--refresh", "--dry-run"]);
+    /// assert!(res.is_err(), "refresh changes the binding");
+    /// ```
+    ///
+    /// Convenience: sync dependency-mappings with their buildpacks
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--update", "--remove-retired", "-b", "buildpack/id-1"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert!(cmd.get_flag("UPDATE"));
+    /// assert!(cmd.get_flag("REMOVE_RETIRED"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "--remove-retired", "-b", "buildpack/id-1"]);
+    /// assert!(res.is_err(), "only an update knows what was retired");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings without downloading
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--no-download", "-b", "buildpack/id-1"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert!(cmd.get_flag("NO_DOWNLOAD"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "--no-download", "--offline", "-t", "buildpack.toml"]);
+    /// assert!(res.is_err(), "nothing is downloaded offline either way");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings for binaries served from elsewhere
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--uri-prefix", "https://artifacts.corp/bp-deps/", "-b", "buildpack/id-1"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("URI_PREFIX").unwrap(), "https://artifacts.corp/bp-deps/");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "--uri-prefix", "https://artifacts.corp/", "--no-download", "-b", "buildpack/id-1"]);
+    /// assert!(res.is_err(), "mappings without downloads point at the dependency URIs");
+    /// ```
+    ///
+    /// Convenience: serve the binaries of dependency-mappings
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--serve", ":8080"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("SERVE").unwrap(), ":8080");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "--serve", ":8080", "--no-download", "-b", "buildpack/id-1"]);
+    /// assert!(res.is_err(), "there is nothing to serve without downloads");
+    /// ```
+    ///
+    /// Convenience: clean up dependency-mappings
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "purge", "-n", "deps"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    /// let purge = cmd.subcommand_matches("purge").unwrap();
+    ///
+    /// assert_eq!(purge.get_one::<String>("NAME").unwrap(), "deps");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "purge"]);
+    /// assert!(res.is_err(), "purge doesn't add dependencies");
+    /// ```
+    ///
+    /// Convenience: share a dependency-mapping through an OCI registry
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "push", "ghcr.io/org/deps:tag"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    /// let push = cmd.subcommand_matches("push").unwrap();
+    ///
+    /// assert_eq!(push.get_one::<String>("IMAGE").unwrap(), "ghcr.io/org/deps:tag");
+    /// assert_eq!(push.get_one::<String>("NAME"), None);
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "push"]);
+    /// assert!(res.is_err(), "push needs an image");
+    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "pull", "-n", "deps", "ghcr.io/org/deps:tag"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    /// let pull = cmd.subcommand_matches("pull").unwrap();
+    ///
+    /// assert_eq!(pull.get_one::<String>("IMAGE").unwrap(), "ghcr.io/org/deps:tag");
+    /// assert_eq!(pull.get_one::<String>("NAME").unwrap(), "deps");
+    /// ```
+    ///
+    /// Convenience: carry a dependency-mapping to a disconnected network
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--bundle", "deps.tar.gz"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("BUNDLE").unwrap(), "deps.tar.gz");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--no-download", "--bundle", "deps.tar.gz"]);
+    /// assert!(res.is_err(), "a bundle needs the binaries");
+    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "import", "deps.tar.gz"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    /// let import = cmd.subcommand_matches("import").unwrap();
+    ///
+    /// assert_eq!(import.get_one::<String>("BUNDLE").unwrap(), "deps.tar.gz");
+    /// ```
+    ///
+    /// Convenience: describe the binaries of a dependency-mapping for security teams
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--sbom", "sbom.json"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("SBOM").unwrap(), "sbom.json");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--dry-run", "--sbom", "-"]);
+    /// assert!(res.is_err(), "nothing is in the binding after a dry run");
+    /// ```
+    ///
+    /// Convenience: only add artifacts signed with cosign
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--verify-key", "cosign.pub"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("VERIFY_KEY").unwrap(), "cosign.pub");
+    /// assert!(!cmd.get_flag("INSECURE_SKIP_VERIFY"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--verify-key", "cosign.pub", "--insecure-skip-verify"]);
+    /// assert!(res.is_err(), "signatures are either checked or skipped");
+    /// ```
+    ///
+    /// Convenience: prune old versions of dependency-mappings
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--keep-latest", "2", "-b", "buildpack/id-1"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<u16>("KEEP_LATEST"), Some(&2));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "--keep-latest", "0"]);
+    /// assert!(res.is_err(), "at least one version is kept");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings for some components of a composite buildpack
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "paketo-buildpacks/java", "--component", "paketo-buildpacks/maven", "--component", "paketo-buildpacks/gradle"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_many::<String>("COMPONENT").unwrap().collect::<Vec<_>>(), vec!["paketo-buildpacks/maven", "paketo-buildpacks/gradle"]);
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "-t", "buildpack.toml", "--component", "paketo-buildpacks/maven"]);
+    /// assert!(res.is_err(), "components are only picked from buildpacks");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings for the buildpacks of a project
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--project", "project.toml"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("PROJECT").unwrap(), "project.toml");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "--project", "project.toml", "-b", "buildpack/id-1"]);
+    /// assert!(res.is_err(), "a project is its own list of buildpacks");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings for some of a buildpack's dependencies
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--include", "*linux-amd64*", "--exclude", "*musl*"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_many::<String>("INCLUDE").unwrap().collect::<Vec<_>>(), vec!["*linux-amd64*"]);
+    /// assert_eq!(cmd.get_many::<String>("EXCLUDE").unwrap().collect::<Vec<_>>(), vec!["*musl*"]);
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings and report on the downloads
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--report", "report.json"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("REPORT").unwrap(), "report.json");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--report", "-", "--dry-run"]);
+    /// assert!(res.is_err(), "nothing is downloaded on a dry run");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings without using all the bandwidth
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--limit-rate", "10M"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(*cmd.get_one::<u64>("LIMIT_RATE").unwrap(), 10 * 1024 * 1024);
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--limit-rate", "fast"]);
+    /// assert!(res.is_err(), "rate should be a number of bytes");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings over a slow connection
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1", "--connect-timeout", "10", "--read-timeout", "30", "--timeout", "600"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(*cmd.get_one::<u64>("CONNECT_TIMEOUT").unwrap(), 10);
+    /// assert_eq!(*cmd.get_one::<u64>("READ_TIMEOUT").unwrap(), 30);
+    /// assert_eq!(*cmd.get_one::<u64>("TIMEOUT").unwrap(), 600);
+    /// ```
+    ///
+    /// Convenience: list the versions of a buildpack
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "--list-versions", "paketo-buildpacks/java"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("LIST_VERSIONS").unwrap(), "paketo-buildpacks/java");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "--list-versions", "paketo-buildpacks/java", "-b", "buildpack/id-1"]);
+    /// assert!(res.is_err(), "listing versions doesn't add dependencies");
+    /// ```
+    ///
+    /// Convenience: add dependency-mappings with limited download concurrency
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-j", "2", "-b", "buildpack/id-1"]);
+    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<u16>("JOBS"), Some(&2));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "dependency-mapping", "-j", "0", "-b", "buildpack/id-1"]);
+    /// assert!(res.is_err(), "jobs should be at least 1");
+    /// ```
+    ///
+    /// Convenience: configure bash
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "init", "bash"]);
+    /// let cmd = args.subcommand_matches("init").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("SHELL").unwrap(), "bash");
+    /// ```
+    ///
+    /// Convenience: configure PowerShell
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "init", "powershell"]);
+    /// let cmd = args.subcommand_matches("init").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("SHELL").unwrap(), "powershell");
+    /// ```
+    ///
+    /// Convenience: configure Nushell
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "init", "nu"]);
+    /// let cmd = args.subcommand_matches("init").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("SHELL").unwrap(), "nu");
+    /// ```
+    ///
+    /// Convenience: wrappers that don't shadow docker, pack and podman
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "init", "bash", "--prefix", "bt-"]);
+    /// let cmd = args.subcommand_matches("init").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("PREFIX").unwrap(), "bt-");
+    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "init", "bash", "--no-shadow"]);
+    /// let cmd = args.subcommand_matches("init").unwrap();
+    ///
+    /// assert!(cmd.get_flag("NO_SHADOW"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "init", "--prefix", "my-", "--no-shadow"]);
+    /// assert!(res.is_err(), "should not take both");
+    /// ```
+    ///
+    /// Convenience: export the binding root with direnv
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "init", "direnv", "--watch"]);
+    /// let cmd = args.subcommand_matches("init").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("SHELL").unwrap(), "direnv");
+    /// assert!(cmd.get_flag("WATCH"));
+    /// ```
+    ///
+    /// Convenience: detect the shell
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "init"]);
+    /// let cmd = args.subcommand_matches("init").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("SHELL"), None);
+    /// ```
+    ///
+    ///
+    /// Inject: copy the bindings into a running container
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "inject", "my-app", "--container-path", "/custom/bindings"]);
+    /// let cmd = args.subcommand_matches("inject").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("CONTAINER").unwrap(), "my-app");
+    /// assert_eq!(cmd.get_one::<String>("CONTAINER_PATH").unwrap(), "/custom/bindings");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "inject"]);
+    /// assert!(res.is_err(), "should require a container");
+    /// ```
+    ///
+    /// Generate: a container entrypoint that exports the bindings
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "generate", "entrypoint"]);
+    /// let cmd = args.subcommand_matches("generate").unwrap();
+    ///
+    /// assert!(cmd.subcommand_matches("entrypoint").is_some());
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "generate"]);
+    /// assert!(res.is_err(), "should require something to generate");
+    /// ```
+    ///
+    /// Convenience: add arguments for docker run
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert_eq!(cmd.value_source("DOCKER"), Some(clap::parser::ValueSource::CommandLine));
+    /// assert_eq!(cmd.value_source("PACK"), Some(clap::parser::ValueSource::DefaultValue));
+    /// ```
+    ///
+    /// Convenience: add arguments for pack build
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-p"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert_eq!(cmd.value_source("DOCKER"), Some(clap::parser::ValueSource::DefaultValue));
+    /// assert_eq!(cmd.value_source("PACK"), Some(clap::parser::ValueSource::CommandLine));
+    /// ```
+    ///
+    /// Convenience: add arguments for podman run
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "--podman"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert!(cmd.get_flag("PODMAN"));
+    /// assert!(!cmd.get_flag("DOCKER"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "args", "-d", "--podman"]);
+    /// assert!(res.is_err(), "should only take one type of args");
+    /// ```
+    ///
+    /// Convenience: add a docker compose service fragment
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "--compose"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert!(cmd.get_flag("COMPOSE"));
+    /// assert!(!cmd.get_flag("PACK"));
+    /// ```
+    ///
+    /// Convenience: add a dotenv file of the binding keys
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "--env-file"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert!(cmd.get_flag("ENV_FILE"));
+    /// ```
+    ///
+    /// Convenience: add BuildKit secrets for docker buildx
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "--buildkit-secret"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert!(cmd.get_flag("BUILDKIT_SECRET"));
+    /// ```
+    ///
+    /// Convenience: add the code for a Testcontainers container
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "--testcontainers", "java"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("TESTCONTAINERS").unwrap(), "java");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "args", "--testcontainers", "rust"]);
+    /// assert!(res.is_err(), "should only take java or go");
+    /// ```
+    ///
+    /// Convenience: add args for another tool from a template
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "--format", "--mount type=bind,src={root},dst={dst}"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("FORMAT").unwrap(), "--mount type=bind,src={root},dst={dst}");
+    /// ```
+    ///
+    /// Convenience: add the config of a Tilt or Skaffold dev loop
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "--format", "skaffold"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert_eq!(cmd.get_one::<String>("FORMAT").unwrap(), "skaffold");
+    /// ```
+    ///
+    /// Convenience: add Kubernetes Pod spec fragments, or the Secrets they project
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "--kubernetes", "--secrets"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert!(cmd.get_flag("KUBERNETES"));
+    /// assert!(cmd.get_flag("SECRETS"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "args", "-d", "--secrets"]);
+    /// assert!(res.is_err(), "should only generate secrets for kubernetes");
+    /// ```
+    ///
+    /// Convenience: take the args of the command into account
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--", "-v", "/a:/b", "my-app"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert_eq!(cmd.get_many::<String>("USER_ARGS").unwrap().collect::<Vec<_>>(), vec!["-v", "/a:/b", "my-app"]);
+    /// ```
+    ///
+    /// Convenience: fail when there are no bindings
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--strict"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert!(cmd.get_flag("STRICT"));
+    /// ```
+    ///
+    /// Convenience: mount the bindings read-only, or writable
+    ///
+    /// ```
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--writable"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    ///
+    /// assert!(cmd.get_flag("WRITABLE"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "args", "-d", "--read-only", "--writable"]);
+    /// assert!(res.is_err(), "should be either read-only or writable");
+    /// ```
+    ///
+    /// Convenience: mount a named volume holding the bindings
     ///
     /// ```
-    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "delete", "-f", "-n", "better_name", "-k", "foo"]);
-    /// let cmd = args.subcommand_matches("delete").unwrap();
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--volume-name", "my-bindings"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
     ///
-    /// let keys:Vec<_> = cmd.get_many::<String>("KEY").unwrap().collect();
-    /// assert_eq!(keys, vec!["foo"]);
-    /// assert_eq!(cmd.get_one::<String>("NAME").unwrap(), "better_name");
-    /// assert_eq!(cmd.contains_id("FORCE"), true);
+    /// assert_eq!(cmd.get_one::<String>("VOLUME_NAME").unwrap(), "my-bindings");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "args", "-d", "--volume-name", "my-bindings", "-n", "my-db"]);
+    /// assert!(res.is_err(), "should copy all of the bindings into the volume");
     /// ```
     ///
-    /// Convenience: add ca-certificates
+    /// Convenience: mount each binding on its own
     ///
     /// ```
-    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "ca-certs", "-f", "-n", "my-certs", "-c", "/path/to/ca.crt"]);
-    /// let cmd = args.subcommand_matches("ca-certs").unwrap();
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--per-binding"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
     ///
+    /// assert!(cmd.get_flag("PER_BINDING"));
     ///
-    /// let certs:Vec<_> = cmd.get_many::<String>("CERT").unwrap().collect();
-    /// assert_eq!(certs, vec!["/path/to/ca.crt"]);
-    /// assert_eq!(cmd.get_one::<String>("NAME").unwrap(), "my-certs");
-    /// assert_eq!(cmd.contains_id("FORCE"), true);
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "args", "-d", "--per-binding", "-n", "my-db"]);
+    /// assert!(res.is_err(), "should either mount each binding or the selected ones");
     /// ```
     ///
-    /// Convenience: add dependency-mappings
+    /// Convenience: relabel the bindings for SELinux
     ///
     /// ```
-    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-n", "my-deps", "-t", "/path/to/file.zip"]);
-    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--selinux-label", "Z"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
     ///
-    /// let files:Vec<_> = cmd.get_many::<String>("TOML").unwrap().collect();
-    /// assert_eq!(files, vec!["/path/to/file.zip"]);
-    /// assert_eq!(cmd.get_one::<String>("NAME").unwrap(), "my-deps");
+    /// assert_eq!(cmd.get_one::<String>("SELINUX_LABEL").unwrap(), "Z");
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "args", "-d", "--selinux-label", "x"]);
+    /// assert!(res.is_err(), "should only take z or Z");
     /// ```
     ///
-    /// Convenience: add dependency-mappings from buildpack
+    /// Convenience: only add some of the bindings
     ///
     /// ```
-    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "dependency-mapping", "-b", "buildpack/id-1:v1.0.1", "-b", "buildpack/id-2:v2.1.0"]);
-    /// let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--name", "my-db", "-n", "certs"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
     ///
-    /// let bps:Vec<_> = cmd.get_many::<String>("BUILDPACK").unwrap().collect();
-    /// assert_eq!(bps, vec!["buildpack/id-1:v1.0.1", "buildpack/id-2:v2.1.0"]);
+    /// assert_eq!(cmd.get_many::<String>("NAME").unwrap().collect::<Vec<_>>(), vec!["my-db", "certs"]);
     /// ```
     ///
-    /// Convenience: configure bash
+    /// Convenience: mount the bindings somewhere else in the container
     ///
     /// ```
-    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "init", "bash"]);
-    /// let cmd = args.subcommand_matches("init").unwrap();
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--container-path", "/custom/bindings"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
     ///
-    /// assert_eq!(cmd.get_one::<String>("SHELL").unwrap(), "bash");
+    /// assert_eq!(cmd.get_one::<String>("CONTAINER_PATH").unwrap(), "/custom/bindings");
     /// ```
     ///
-    /// Convenience: don't set the type of args and fails
+    /// Convenience: quote the args for PowerShell
     ///
     /// ```
-    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "init"]);
-    /// assert!(res.is_err(), "should require a argument");
-    /// ```
-    ///
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--shell", "powershell"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
     ///
-    /// Convenience: add arguments for docker run
+    /// assert_eq!(cmd.get_one::<String>("SHELL").unwrap(), "powershell");
     ///
-    /// ```
     /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d"]);
     /// let cmd = args.subcommand_matches("args").unwrap();
-    ///
-    /// assert_eq!(cmd.value_source("DOCKER"), Some(clap::parser::ValueSource::CommandLine));
-    /// assert_eq!(cmd.value_source("PACK"), Some(clap::parser::ValueSource::DefaultValue));
+    /// assert_eq!(cmd.get_one::<String>("SHELL").unwrap(), "posix");
     /// ```
     ///
-    /// Convenience: add arguments for pack build
+    /// Convenience: output the args as JSON, or NUL terminated
     ///
     /// ```
-    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-p"]);
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "--json"]);
     /// let cmd = args.subcommand_matches("args").unwrap();
+    /// assert!(cmd.get_flag("JSON"));
     ///
-    /// assert_eq!(cmd.value_source("DOCKER"), Some(clap::parser::ValueSource::DefaultValue));
-    /// assert_eq!(cmd.value_source("PACK"), Some(clap::parser::ValueSource::CommandLine));
+    /// let args = binding_tool::args::Parser::new().parse_args(vec!["bt", "args", "-d", "-z"]);
+    /// let cmd = args.subcommand_matches("args").unwrap();
+    /// assert!(cmd.get_flag("NULL"));
+    ///
+    /// let res = binding_tool::args::Parser::new().try_parse_args(vec!["bt", "args", "-d", "--json", "-z"]);
+    /// assert!(res.is_err(), "should only output one way");
     /// ```
     ///
     /// Convenience: don't set the type of args and fails
@@ -166,6 +894,11 @@ impl Parser {
         self.app.try_get_matches_from(args)
     }
 
+    /// The clap command behind the parser, for generating shell completions
+    pub(crate) fn into_command(self) -> Command {
+        self.app
+    }
+
     pub fn new() -> Parser {
         let force = Arg::new("FORCE")
             .short('f')
@@ -173,8 +906,38 @@ impl Parser {
             .action(ArgAction::SetTrue)
             .help("force update if key exists");
 
+        // `check` and `sync` look at expiry the same way
+        let warn_days = Arg::new("WARN_DAYS")
+            .long("warn-days")
+            .value_name("days")
+            .value_parser(clap::value_parser!(u32))
+            .default_value("30");
+
         Parser {
             app: command!()
+            .arg(
+                Arg::new("BINDING_ROOT")
+                    .short('r')
+                    .long("binding-root")
+                    .value_name("path")
+                    .global(true)
+                    .help("directory the bindings are kept in, overrides `SERVICE_BINDING_ROOT`"),
+            )
+            .arg(
+                Arg::new("YES")
+                    .short('y')
+                    .long("yes")
+                    .action(ArgAction::SetTrue)
+                    .global(true)
+                    .help("answers yes to every confirmation, for scripts"),
+            )
+            .arg(
+                Arg::new("PROFILE")
+                    .long("profile")
+                    .value_name("profile")
+                    .global(true)
+                    .help("profile from config.toml to use, overrides `BT_PROFILE`"),
+            )
             .subcommand(
                 Command::new("add")
                     .alias("a")
@@ -248,10 +1011,190 @@ impl Parser {
                             .short('c')
                             .long("cert")
                             .value_name("cert")
-                            .required(true)
+                            .required_unless_present_any(["CERT_DIR", "FROM_URL", "FROM_K8S", "DETECT_PROXY"])
+                            .action(ArgAction::Append)
+                            .help("path to a CA certificate to add, `*` and `?` in file names are\n\
+                                expanded"),
+                    )
+                    .arg(
+                        Arg::new("CERT_DIR")
+                            .long("cert-dir")
+                            .value_name("dir")
+                            .action(ArgAction::Append)
+                            .help("directory to add every .crt and .pem certificate from, including\n\
+                                its subdirectories"),
+                    )
+                    .arg(
+                        Arg::new("FROM_URL")
+                            .long("from-url")
+                            .value_name("url")
+                            .action(ArgAction::Append)
+                            .help("https URL of a PEM encoded CA certificate to download and add"),
+                    )
+                    .arg(
+                        Arg::new("FROM_K8S")
+                            .long("from-k8s")
+                            .value_name("resource")
                             .action(ArgAction::Append)
-                            .help("path to a CA certificate to add"),
+                            .help("`secret/<name>` or `configmap/<name>` to read CA certificates from with\n\
+                                kubectl, entries that aren't certificates are skipped"),
+                    )
+                    .arg(
+                        Arg::new("DETECT_PROXY")
+                            .long("detect-proxy")
+                            .value_name("url")
+                            .help("https URL to connect to through the configured proxy, the CA that\n\
+                                issued the certificates it presents is offered to be added"),
+                    )
+                    .arg(
+                        Arg::new("NAMESPACE")
+                            .long("namespace")
+                            .value_name("namespace")
+                            .requires("FROM_K8S")
+                            .help("namespace of the Kubernetes resources,\ndefaults to the namespace of the current context"),
+                    )
+                    .arg(
+                        Arg::new("EXPIRY_WINDOW")
+                            .long("expiry-window")
+                            .value_name("days")
+                            .value_parser(clap::value_parser!(u32))
+                            .default_value("30")
+                            .help("warn about certificates that expire within this many days"),
+                    )
+                    .arg(
+                        Arg::new("CA_ONLY")
+                            .long("ca-only")
+                            .action(ArgAction::SetTrue)
+                            .help("leave out certificates that aren't CA certificates, like the leaf\n\
+                                certificate of a server's chain"),
+                    )
+                    .arg(
+                        Arg::new("STRICT")
+                            .long("strict")
+                            .action(ArgAction::SetTrue)
+                            .help("fail instead of warning about certificates that are expired, not\n\
+                                valid yet or expire within the expiry window"),
                     )
+                    .arg(
+                        Arg::new("TRUSTSTORE")
+                            .long("truststore")
+                            .value_name("format")
+                            .value_parser(["pkcs12", "jks"])
+                            .help("also write the binding's certificates to a truststore key,\n\
+                                truststore.p12 or truststore.jks"),
+                    )
+                    .arg(
+                        Arg::new("PASSWORD_KEY")
+                            .long("password-key")
+                            .value_name("key")
+                            .default_value("truststore-password")
+                            .requires("TRUSTSTORE")
+                            .help("binding key with the truststore's password, an existing\n\
+                                password is kept and a random one is generated otherwise"),
+                    )
+                    .subcommand(
+                        Command::new("list")
+                            .arg(
+                                Arg::new("NAME")
+                                    .short('n')
+                                    .long("name")
+                                    .value_name("name")
+                                    .required(false)
+                                    .help("optional name of the binding,\nevery ca-certificates binding is listed by default"),
+                            )
+                            .about("List the certificates in ca-certificates bindings with their subject and expiry"),
+                    )
+                    .subcommand(
+                        Command::new("check")
+                            .arg(
+                                Arg::new("NAME")
+                                    .short('n')
+                                    .long("name")
+                                    .value_name("name")
+                                    .required(false)
+                                    .help("optional name of the binding,\nevery ca-certificates binding is checked by default"),
+                            )
+                            .arg(
+                                warn_days
+                                    .clone()
+                                    .help("fail when a certificate expires within this many days"),
+                            )
+                            .about("Fail if certificates in ca-certificates bindings have expired or expire soon"),
+                    )
+                    .subcommand(
+                        Command::new("remove")
+                            .arg(&force)
+                            .arg(
+                                Arg::new("NAME")
+                                    .short('n')
+                                    .long("name")
+                                    .value_name("name")
+                                    .required(false)
+                                    .help("optional name of the binding,\nevery ca-certificates binding is searched by default"),
+                            )
+                            .arg(
+                                Arg::new("FINGERPRINT")
+                                    .long("fingerprint")
+                                    .value_name("sha256")
+                                    .help("SHA-256 fingerprint of the certificate, or the start of it"),
+                            )
+                            .arg(
+                                Arg::new("CN")
+                                    .long("cn")
+                                    .value_name("common name")
+                                    .help("common name of the certificate's subject"),
+                            )
+                            .arg(
+                                Arg::new("ALL")
+                                    .long("all")
+                                    .action(ArgAction::SetTrue)
+                                    .help("remove every certificate that matches, rather than failing\nwhen there's more than one"),
+                            )
+                            .group(
+                                ArgGroup::new("MATCH")
+                                    .args(["FINGERPRINT", "CN"])
+                                    .multiple(false)
+                                    .required(true)
+                            )
+                            .about("Remove certificates from ca-certificates bindings by fingerprint or common name"),
+                    )
+                    .subcommand(
+                        Command::new("sync")
+                            .arg(&force)
+                            .arg(
+                                Arg::new("NAME")
+                                    .short('n')
+                                    .long("name")
+                                    .value_name("name")
+                                    .default_value("ca-certificates")
+                                    .help("name of the binding"),
+                            )
+                            .arg(
+                                Arg::new("CERT")
+                                    .short('c')
+                                    .long("cert")
+                                    .value_name("cert")
+                                    .required_unless_present("CERT_DIR")
+                                    .action(ArgAction::Append)
+                                    .help("path to a CA certificate the binding should hold, `*` and `?` in\n\
+                                        file names are expanded"),
+                            )
+                            .arg(
+                                Arg::new("CERT_DIR")
+                                    .long("cert-dir")
+                                    .value_name("dir")
+                                    .action(ArgAction::Append)
+                                    .help("directory with .crt and .pem certificates the binding should hold,\n\
+                                        including its subdirectories"),
+                            )
+                            .arg(
+                                warn_days
+                                    .help("warn about certificates that expire within this many days"),
+                            )
+                            .about("Make a ca-certificates binding hold exactly the given certificates, adding missing ones and removing the rest"),
+                    )
+                    .args_conflicts_with_subcommands(true)
+                    .subcommand_negates_reqs(true)
                     .about("Convenience for adding `ca-certificates` bindings")
                     .after_help(include_str!("help/additional_help_binding.txt")),
             )
@@ -273,7 +1216,7 @@ impl Parser {
                             .long("toml")
                             .value_name("toml")
                             .action(ArgAction::Append)
-                            .conflicts_with("BUILDPACK")
+                            .conflicts_with("FROM_LOCK")
                             .help("path to local buildpack.toml file with metadata dependencies"),
                     )
                     .arg(
@@ -282,10 +1225,332 @@ impl Parser {
                             .long("buildpack")
                             .value_name("buildpack")
                             .action(ArgAction::Append)
-                            .conflicts_with("TOML")
-                            .help("buildpack ID and optional version from which dependencies will be loaded\n    \
-                                Example: `buildpack/id@version` or `buildpack/id`"),
+                            .conflicts_with("FROM_LOCK")
+                            .help("buildpack ID and optional version to load dependencies from,\n\
+                                resolved through the Buildpack Registry or GitHub, or a buildpack image\n    \
+                                Example: `buildpack/id@version`, `buildpack/id@^1.2`, `buildpack/id` or `docker://registry/image:tag`"),
+                    )
+                    .arg(
+                        Arg::new("COMPONENT")
+                            .long("component")
+                            .value_name("id")
+                            .action(ArgAction::Append)
+                            .requires("BUILDPACK")
+                            .conflicts_with_all(["TOML", "URL", "FROM_LOCK"])
+                            .help("only include dependencies of this component of a composite buildpack,\n\
+                                may be repeated, defaults to every component"),
+                    )
+                    .arg(
+                        Arg::new("URL")
+                            .short('u')
+                            .long("url")
+                            .value_name("url")
+                            .action(ArgAction::Append)
+                            .conflicts_with("FROM_LOCK")
+                            .help("URL of a buildpack.toml file with metadata dependencies"),
+                    )
+                    .arg(
+                        Arg::new("PROJECT")
+                            .long("project")
+                            .value_name("project.toml")
+                            .conflicts_with_all(["TOML", "BUILDPACK", "URL", "FROM_LOCK", "COMPONENT", "OFFLINE"])
+                            .help("path to a CNB project.toml or builder.toml,\n\
+                                loads dependencies from every buildpack it uses"),
+                    )
+                    .arg(
+                        Arg::new("FROM_LOCK")
+                            .long("from-lock")
+                            .value_name("lockfile")
+                            .num_args(0..=1)
+                            .default_missing_value("bt-deps.lock")
+                            .conflicts_with_all(["TOML", "BUILDPACK", "URL"])
+                            .help("recreate the binding from the dependencies recorded in a lockfile,\n\
+                                written by `--lock`"),
+                    )
+                    .arg(
+                        Arg::new("LOCK")
+                            .long("lock")
+                            .value_name("lockfile")
+                            .num_args(0..=1)
+                            .default_missing_value("bt-deps.lock")
+                            .conflicts_with("FROM_LOCK")
+                            .help("record the buildpacks and dependencies added in a lockfile,\n\
+                                `bt-deps.lock` in the current directory by default"),
+                    )
+                    .arg(
+                        Arg::new("INCLUDE")
+                            .long("include")
+                            .value_name("pattern")
+                            .action(ArgAction::Append)
+                            .help("only add dependencies whose URI or id matches this glob pattern,\n\
+                                may be repeated\n    \
+                                Example: `*linux-amd64*` or `jdk`"),
+                    )
+                    .arg(
+                        Arg::new("EXCLUDE")
+                            .long("exclude")
+                            .value_name("pattern")
+                            .action(ArgAction::Append)
+                            .help("skip dependencies whose URI or id matches this glob pattern,\n\
+                                may be repeated"),
+                    )
+                    .arg(
+                        Arg::new("JOBS")
+                            .short('j')
+                            .long("jobs")
+                            .value_name("jobs")
+                            .value_parser(clap::value_parser!(u16).range(1..))
+                            .help("maximum number of simultaneous downloads,\noverrides `BT_MAX_SIMULTANEOUS` (default 5)"),
+                    )
+                    .arg(
+                        Arg::new("LIMIT_RATE")
+                            .long("limit-rate")
+                            .value_name("rate")
+                            .value_parser(parse_rate)
+                            .conflicts_with_all(["OFFLINE", "DRY_RUN", "NO_DOWNLOAD"])
+                            .help("maximum combined download speed in bytes per second,\n\
+                                with an optional K, M or G suffix\n    \
+                                Example: `10M` or `500K`"),
+                    )
+                    .arg(
+                        Arg::new("VERIFY_KEY")
+                            .long("verify-key")
+                            .value_name("file")
+                            .conflicts_with_all(["DRY_RUN", "NO_DOWNLOAD"])
+                            .help("check the cosign signature of every artifact with this public key,\n\
+                                signatures are read from the dependency's `signature` or `<uri>.sig`"),
+                    )
+                    .arg(
+                        Arg::new("INSECURE_SKIP_VERIFY")
+                            .long("insecure-skip-verify")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with_all(["VERIFY_KEY", "DRY_RUN", "NO_DOWNLOAD"])
+                            .help("add signed artifacts without checking their signatures"),
+                    )
+                    .arg(
+                        Arg::new("CONNECT_TIMEOUT")
+                            .long("connect-timeout")
+                            .value_name("seconds")
+                            .value_parser(clap::value_parser!(u64).range(1..))
+                            .help("timeout for connecting to a server,\noverrides `BT_CONN_TIMEOUT` (default 5)"),
+                    )
+                    .arg(
+                        Arg::new("READ_TIMEOUT")
+                            .long("read-timeout")
+                            .value_name("seconds")
+                            .value_parser(clap::value_parser!(u64).range(1..))
+                            .help("timeout for each read from a server,\noverrides `BT_READ_TIMEOUT` (default 5)"),
+                    )
+                    .arg(
+                        Arg::new("TIMEOUT")
+                            .long("timeout")
+                            .value_name("seconds")
+                            .value_parser(clap::value_parser!(u64).range(1..))
+                            .help("timeout for each whole request, including the download,\noverrides `BT_REQ_TIMEOUT` (default none)"),
+                    )
+                    .arg(
+                        Arg::new("HEADER")
+                            .short('H')
+                            .long("header")
+                            .value_name("name: value")
+                            .action(ArgAction::Append)
+                            .help("header to send with every dependency download,\nlike an `Authorization` header for an internal mirror"),
+                    )
+                    .arg(
+                        Arg::new("MIRROR")
+                            .short('m')
+                            .long("mirror")
+                            .value_name("[host=]url")
+                            .action(ArgAction::Append)
+                            .help("rewrite dependency URIs to a mirror before downloading,\noverrides `BT_MIRROR`\n    \
+                                Example: `https://nexus.corp/remote/` or `github.com=https://nexus.corp/{originalHost}`"),
                     )
+                    .arg(
+                        Arg::new("NO_CACHE")
+                            .long("no-cache")
+                            .action(ArgAction::SetTrue)
+                            .help("download directly into the binding,\nbypassing the shared download cache"),
+                    )
+                    .arg(
+                        Arg::new("OFFLINE")
+                            .long("offline")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with_all(["NO_CACHE", "BUILDPACK", "URL"])
+                            .help("don't use the network, artifacts must already be in the download cache"),
+                    )
+                    .arg(
+                        Arg::new("DRY_RUN")
+                            .long("dry-run")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with("OFFLINE")
+                            .help("list what would be downloaded and how big it is,\nwithout downloading or changing the binding"),
+                    )
+                    .arg(
+                        Arg::new("REFRESH")
+                            .long("refresh")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with("DRY_RUN")
+                            .help("re-verify the binaries already in the binding and re-download\n\
+                                any that are missing or don't match their checksum, the URIs come\n\
+                                from `bt-deps.lock` unless a buildpack.toml is given"),
+                    )
+                    .arg(
+                        Arg::new("UPDATE")
+                            .long("update")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with("REFRESH")
+                            .help("sync the binding with the buildpacks, only adding the mappings of\n\
+                                new or changed dependencies and listing the ones no buildpack has anymore"),
+                    )
+                    .arg(
+                        Arg::new("REMOVE_RETIRED")
+                            .long("remove-retired")
+                            .action(ArgAction::SetTrue)
+                            .requires("UPDATE")
+                            .conflicts_with("DRY_RUN")
+                            .help("with --update, remove the mappings and binaries of dependencies\n\
+                                no buildpack has anymore"),
+                    )
+                    .arg(
+                        Arg::new("NO_DOWNLOAD")
+                            .long("no-download")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with_all(["OFFLINE", "DRY_RUN", "REFRESH"])
+                            .help("only write the mappings, pointing at the dependency URIs\n\
+                                (or their mirrors) instead of downloaded binaries"),
+                    )
+                    .arg(
+                        Arg::new("URI_PREFIX")
+                            .long("uri-prefix")
+                            .value_name("uri")
+                            .conflicts_with("NO_DOWNLOAD")
+                            .help("base URI the binaries will be served from, the mappings point\n\
+                                at it instead of `file:///bindings/<name>/binaries/`"),
+                    )
+                    .arg(
+                        Arg::new("SERVE")
+                            .long("serve")
+                            .value_name("addr")
+                            .conflicts_with_all(["DRY_RUN", "NO_DOWNLOAD", "REFRESH"])
+                            .help("serve the binaries of the binding over HTTP, like `:8080`, and\n\
+                                point the mappings at the server. Without a buildpack.toml, only\n\
+                                serves the binaries already in the binding"),
+                    )
+                    .arg(
+                        Arg::new("KEEP_LATEST")
+                            .long("keep-latest")
+                            .value_name("n")
+                            .value_parser(clap::value_parser!(u16).range(1..))
+                            .conflicts_with_all(["DRY_RUN", "REFRESH"])
+                            .help("keep only the newest n versions of each dependency in the binding,\n\
+                                removing the mappings and binaries of older versions"),
+                    )
+                    .arg(
+                        Arg::new("BUNDLE")
+                            .long("bundle")
+                            .value_name("file")
+                            .conflicts_with_all(["DRY_RUN", "NO_DOWNLOAD", "REFRESH"])
+                            .help("write the binding and its binaries to a gzipped tarball that\n\
+                                can be imported on a disconnected network with `import`"),
+                    )
+                    .arg(
+                        Arg::new("SBOM")
+                            .long("sbom")
+                            .value_name("file")
+                            .conflicts_with_all(["DRY_RUN", "NO_DOWNLOAD", "REFRESH"])
+                            .help("write a CycloneDX SBOM of the binaries in the binding to file,\n\
+                                or to stdout with `-`"),
+                    )
+                    .arg(
+                        Arg::new("REPORT")
+                            .long("report")
+                            .value_name("file")
+                            .conflicts_with_all(["DRY_RUN", "NO_DOWNLOAD"])
+                            .help("write a JSON report of every download to a file, `-` for stdout,\n\
+                                with its status, size, duration and any error"),
+                    )
+                    .arg(
+                        Arg::new("LIST_VERSIONS")
+                            .long("list-versions")
+                            .value_name("buildpack")
+                            .exclusive(true)
+                            .help("list the released versions of a buildpack, newest first"),
+                    )
+                    .arg(
+                        Arg::new("QUIET")
+                            .short('q')
+                            .long("quiet")
+                            .action(ArgAction::SetTrue)
+                            .help("don't display download progress"),
+                    )
+                    .subcommand(
+                        Command::new("purge")
+                            .arg(
+                                Arg::new("NAME")
+                                    .short('n')
+                                    .long("name")
+                                    .value_name("name")
+                                    .required(false)
+                                    .help("optional name for the binding,\nname defaults to the type"),
+                            )
+                            .about("Remove binaries no mapping refers to and mappings whose binaries are gone"),
+                    )
+                    .subcommand(
+                        Command::new("push")
+                            .arg(
+                                Arg::new("NAME")
+                                    .short('n')
+                                    .long("name")
+                                    .value_name("name")
+                                    .required(false)
+                                    .help("optional name for the binding,\nname defaults to the type"),
+                            )
+                            .arg(
+                                Arg::new("IMAGE")
+                                    .value_name("image")
+                                    .required(true)
+                                    .help("where to push the binding, like `ghcr.io/org/deps:tag`"),
+                            )
+                            .about("Push the binding and its binaries to an OCI registry as an artifact"),
+                    )
+                    .subcommand(
+                        Command::new("pull")
+                            .arg(&force)
+                            .arg(
+                                Arg::new("NAME")
+                                    .short('n')
+                                    .long("name")
+                                    .value_name("name")
+                                    .required(false)
+                                    .help("optional name for the binding,\nname defaults to the type"),
+                            )
+                            .arg(
+                                Arg::new("IMAGE")
+                                    .value_name("image")
+                                    .required(true)
+                                    .help("the binding to pull, like `ghcr.io/org/deps:tag`"),
+                            )
+                            .about("Pull a binding pushed with `push` and verify its binaries"),
+                    )
+                    .subcommand(
+                        Command::new("import")
+                            .arg(
+                                Arg::new("NAME")
+                                    .short('n')
+                                    .long("name")
+                                    .value_name("name")
+                                    .required(false)
+                                    .help("optional name for the binding,\nname defaults to the name it was bundled with"),
+                            )
+                            .arg(
+                                Arg::new("BUNDLE")
+                                    .value_name("bundle")
+                                    .required(true)
+                                    .help("a bundle written with `--bundle`"),
+                            )
+                            .about("Unpack a bundle written with `--bundle` and verify its binaries"),
+                    )
+                    .args_conflicts_with_subcommands(true)
                     .about("Convenience for adding `dependency-mapping` bindings")
                     .after_help(include_str!("help/additional_help_binding.txt")),
             )
@@ -294,13 +1559,64 @@ impl Parser {
                     .arg(
                         Arg::new("SHELL")
                             .value_name("shell")
-                            .required(true)
-                            .value_parser(["bash", "fish", "zsh"])
-                            .help("type of shell script to generate"))
+                            .value_parser(["bash", "fish", "zsh", "powershell", "nu", "direnv"])
+                            .help("type of shell script to generate, defaults to the shell in $SHELL, or `direnv` for an .envrc snippet"))
+                    .arg(
+                        Arg::new("PREFIX")
+                            .long("prefix")
+                            .value_name("prefix")
+                            .help("names the wrappers `<prefix>docker`, `<prefix>pack` and `<prefix>podman` rather than shadowing the real commands"),
+                    )
+                    .arg(
+                        Arg::new("NO_SHADOW")
+                            .long("no-shadow")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with("PREFIX")
+                            .help("same as `--prefix bt-`"),
+                    )
+                    .arg(
+                        Arg::new("WATCH")
+                            .long("watch")
+                            .action(ArgAction::SetTrue)
+                            .help("with direnv, reloads the environment when the bindings change"),
+                    )
                     .about(
                         "Generates shell wrappers that make using `pack build` and `docker run` easier",
                     ),
             )
+            .subcommand(
+                Command::new("inject")
+                    .arg(
+                        Arg::new("CONTAINER")
+                            .value_name("container")
+                            .required(true)
+                            .help("name or id of the running container"),
+                    )
+                    .arg(
+                        Arg::new("CONTAINER_PATH")
+                            .long("container-path")
+                            .value_name("path")
+                            .help("where the bindings are copied in the container, defaults to\n\
+                                $BT_CONTAINER_PATH or /bindings"),
+                    )
+                    .arg(
+                        Arg::new("PODMAN")
+                            .long("podman")
+                            .action(ArgAction::SetTrue)
+                            .help("copies the bindings with podman rather than docker"),
+                    )
+                    .about("Copies the bindings into a running container"),
+            )
+            .subcommand(
+                Command::new("generate")
+                    .subcommand_required(true)
+                    .subcommand(
+                        Command::new("entrypoint")
+                            .about("Generates a POSIX script for images that exports the bindings as\n\
+                                <TYPE>_<KEY> environment variables and runs the app"),
+                    )
+                    .about("Generates files that consume bindings where there's no native support"),
+            )
             .subcommand(
                 Command::new("args")
                     .arg(
@@ -317,14 +1633,202 @@ impl Parser {
                             .action(ArgAction::SetTrue)
                             .help("generates binding args for `pack build`"),
                     )
+                    .arg(
+                        Arg::new("PODMAN")
+                            .long("podman")
+                            .action(ArgAction::SetTrue)
+                            .help("generates binding args for `podman run`, relabeling the\n\
+                                bindings for SELinux"),
+                    )
+                    .arg(
+                        Arg::new("COMPOSE")
+                            .long("compose")
+                            .action(ArgAction::SetTrue)
+                            .help("generates the `volumes` and `environment` of a docker compose\n\
+                                service"),
+                    )
+                    .arg(
+                        Arg::new("ENV_FILE")
+                            .long("env-file")
+                            .action(ArgAction::SetTrue)
+                            .help("generates a dotenv file with a `<BINDING>_<KEY>` variable for each\n\
+                                key of the bindings, for `--env-file`"),
+                    )
+                    .arg(
+                        Arg::new("BUILDKIT_SECRET")
+                            .long("buildkit-secret")
+                            .action(ArgAction::SetTrue)
+                            .help("generates a BuildKit secret for each key of the bindings, for\n\
+                                `docker buildx build`"),
+                    )
+                    .arg(
+                        Arg::new("TESTCONTAINERS")
+                            .long("testcontainers")
+                            .value_name("language")
+                            .value_parser(["java", "go"])
+                            .help("generates the code that mounts the bindings in a Testcontainers\n\
+                                container"),
+                    )
+                    .arg(
+                        Arg::new("FORMAT")
+                            .long("format")
+                            .value_name("template")
+                            .allow_hyphen_values(true)
+                            .help("generates args from a template for any other tool, `{root}` is\n\
+                                replaced by the binding root, `{dst}` by where it's mounted in the\n\
+                                container and `{name}` by the name of the binding, rendering the\n\
+                                template for each binding. `tilt` and `skaffold` generate the config\n\
+                                that applies the Secrets of the bindings in those dev loops"),
+                    )
+                    .arg(
+                        Arg::new("KUBERNETES")
+                            .long("kubernetes")
+                            .action(ArgAction::SetTrue)
+                            .help("generates the `volumes`, `volumeMounts` and `env` of a Pod spec\n\
+                                that project the bindings from Secrets"),
+                    )
+                    .arg(
+                        Arg::new("SECRETS")
+                            .long("secrets")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with_all([
+                                "DOCKER",
+                                "PACK",
+                                "PODMAN",
+                                "COMPOSE",
+                                "ENV_FILE",
+                                "BUILDKIT_SECRET",
+                                "TESTCONTAINERS",
+                                "FORMAT",
+                            ])
+                            .help("generates the Secrets holding the bindings instead, to apply with\n\
+                                `kubectl apply -f -`"),
+                    )
+                    .arg(
+                        Arg::new("STRICT")
+                            .long("strict")
+                            .action(ArgAction::SetTrue)
+                            .help("fails when there are no bindings, rather than warning"),
+                    )
+                    .arg(
+                        Arg::new("USER_ARGS")
+                            .value_name("args")
+                            .num_args(0..)
+                            .last(true)
+                            .allow_hyphen_values(true)
+                            .help("args of the command the bindings are for, the bindings aren't\n\
+                                mounted again where these mount something, and they're mounted\n\
+                                where these set SERVICE_BINDING_ROOT"),
+                    )
+                    .arg(
+                        Arg::new("READ_ONLY")
+                            .long("read-only")
+                            .action(ArgAction::SetTrue)
+                            .help("mounts the bindings read-only, the default"),
+                    )
+                    .arg(
+                        Arg::new("WRITABLE")
+                            .long("writable")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with("READ_ONLY")
+                            .help("mounts the bindings so the container may change them"),
+                    )
+                    .arg(
+                        Arg::new("SELINUX_LABEL")
+                            .long("selinux-label")
+                            .value_name("label")
+                            .value_parser(["z", "Z"])
+                            .help("relabels the bindings for SELinux, `z` to share them between\n\
+                                containers or `Z` for this container only, defaults to `z` for\n\
+                                podman"),
+                    )
+                    .arg(
+                        Arg::new("VOLUME_NAME")
+                            .long("volume-name")
+                            .value_name("volume")
+                            .conflicts_with_all([
+                                "NAME",
+                                "PER_BINDING",
+                                "ENV_FILE",
+                                "BUILDKIT_SECRET",
+                                "KUBERNETES",
+                            ])
+                            .help("copies the bindings into this named volume and mounts it rather\n\
+                                than the binding root, for daemons that can't see the paths of this\n\
+                                host"),
+                    )
+                    .arg(
+                        Arg::new("PER_BINDING")
+                            .long("per-binding")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with("NAME")
+                            .help("mounts each binding on its own rather than the whole binding\n\
+                                root, leaving out anything else in it"),
+                    )
+                    .arg(
+                        Arg::new("NAME")
+                            .short('n')
+                            .long("name")
+                            .value_name("name")
+                            .action(ArgAction::Append)
+                            .help("only the bindings with this name, each mounted on its own,\n\
+                                may be repeated, defaults to all of the bindings"),
+                    )
+                    .arg(
+                        Arg::new("JSON")
+                            .long("json")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with_all(["COMPOSE", "ENV_FILE", "TESTCONTAINERS", "KUBERNETES"])
+                            .help("outputs the args as a JSON array instead of quoting them"),
+                    )
+                    .arg(
+                        Arg::new("NULL")
+                            .short('z')
+                            .long("null")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with_all([
+                                "JSON",
+                                "COMPOSE",
+                                "ENV_FILE",
+                                "TESTCONTAINERS",
+                                "KUBERNETES",
+                            ])
+                            .help("outputs each arg terminated by a NUL instead of quoting them"),
+                    )
+                    .arg(
+                        Arg::new("SHELL")
+                            .long("shell")
+                            .value_name("shell")
+                            .value_parser(["posix", "powershell"])
+                            .default_value("posix")
+                            .help("shell to quote the args for"),
+                    )
+                    .arg(
+                        Arg::new("CONTAINER_PATH")
+                            .long("container-path")
+                            .value_name("path")
+                            .help("where the bindings are mounted in the container, defaults to\n\
+                                $BT_CONTAINER_PATH, or /platform/bindings for pack and /bindings for\n\
+                                docker, podman, compose and kubernetes"),
+                    )
                     .group(
                         ArgGroup::new("TYPES")
-                            .args(["DOCKER", "PACK"])
+                            .args([
+                                "DOCKER",
+                                "PACK",
+                                "PODMAN",
+                                "COMPOSE",
+                                "ENV_FILE",
+                                "BUILDKIT_SECRET",
+                                "TESTCONTAINERS",
+                                "FORMAT",
+                                "KUBERNETES",
+                            ])
                             .multiple(false)
                             .required(true)
                     )
                     .about(
-                        "Convenience that generates binding args for `pack build` and `docker run`",
+                        "Convenience that generates binding args for `pack build`, `docker run` and `podman run`",
                     )
                     .after_help(include_str!("help/additional_help_binding.txt")),
             )
@@ -337,3 +1841,22 @@ impl Default for Parser {
         Self::new()
     }
 }
+
+/// Parses a rate like `500K` or `10M`, suffixes are powers of 1024
+fn parse_rate(input: &str) -> Result<u64, String> {
+    let (number, multiplier) = match input.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&input[..i], 1024),
+        Some((i, 'm' | 'M')) => (&input[..i], 1024 * 1024),
+        Some((i, 'g' | 'G')) => (&input[..i], 1024 * 1024 * 1024),
+        _ => (input, 1),
+    };
+
+    match number.parse::<u64>() {
+        Ok(n) if n > 0 => n
+            .checked_mul(multiplier)
+            .ok_or_else(|| format!("{input} is too large")),
+        _ => Err(format!(
+            "{input} should be a positive number of bytes, like `500K` or `10M`"
+        )),
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{Context, Result};
+use sha2::Digest;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use std::{fs, path};
+use toml::Value as Toml;
+
+use crate::deps::hash_file;
+
+/// Index of the artifacts that have been checked against their sha256, in the cache directory
+pub(super) const INDEX: &str = "verified.toml";
+
+/// Size and modification time of an artifact when it was verified
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Stamp {
+    size: u64,
+    /// nanoseconds since the epoch
+    modified: i64,
+}
+
+impl Stamp {
+    fn of(path: &path::Path) -> Result<Stamp> {
+        let meta = fs::metadata(path).with_context(|| format!("cannot read {path:?}"))?;
+        let modified = meta.modified()?.duration_since(UNIX_EPOCH)?.as_nanos();
+        Ok(Stamp {
+            size: meta.len(),
+            modified: i64::try_from(modified)?,
+        })
+    }
+}
+
+/// The artifacts of the download cache, stored by sha256, along with an index of how they
+/// looked when they were last verified. An artifact that changed since then is hashed again
+/// before it's used, and removed if it no longer matches.
+#[derive(Debug)]
+pub(super) struct ArtifactCache {
+    dir: path::PathBuf,
+    index: path::PathBuf,
+    verified: Mutex<HashMap<String, Stamp>>,
+}
+
+impl ArtifactCache {
+    /// Opens the artifacts under `cache_dir`, an unreadable index is treated as empty
+    pub(super) fn open(cache_dir: &path::Path) -> ArtifactCache {
+        let index = cache_dir.join(INDEX);
+        let verified = fs::read_to_string(&index)
+            .ok()
+            .and_then(|s| s.parse::<Toml>().ok())
+            .and_then(|t| t.as_table().cloned())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(sha256, entry)| {
+                let int = |key: &str| entry.get(key).and_then(|v| v.as_integer());
+                Some((
+                    sha256.clone(),
+                    Stamp {
+                        size: u64::try_from(int("size")?).ok()?,
+                        modified: int("modified")?,
+                    },
+                ))
+            })
+            .collect();
+
+        ArtifactCache {
+            dir: cache_dir.join("artifacts"),
+            index,
+            verified: Mutex::new(verified),
+        }
+    }
+
+    pub(super) fn dir(&self) -> &path::Path {
+        &self.dir
+    }
+
+    /// Where the artifact with `sha256` is kept
+    pub(super) fn path(&self, sha256: &str) -> path::PathBuf {
+        self.dir.join(sha256)
+    }
+
+    /// Returns true if the artifact is cached and matches `sha256`. It's only hashed when it
+    /// isn't in the index or has changed since it was verified, a changed artifact that doesn't
+    /// match anymore is removed so it's downloaded again.
+    pub(super) fn check(&self, sha256: &str) -> Result<bool> {
+        let path = self.path(sha256);
+        if !path.is_file() {
+            self.forget(sha256);
+            return Ok(false);
+        }
+
+        let stamp = Stamp::of(&path)?;
+        if self.verified.lock().unwrap().get(sha256) == Some(&stamp) {
+            return Ok(true);
+        }
+
+        if hex::encode(hash_file(&path)?.finalize()) == sha256 {
+            self.verified
+                .lock()
+                .unwrap()
+                .insert(sha256.to_owned(), stamp);
+            return Ok(true);
+        }
+
+        self.forget(sha256);
+        fs::remove_file(&path).with_context(|| format!("cannot remove {path:?}"))?;
+        Ok(false)
+    }
+
+    /// Records that the artifact with `sha256` was just verified
+    pub(super) fn record(&self, sha256: &str) -> Result<()> {
+        let stamp = Stamp::of(&self.path(sha256))?;
+        self.verified
+            .lock()
+            .unwrap()
+            .insert(sha256.to_owned(), stamp);
+        Ok(())
+    }
+
+    fn forget(&self, sha256: &str) {
+        self.verified.lock().unwrap().remove(sha256);
+    }
+
+    /// Writes the index, replacing the old one in a single step so it's never half written
+    pub(super) fn save(&self) -> Result<()> {
+        let mut table = toml::value::Table::new();
+        for (sha256, stamp) in self.verified.lock().unwrap().iter() {
+            let mut entry = toml::value::Table::new();
+            entry.insert("size".into(), Toml::Integer(i64::try_from(stamp.size)?));
+            entry.insert("modified".into(), Toml::Integer(stamp.modified));
+            table.insert(sha256.clone(), Toml::Table(entry));
+        }
+
+        let mut part = self.index.as_os_str().to_owned();
+        part.push(".part");
+        fs::write(&part, toml::to_string(&Toml::Table(table))?)
+            .with_context(|| format!("cannot write {part:?}"))?;
+        fs::rename(&part, &self.index).with_context(|| format!("cannot write {:?}", self.index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArtifactCache, INDEX};
+    use std::fs;
+
+    const HELLO: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn verified_artifacts_round_trip() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let artifacts = cache_dir.path().join("artifacts");
+        fs::create_dir(&artifacts).unwrap();
+        fs::write(artifacts.join(HELLO), "hello").unwrap();
+
+        let cache = ArtifactCache::open(cache_dir.path());
+        assert!(!cache.check(&"0".repeat(64)).unwrap());
+        assert!(cache.check(HELLO).unwrap());
+        cache.save().unwrap();
+
+        let index = fs::read_to_string(cache_dir.path().join(INDEX)).unwrap();
+        assert!(index.contains(HELLO));
+        assert!(ArtifactCache::open(cache_dir.path()).check(HELLO).unwrap());
+    }
+
+    #[test]
+    fn changed_artifacts_are_verified_again() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let artifacts = cache_dir.path().join("artifacts");
+        fs::create_dir(&artifacts).unwrap();
+        fs::write(artifacts.join(HELLO), "hello").unwrap();
+
+        let cache = ArtifactCache::open(cache_dir.path());
+        assert!(cache.check(HELLO).unwrap());
+
+        fs::write(artifacts.join(HELLO), "poisoned").unwrap();
+        assert!(!cache.check(HELLO).unwrap());
+        assert!(!artifacts.join(HELLO).exists());
+
+        cache.save().unwrap();
+        let index = fs::read_to_string(cache_dir.path().join(INDEX)).unwrap();
+        assert!(!index.contains(HELLO));
+    }
+}
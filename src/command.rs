@@ -12,15 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
 use std::io::{prelude::*, stdin, Stdout};
-use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{env, fs, path, str};
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use clap::parser::ValueSource;
 use clap::ArgMatches;
+use clap_complete::{generate, Shell};
+use clap_complete_nushell::Nushell;
+use indicatif::HumanBytes;
 
-use crate::{args, deps};
+use crate::{args, certs, config, deps, docker_config, kube, lock, serve};
 
 pub struct BT {}
 
@@ -31,29 +34,40 @@ impl BT {
         let executed_command = matches.subcommand_name().unwrap_or("help");
         let args = matches.subcommand_matches(executed_command);
 
-        match Command::from_str(executed_command) {
+        // config.toml is read once, with the profile from `--profile` or `BT_PROFILE`
+        let profile = args
+            .and_then(|a| a.get_one::<String>("PROFILE").cloned())
+            .or_else(|| env::var("BT_PROFILE").ok().filter(|p| !p.is_empty()));
+        let config = config::load(profile.as_deref())?;
+
+        match Command::new(executed_command, config) {
             Ok(Command::Add(mut handler)) => handler.handle(args),
             Ok(Command::Args(mut handler)) => handler.handle(args),
             Ok(Command::CaCerts(mut handler)) => handler.handle(args),
             Ok(Command::Delete(mut handler)) => handler.handle(args),
             Ok(Command::DependencyMapping(mut handler)) => handler.handle(args),
+            Ok(Command::Generate(mut handler)) => handler.handle(args),
             Ok(Command::Init(mut handler)) => handler.handle(args),
+            Ok(Command::Inject(mut handler)) => handler.handle(args),
             Err(err) => Err(err),
         }
     }
 }
 
-fn service_binding_root() -> String {
-    // binding root = SERVICE_BINDING_ROOT (or default to "./bindings")
-    match env::var("SERVICE_BINDING_ROOT") {
-        Ok(root) => root,
-        Err(_) => env::current_dir()
-            .unwrap()
-            .join("bindings")
-            .to_str()
-            .unwrap()
-            .into(),
+fn service_binding_root(args: &ArgMatches, config: &config::Config) -> Result<String> {
+    // binding root = --binding-root, SERVICE_BINDING_ROOT, binding-root from config.toml (or
+    // default to "./bindings")
+    if let Some(root) = args.get_one::<String>("BINDING_ROOT") {
+        return Ok(env::current_dir()?.join(root).to_str().unwrap().into());
+    }
+    if let Ok(root) = env::var("SERVICE_BINDING_ROOT") {
+        return Ok(root);
     }
+    let root = config
+        .binding_root
+        .clone()
+        .unwrap_or_else(|| String::from("bindings"));
+    Ok(env::current_dir()?.join(root).to_str().unwrap().into())
 }
 
 trait BindingConfirmer {
@@ -67,6 +81,32 @@ enum BindingConfirmers {
 }
 
 impl BindingConfirmers {
+    /// Questions that would be asked on the console are answered with yes when `--yes` is passed
+    fn assume_yes(self, args: &ArgMatches) -> BindingConfirmers {
+        match self {
+            BindingConfirmers::Console if args.get_flag("YES") => BindingConfirmers::Always,
+            confirmer => confirmer,
+        }
+    }
+
+    /// How keys that already exist are handled, `--force` overwrites them and otherwise
+    /// `on-conflict` in config.toml decides, falling back to the command's own default
+    fn for_conflicts(
+        force: bool,
+        default: BindingConfirmers,
+        config: &config::Config,
+    ) -> BindingConfirmers {
+        if force {
+            return BindingConfirmers::Always;
+        }
+        match config.on_conflict {
+            Some(config::Conflict::Ask) => BindingConfirmers::Console,
+            Some(config::Conflict::Overwrite) => BindingConfirmers::Always,
+            Some(config::Conflict::Fail) => BindingConfirmers::Never,
+            None => default,
+        }
+    }
+
     fn confirm(&self, msg: &str) -> bool {
         match self {
             BindingConfirmers::Always => AlwaysBindingConfirmer {}.confirm(msg),
@@ -289,35 +329,51 @@ trait CommandHandler {
 enum Command {
     Add(AddCommandHandler),
     Args(ArgsCommandHandler<Stdout>),
-    CaCerts(CaCertsCommandHandler),
+    CaCerts(CaCertsCommandHandler<Stdout>),
     Delete(DeleteCommandHandler),
-    DependencyMapping(DependencyMappingCommandHandler),
+    DependencyMapping(DependencyMappingCommandHandler<Stdout>),
+    Generate(GenerateCommandHandler<Stdout>),
     Init(InitCommandHandler<Stdout>),
+    Inject(InjectCommandHandler<Stdout>),
 }
 
-impl str::FromStr for Command {
-    type Err = anyhow::Error;
-
-    fn from_str(input: &str) -> Result<Command, Self::Err> {
+impl Command {
+    fn new(input: &str, config: config::Config) -> Result<Command> {
         match input {
-            "add" => Ok(Command::Add(AddCommandHandler {})),
-            "delete" => Ok(Command::Delete(DeleteCommandHandler {})),
-            "ca-certs" => Ok(Command::CaCerts(CaCertsCommandHandler {})),
+            "add" => Ok(Command::Add(AddCommandHandler { config })),
+            "delete" => Ok(Command::Delete(DeleteCommandHandler { config })),
+            "ca-certs" => Ok(Command::CaCerts(CaCertsCommandHandler {
+                output: std::io::stdout(),
+                config,
+            })),
             "dependency-mapping" => Ok(Command::DependencyMapping(
-                DependencyMappingCommandHandler {},
+                DependencyMappingCommandHandler {
+                    output: std::io::stdout(),
+                    config,
+                },
             )),
             "args" => Ok(Command::Args(ArgsCommandHandler {
                 output: std::io::stdout(),
+                config,
+            })),
+            "generate" => Ok(Command::Generate(GenerateCommandHandler {
+                output: std::io::stdout(),
             })),
             "init" => Ok(Command::Init(InitCommandHandler {
                 output: std::io::stdout(),
             })),
+            "inject" => Ok(Command::Inject(InjectCommandHandler {
+                output: std::io::stdout(),
+                config,
+            })),
             _ => bail!("could not part argument"),
         }
     }
 }
 
-struct AddCommandHandler {}
+struct AddCommandHandler {
+    config: config::Config,
+}
 
 impl CommandHandler for AddCommandHandler {
     fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
@@ -332,13 +388,15 @@ impl CommandHandler for AddCommandHandler {
 
         let binding_type = args.get_one::<String>("TYPE").map(|s| s.as_str());
         let binding_name = args.get_one::<String>("NAME").map(|s| s.as_str());
-        let bindings_home = service_binding_root();
+        let bindings_home = service_binding_root(args, &self.config)?;
 
-        let confirmer = if args.contains_id("FORCE") {
-            BindingConfirmers::Always
-        } else {
-            BindingConfirmers::Console
-        };
+        // existing keys have always been replaced here, unless on-conflict says otherwise
+        let confirmer = BindingConfirmers::for_conflicts(
+            args.get_flag("FORCE"),
+            BindingConfirmers::Always,
+            &self.config,
+        )
+        .assume_yes(args);
 
         // process bindings
         let btp = BindingProcessor::new(&bindings_home, binding_type, binding_name, confirmer);
@@ -346,7 +404,9 @@ impl CommandHandler for AddCommandHandler {
     }
 }
 
-struct DeleteCommandHandler {}
+struct DeleteCommandHandler {
+    config: config::Config,
+}
 
 impl CommandHandler for DeleteCommandHandler {
     fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
@@ -361,39 +421,62 @@ impl CommandHandler for DeleteCommandHandler {
         let binding_key_vals = args.get_many::<String>("KEY").unwrap_or_default();
 
         // binding root = SERVICE_BINDING_ROOT (or default to "./bindings")
-        let bindings_home = service_binding_root();
-
-        let confirmer = if args.contains_id("FORCE") {
-            BindingConfirmers::Never
-        } else {
-            BindingConfirmers::Console
-        };
+        let bindings_home = service_binding_root(args, &self.config)?;
 
         // process bindings
-        let btp = BindingProcessor::new(&bindings_home, None, binding_name, confirmer);
+        let btp = BindingProcessor::new(&bindings_home, None, binding_name, self.confirmer(args));
         btp.delete_bindings(binding_key_vals.into_iter().map(|s| s.as_str()))
     }
 }
 
-struct CaCertsCommandHandler {}
+impl DeleteCommandHandler {
+    /// `--force` deletes without asking, otherwise `on-conflict` in config.toml decides and
+    /// falls back to asking on the console, which `--yes` answers
+    fn confirmer(&self, args: &ArgMatches) -> BindingConfirmers {
+        BindingConfirmers::for_conflicts(
+            args.get_flag("FORCE"),
+            BindingConfirmers::Console,
+            &self.config,
+        )
+        .assume_yes(args)
+    }
+}
+
+struct CaCertsCommandHandler<T> {
+    output: T,
+    config: config::Config,
+}
 
-impl CommandHandler for CaCertsCommandHandler {
+impl<T> CommandHandler for CaCertsCommandHandler<T>
+where
+    T: Write,
+{
     fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
         ensure!(args.is_some(), "missing required args");
         let args = args.unwrap();
 
-        let bindings_home = service_binding_root();
+        if let Some(list) = args.subcommand_matches("list") {
+            return self.list(list);
+        }
+        if let Some(remove) = args.subcommand_matches("remove") {
+            return self.remove(remove);
+        }
+        if let Some(check) = args.subcommand_matches("check") {
+            return self.check(check);
+        }
+        if let Some(sync) = args.subcommand_matches("sync") {
+            return self.sync(sync);
+        }
+
+        let bindings_home = service_binding_root(args, &self.config)?;
         let binding_name = args
             .get_one::<String>("NAME")
             .map(|s| s.as_str())
             .unwrap_or("ca-certificates");
-        let certs = args.get_many::<String>("CERT");
-
-        let confirmer = if args.contains_id("FORCE") {
-            BindingConfirmers::Always
-        } else {
-            BindingConfirmers::Console
-        };
+        let force = args.get_flag("FORCE");
+        let confirmer =
+            BindingConfirmers::for_conflicts(force, BindingConfirmers::Console, &self.config)
+                .assume_yes(args);
 
         // process bindings
         let btp = BindingProcessor::new(
@@ -403,585 +486,4363 @@ impl CommandHandler for CaCertsCommandHandler {
             confirmer,
         );
 
-        let cert_args: Vec<String> = certs
-            .unwrap()
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let window = args.get_one::<u32>("EXPIRY_WINDOW").copied().unwrap_or(30);
+        let mut warnings = vec![];
+
+        let mut cert_args = file_cert_args(args, now, window, &mut warnings)?;
+
+        // downloaded certificates are named after the last segment of their URL
+        for (i, uri) in args
+            .get_many::<String>("FROM_URL")
+            .unwrap_or_default()
             .enumerate()
-            .map(|(i, c)| match path::Path::new(c).file_name() {
-                Some(file_name) => format!("{}=@{}", file_name.to_string_lossy(), c),
-                None => format!("cert-{i}=@{c}"),
-            })
-            .collect();
+        {
+            let pem = deps::fetch_certificate(uri, path::Path::new(&bindings_home))?;
+            certs::validate(uri, pem.as_bytes())?;
+            warnings.extend(certs::validity_warnings(uri, pem.as_bytes(), now, window));
+            let name = url::Url::parse(uri)?
+                .path_segments()
+                .and_then(|mut s| s.next_back().map(|s| s.to_owned()))
+                .filter(|s| !s.is_empty() && !s.contains('='))
+                .unwrap_or_else(|| format!("cert-url-{i}"));
+            if certs::count(&pem) > 1 {
+                cert_args.extend(bundle_keys(&pem)?);
+            } else {
+                cert_args.push((format!("{name}={pem}"), pem.into_bytes()));
+            }
+        }
 
-        btp.add_bindings(cert_args.iter().map(|s| &s[..]))
-    }
-}
+        // entries of a Secret or ConfigMap keep their keys, those that aren't certificates, like
+        // the `tls.key` of a TLS secret, are skipped
+        let namespace = args.get_one::<String>("NAMESPACE").map(|n| n.as_str());
+        for resource in args.get_many::<String>("FROM_K8S").unwrap_or_default() {
+            let mut found = 0;
+            for (key, contents) in kube::resource_data(resource, namespace)? {
+                let name = format!("{resource}:{key}");
+                if certs::validate(&name, &contents).is_err() || key.contains('=') {
+                    continue;
+                }
+                found += 1;
+                warnings.extend(certs::validity_warnings(&name, &contents, now, window));
 
-struct DependencyMappingCommandHandler {}
+                let pem = certs::to_pem(&contents);
+                if certs::count(&pem) > 1 {
+                    cert_args.extend(bundle_keys(&pem)?);
+                } else {
+                    cert_args.push((format!("{key}={pem}"), pem.into_bytes()));
+                }
+            }
+            ensure!(found > 0, "{resource} has no certificates");
+        }
 
-impl CommandHandler for DependencyMappingCommandHandler {
-    fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
-        // TODO: add support for id & version filters
-        ensure!(args.is_some(), "missing required args");
-        let args = args.unwrap();
+        // the CA of a proxy intercepting TLS is only added once it's been looked at
+        if let Some(uri) = args.get_one::<String>("DETECT_PROXY") {
+            let chain = deps::peer_chain(uri)?;
+            writeln!(self.output, "{uri} presented:")?;
+            for cert in &chain {
+                for line in certs::describe(cert.as_ref()) {
+                    writeln!(self.output, "  {line}")?;
+                }
+            }
 
-        let buildpack = args.get_one::<String>("BUILDPACK");
-        let toml_file = args.get_one::<String>("TOML");
+            // a chain a public CA vouches for isn't intercepted, there's no proxy CA to add
+            let host = url::Url::parse(uri)
+                .ok()
+                .and_then(|u| u.host_str().map(|h| h.to_owned()))
+                .unwrap_or_default();
+            if certs::publicly_trusted(&chain, &host) {
+                writeln!(
+                    self.output,
+                    "{uri} is issued by a public CA, the connection isn't intercepted"
+                )?;
+            } else {
+                let ca =
+                    certs::issuing_ca(&chain, &rustls_native_certs::load_native_certs().certs)?;
+                let pem = certs::to_pem(ca.as_ref());
+                let subject = certs::describe(pem.as_bytes()).concat();
+                let confirmer = if force {
+                    BindingConfirmers::Always
+                } else {
+                    BindingConfirmers::Console.assume_yes(args)
+                };
+                ensure!(
+                    confirmer.confirm(&format!("Add the issuing CA {subject}?")),
+                    "the issuing CA was not added"
+                );
+                warnings.extend(certs::validity_warnings(uri, pem.as_bytes(), now, window));
+                cert_args.extend(bundle_keys(&pem)?);
+            }
+        }
 
-        let bindings_home = service_binding_root();
-        let binding_name = args
-            .get_one::<String>("NAME")
-            .map(|s| s.as_str())
-            .unwrap_or("dependency-mapping");
-        let confirmer = if args.contains_id("FORCE") {
-            BindingConfirmers::Always
-        } else {
-            BindingConfirmers::Console
-        };
+        // with --strict, nothing is added when any certificate would be warned about
+        if args.get_flag("STRICT") {
+            ensure!(
+                warnings.is_empty(),
+                "not adding certificates that are expired or about to expire:\n{}",
+                warnings
+                    .iter()
+                    .map(|w| format!("  {w}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+        for warning in &warnings {
+            eprintln!("Warning: {warning}");
+        }
 
-        // process bindings
-        let btp = BindingProcessor::new(
-            &bindings_home,
-            Some("dependency-mapping"),
-            Some(binding_name),
-            confirmer,
-        );
+        // leaf certificates are only any use to the server they were issued for
+        let ca_only = args.get_flag("CA_ONLY");
+        cert_args.retain(|(arg, contents)| {
+            let leaves = certs::leaf_certificates(contents);
+            if leaves.is_empty() {
+                return true;
+            }
+            let key = arg.split_once('=').map(|(k, _)| k).unwrap_or(arg);
+            if ca_only {
+                eprintln!(
+                    "Warning: excluded {key}, {} is not a CA certificate",
+                    leaves.join(", ")
+                );
+            } else {
+                eprintln!(
+                    "Warning: {key} is not a CA certificate ({}), pass --ca-only to leave it out",
+                    leaves.join(", ")
+                );
+            }
+            !ca_only
+        });
 
-        let deps = if let Some(buildpack) = buildpack {
-            deps::parse_buildpack_toml_from_network(buildpack)
-        } else if let Some(toml_file) = toml_file {
-            deps::parse_buildpack_toml_from_disk(path::Path::new(toml_file))
-        } else {
-            Err(anyhow!("must have a buildpack.toml file"))
-        }?;
+        // certificates already in the binding are skipped, whatever key they're under
+        let mut known =
+            certs::binding_fingerprints(&path::Path::new(&bindings_home).join(binding_name))?;
+        for (arg, contents) in &cert_args {
+            let key = arg.split_once('=').map(|(k, _)| k).unwrap_or(arg);
+            let fingerprints = certs::fingerprints(contents);
+            if let Some(existing) = fingerprints
+                .iter()
+                .map(|f| known.get(f))
+                .collect::<Option<Vec<_>>>()
+                .and_then(|keys| keys.first().cloned())
+            {
+                writeln!(
+                    self.output,
+                    "Skipped {key}, it's a duplicate of {existing} already in the binding"
+                )?;
+                continue;
+            }
 
-        let binding_path = path::Path::new(&bindings_home).join(binding_name);
-        fs::create_dir_all(binding_path.join("binaries"))?;
-        deps::download_dependencies(deps.clone(), binding_path)?;
+            for fingerprint in fingerprints {
+                known.entry(fingerprint).or_insert_with(|| key.to_owned());
+            }
 
-        let deps_args: Vec<String> = deps
-            .iter()
-            .filter_map(|d| {
-                if let Ok(filename) = d.filename() {
-                    Some(format!(
-                        "{}=file:///bindings/{}/binaries/{}",
-                        d.sha256, binding_name, filename
-                    ))
-                } else {
-                    None
+            // what's being replaced is shown before asking, not just the key
+            let existing = path::Path::new(&bindings_home).join(binding_name).join(key);
+            if existing.is_file() && !force {
+                writeln!(self.output, "{key} is already in the binding with:")?;
+                for line in certs::describe(&fs::read(&existing)?) {
+                    writeln!(self.output, "  {line}")?;
                 }
-            })
-            .collect();
-        btp.add_bindings(deps_args.iter().map(|s| &s[..]))
-    }
-}
+                writeln!(self.output, "and would be replaced with:")?;
+                for line in certs::describe(contents) {
+                    writeln!(self.output, "  {line}")?;
+                }
+            }
+            btp.add_binding(arg)?;
+        }
 
-struct ArgsCommandHandler<T> {
-    output: T,
+        // the truststore is rebuilt from the whole binding, so it's replaced without asking
+        if let Some(format) = args.get_one::<String>("TRUSTSTORE") {
+            let binding_path = path::Path::new(&bindings_home).join(binding_name);
+            let password_key = args
+                .get_one::<String>("PASSWORD_KEY")
+                .map(|s| s.as_str())
+                .unwrap_or("truststore-password");
+            let password_path = binding_path.join(password_key);
+            let password = match fs::read_to_string(&password_path) {
+                Ok(password) => password.trim_end().to_owned(),
+                Err(_) => certs::truststore_password()?,
+            };
+
+            let (store, count) = certs::truststore(&binding_path, format, &password)?;
+            let key = if format == "jks" {
+                "truststore.jks"
+            } else {
+                "truststore.p12"
+            };
+            fs::write(binding_path.join(key), store)
+                .with_context(|| format!("cannot write {key}"))?;
+            fs::write(&password_path, &password)
+                .with_context(|| format!("cannot write {password_key}"))?;
+            writeln!(
+                self.output,
+                "Wrote {key} with {count} certificates, its password is in {password_key}"
+            )?;
+        }
+        Ok(())
+    }
 }
 
-impl<T> CommandHandler for ArgsCommandHandler<T>
+impl<T> CaCertsCommandHandler<T>
 where
     T: Write,
 {
-    fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
-        ensure!(args.is_some(), "missing required args");
-        let args = args.unwrap();
-
-        // binding root = SERVICE_BINDING_ROOT (or default to "./bindings")
-        let bindings_root = service_binding_root();
-        let bindings_home = path::Path::new(&bindings_root);
-
-        if !bindings_home.exists() {
-            return Ok(());
-        }
-
-        let binding_count = bindings_home
-            .read_dir()?
-            .filter_map(|res| res.ok())
-            .filter(|entry| entry.path().is_dir() && entry.path().join("type").exists())
-            .count();
-        if binding_count == 0 {
+    fn list(&mut self, args: &ArgMatches) -> Result<()> {
+        let name = args.get_one::<String>("NAME").map(|s| s.as_str());
+        let infos = certs::list(
+            path::Path::new(&service_binding_root(args, &self.config)?),
+            name,
+        )?;
+        if infos.is_empty() {
+            writeln!(self.output, "No certificates found")?;
             return Ok(());
         }
 
-        match (args.value_source("DOCKER"), args.value_source("PACK")) {
-            (Some(ValueSource::DefaultValue), Some(ValueSource::CommandLine)) => write!(
-                self.output,
-                r#"--volume {bindings_root}:/bindings --env SERVICE_BINDING_ROOT=/bindings"#
-            )?,
-            (Some(ValueSource::CommandLine), Some(ValueSource::DefaultValue)) => write!(
-                self.output,
-                r#"--volume {bindings_root}:/bindings --env SERVICE_BINDING_ROOT=/bindings"#
-            )?,
-            // should never happen
-            _ => bail!("cannot have both docker and pack flags"),
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let rows = infos
+            .iter()
+            .map(|c| {
+                let days = (c.not_after - now).div_euclid(86_400);
+                [
+                    format!("{}/{}", c.binding, c.key),
+                    c.subject.clone(),
+                    c.issuer.clone(),
+                    c.fingerprint[..16].to_owned(),
+                    if days < 0 {
+                        String::from("expired")
+                    } else {
+                        days.to_string()
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let header = [
+            "KEY",
+            "SUBJECT",
+            "ISSUER",
+            "FINGERPRINT",
+            "EXPIRES IN (DAYS)",
+        ];
+        let widths = (0..header.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|r| r[i].chars().count())
+                    .chain(std::iter::once(header[i].len()))
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        let line = |cells: Vec<&str>| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(c, w)| format!("{c:<w$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_owned()
         };
 
+        writeln!(self.output, "{}", line(header.to_vec()))?;
+        for row in &rows {
+            writeln!(
+                self.output,
+                "{}",
+                line(row.iter().map(|c| c.as_str()).collect())
+            )?;
+        }
         Ok(())
     }
-}
-
-struct InitCommandHandler<T> {
-    output: T,
-}
-
-impl<T> CommandHandler for InitCommandHandler<T>
-where
-    T: Write,
-{
-    fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
-        ensure!(args.is_some(), "missing required args");
-        let args = args.unwrap();
 
-        let shell = args.get_one::<String>("SHELL").map(|s| s.as_str()).unwrap(); // required, should not fail
+    /// Lists the certificates that have expired or expire within `--warn-days`, failing if
+    /// there are any so a scheduled job notices
+    fn check(&mut self, args: &ArgMatches) -> Result<()> {
+        let name = args.get_one::<String>("NAME").map(|s| s.as_str());
+        let warn_days = args.get_one::<u32>("WARN_DAYS").copied().unwrap_or(30);
+        let infos = certs::list(
+            path::Path::new(&service_binding_root(args, &self.config)?),
+            name,
+        )?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let mut expiring = 0;
+        for c in &infos {
+            let days = (c.not_after - now).div_euclid(86_400);
+            if days >= i64::from(warn_days) {
+                continue;
+            }
+            expiring += 1;
+            if days < 0 {
+                writeln!(
+                    self.output,
+                    "{}/{}: {} has expired",
+                    c.binding, c.key, c.subject
+                )?;
+            } else {
+                writeln!(
+                    self.output,
+                    "{}/{}: {} expires in {days} days",
+                    c.binding, c.key, c.subject
+                )?;
+            }
+        }
 
+        ensure!(
+            expiring == 0,
+            "{expiring} of {} certificates have expired or expire within {warn_days} days",
+            infos.len()
+        );
         writeln!(
             self.output,
-            "{}",
-            match shell {
-                "fish" => include_str!("scripts/fish.sh"),
-                "bash" => include_str!("scripts/bash.sh"),
-                "zsh" => include_str!("scripts/zsh.sh"),
-                _ => bail!("unsupported shell {}", shell),
-            }
-        )
-        .map_err(|e| anyhow!(e))
+            "All {} certificates are valid for at least {warn_days} more days",
+            infos.len()
+        )?;
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use serial_test::serial;
-    use std::str::Utf8Error;
+    /// Makes a binding hold exactly the certificates given, adding the missing ones and removing
+    /// the rest. Keys that aren't certificates, like a truststore, are left alone.
+    fn sync(&mut self, args: &ArgMatches) -> Result<()> {
+        let bindings_home = service_binding_root(args, &self.config)?;
+        let binding_root = path::Path::new(&bindings_home);
+        let binding_name = args
+            .get_one::<String>("NAME")
+            .map(|s| s.as_str())
+            .unwrap_or("ca-certificates");
+        let force = args.get_flag("FORCE");
+        let warn_days = args.get_one::<u32>("WARN_DAYS").copied().unwrap_or(30);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let mut warnings = vec![];
+        let cert_args = file_cert_args(args, now, warn_days, &mut warnings)?;
+        for warning in &warnings {
+            eprintln!("Warning: {warning}");
+        }
 
-    use super::*;
+        let wanted = cert_args
+            .iter()
+            .flat_map(|(_, contents)| certs::fingerprints(contents))
+            .collect::<HashSet<_>>();
+        let stale = certs::list(binding_root, Some(binding_name))?
+            .into_iter()
+            .filter(|c| !wanted.contains(&c.fingerprint))
+            .collect::<Vec<_>>();
+
+        if !stale.is_empty() {
+            for c in &stale {
+                writeln!(self.output, "Removing {}: {}", c.key, c.subject)?;
+            }
+            let confirmer = if force {
+                BindingConfirmers::Always
+            } else {
+                BindingConfirmers::Console.assume_yes(args)
+            };
+            ensure!(
+                confirmer.confirm(&format!(
+                    "Remove {} certificates that weren't given?",
+                    stale.len()
+                )),
+                "{binding_name} was not synced"
+            );
+            certs::remove(binding_root, &stale)?;
+        }
 
-    struct TestBuffer {
-        buffer: Vec<u8>,
-    }
+        // keys that were only partly stale were rewritten above, so what's left is looked up now
+        let btp = BindingProcessor::new(
+            &bindings_home,
+            Some("ca-certificates"),
+            Some(binding_name),
+            BindingConfirmers::for_conflicts(force, BindingConfirmers::Console, &self.config)
+                .assume_yes(args),
+        );
+        let mut known = certs::binding_fingerprints(&binding_root.join(binding_name))?;
+        let mut added = 0;
+        for (arg, contents) in &cert_args {
+            let fingerprints = certs::fingerprints(contents);
+            if fingerprints.iter().all(|f| known.contains_key(f)) {
+                continue;
+            }
 
-    impl Write for TestBuffer {
-        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-            self.buffer.write(buf)
+            let key = arg.split_once('=').map(|(k, _)| k).unwrap_or(arg);
+            btp.add_binding(arg)?;
+            writeln!(self.output, "Added {key}")?;
+            for fingerprint in fingerprints {
+                known.entry(fingerprint).or_insert_with(|| key.to_owned());
+            }
+            added += 1;
         }
 
-        fn flush(&mut self) -> std::io::Result<()> {
-            self.buffer.flush()
+        if added == 0 && stale.is_empty() {
+            writeln!(self.output, "{binding_name} is already in sync")?;
+        } else {
+            writeln!(
+                self.output,
+                "Synced {binding_name}, added {added} keys and removed {} certificates",
+                stale.len()
+            )?;
         }
+        Ok(())
     }
 
-    impl TestBuffer {
-        fn new() -> TestBuffer {
-            TestBuffer { buffer: vec![] }
+    fn remove(&mut self, args: &ArgMatches) -> Result<()> {
+        let binding_root = service_binding_root(args, &self.config)?;
+        let binding_root = path::Path::new(&binding_root);
+        let name = args.get_one::<String>("NAME").map(|s| s.as_str());
+        // fingerprints are matched however they're written, `2A:9F:..` or `2a9f..`
+        let fingerprint = args
+            .get_one::<String>("FINGERPRINT")
+            .map(|f| f.replace(':', "").to_lowercase());
+        if let Some(fingerprint) = &fingerprint {
+            ensure!(
+                fingerprint.len() >= MIN_FINGERPRINT
+                    && fingerprint.chars().all(|c| c.is_ascii_hexdigit()),
+                "the fingerprint should be at least {MIN_FINGERPRINT} hex digits"
+            );
         }
+        let cn = args.get_one::<String>("CN");
+
+        let matched = certs::list(binding_root, name)?
+            .into_iter()
+            .filter(|c| match (&fingerprint, cn) {
+                (Some(fingerprint), _) => c.fingerprint.starts_with(fingerprint.as_str()),
+                (None, Some(cn)) => c
+                    .common_name
+                    .as_ref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(cn)),
+                (None, None) => false,
+            })
+            .collect::<Vec<_>>();
+        ensure!(
+            !matched.is_empty(),
+            "no certificate in the ca-certificates bindings matches"
+        );
+        ensure!(
+            matched.len() == 1 || args.get_flag("ALL"),
+            "{} certificates match, pass --all to remove all of them",
+            matched.len()
+        );
 
-        fn writer(&mut self) -> &mut impl Write {
-            &mut self.buffer
+        for c in &matched {
+            writeln!(
+                self.output,
+                "{}/{}: {} ({})",
+                c.binding,
+                c.key,
+                c.subject,
+                &c.fingerprint[..16]
+            )?;
         }
+        let confirmer = if args.get_flag("FORCE") {
+            BindingConfirmers::Always
+        } else {
+            BindingConfirmers::Console.assume_yes(args)
+        };
+        ensure!(
+            confirmer.confirm(&format!("Remove {} certificates?", matched.len())),
+            "certificates were not removed"
+        );
 
-        fn string(&self) -> Result<&str, Utf8Error> {
-            str::from_utf8(&self.buffer)
+        certs::remove(binding_root, &matched)?;
+        writeln!(self.output, "Removed {} certificates", matched.len())?;
+        Ok(())
+    }
+}
+
+/// Hex digits of a fingerprint needed to remove a certificate by it, fewer could match any
+const MIN_FINGERPRINT: usize = 8;
+
+/// Binding keys for the certificate files given with -c and --cert-dir, along with their
+/// contents. Bundles are split into a key per certificate, other files are copied as they are.
+fn file_cert_args(
+    args: &ArgMatches,
+    now: i64,
+    window: u32,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut cert_args: Vec<(String, Vec<u8>)> = vec![];
+    // patterns are expanded here too, for scripts that quote them and shells that don't
+    let files = args
+        .get_many::<String>("CERT")
+        .unwrap_or_default()
+        .map(|c| certs::expand_glob(c))
+        .collect::<Result<Vec<_>>>()?
+        .concat();
+    for (i, c) in files.iter().enumerate() {
+        let contents = fs::read(c).with_context(|| format!("cannot read certificate {c}"))?;
+        certs::validate(c, &contents)?;
+        warnings.extend(certs::validity_warnings(c, &contents, now, window));
+        if let Some(bundle) = str::from_utf8(&contents)
+            .ok()
+            .filter(|p| certs::count(p) > 1)
+        {
+            cert_args.extend(bundle_keys(bundle)?);
+            continue;
         }
+        let key = match path::Path::new(c).file_name() {
+            Some(file_name) => unique_key(&cert_args, &file_name.to_string_lossy(), c),
+            None => format!("cert-{i}"),
+        };
+        cert_args.push(cert_file_arg(&key, c, contents));
     }
 
-    #[test]
-    #[serial(requires_cwd)]
-    fn given_no_bindings_root_set_it_returns_current_working_directory() {
-        temp_env::with_var_unset("SERVICE_BINDING_ROOT", || {
-            let root = super::service_binding_root();
-            assert!(root.starts_with(env::current_dir().unwrap().to_str().unwrap()));
-        });
+    // files in a directory that aren't certificates are skipped, and ones whose names clash
+    // are named after their path in the directory
+    for dir in args.get_many::<String>("CERT_DIR").unwrap_or_default() {
+        let dir_path = path::Path::new(dir);
+        let mut found = 0;
+        for relative in certs::certificate_files(dir_path)? {
+            let file = dir_path.join(&relative).to_string_lossy().into_owned();
+            let contents =
+                fs::read(&file).with_context(|| format!("cannot read certificate {file}"))?;
+            if let Err(e) = certs::validate(&file, &contents) {
+                eprintln!("Warning: {e}, skipping it");
+                continue;
+            }
+            found += 1;
+            warnings.extend(certs::validity_warnings(&file, &contents, now, window));
+            if let Some(bundle) = str::from_utf8(&contents)
+                .ok()
+                .filter(|p| certs::count(p) > 1)
+            {
+                cert_args.extend(bundle_keys(bundle)?);
+                continue;
+            }
+
+            let file_name = relative.rsplit('/').next().unwrap_or(&relative);
+            let key = unique_key(&cert_args, file_name, &relative);
+            cert_args.push(cert_file_arg(&key, &file, contents));
+        }
+        ensure!(found > 0, "{dir} has no certificates");
     }
+    Ok(cert_args)
+}
 
-    #[test]
-    fn given_bindings_root_set_it_returns_bindings_root_dir() {
-        temp_env::with_var("SERVICE_BINDING_ROOT", Some("/bindings"), || {
-            let root = super::service_binding_root();
-            assert!(root.starts_with("/bindings"));
-        });
+/// The `key=value` arg that adds the certificate `file`. DER encoded certificates are converted,
+/// the bindings only hold PEM.
+fn cert_file_arg(key: &str, file: &str, contents: Vec<u8>) -> (String, Vec<u8>) {
+    if String::from_utf8_lossy(&contents).contains("-----BEGIN ") {
+        (format!("{key}=@{file}"), contents)
+    } else {
+        let pem = certs::to_pem(&contents);
+        (format!("{key}={pem}"), pem.into_bytes())
     }
+}
 
-    #[test]
-    fn given_binding_args_it_creates_binding() {
-        let tmpdir = tempfile::tempdir().unwrap();
-        let tmppath = tmpdir.path().to_string_lossy();
+/// `file_name` as a binding key, or `path` with its separators replaced when another file of the
+/// same name has already taken that key
+fn unique_key(cert_args: &[(String, Vec<u8>)], file_name: &str, path: &str) -> String {
+    let taken = cert_args
+        .iter()
+        .any(|(arg, _)| arg.split_once('=').map(|(k, _)| k) == Some(file_name));
+    if taken {
+        path.trim_start_matches("./")
+            .trim_start_matches('/')
+            .replace('/', "-")
+    } else {
+        file_name.to_owned()
+    }
+}
 
-        let bp = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
-        let res = bp.add_binding("key=val");
+/// Binding keys for each certificate in a PEM bundle, along with the certificate
+fn bundle_keys(bundle: &str) -> Result<Vec<(String, Vec<u8>)>> {
+    Ok(certs::split_bundle(bundle)?
+        .into_iter()
+        .map(|(name, pem)| (format!("{name}={pem}"), pem.into_bytes()))
+        .collect())
+}
 
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("testType/type").exists());
-        assert!(tmpdir.path().join("testType/key").exists());
+struct DependencyMappingCommandHandler<T> {
+    output: T,
+    config: config::Config,
+}
 
-        let data = fs::read(tmpdir.path().join("testType/type"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"testType");
+impl<T> CommandHandler for DependencyMappingCommandHandler<T>
+where
+    T: Write,
+{
+    fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
+        // TODO: add support for id & version filters
+        ensure!(args.is_some(), "missing required args");
+        let args = args.unwrap();
 
-        let data = fs::read(tmpdir.path().join("testType/key"));
-        assert!(data.is_ok());
+        if let Some(purge) = args.subcommand_matches("purge") {
+            return self.purge(purge);
+        }
+        if let Some(push) = args.subcommand_matches("push") {
+            return self.push(push);
+        }
+        if let Some(pull) = args.subcommand_matches("pull") {
+            return self.pull(pull);
+        }
+        if let Some(import) = args.subcommand_matches("import") {
+            return self.import(import);
+        }
+
+        let timeouts = deps::Timeouts {
+            connect: args.get_one::<u64>("CONNECT_TIMEOUT").copied(),
+            read: args.get_one::<u64>("READ_TIMEOUT").copied(),
+            request: args.get_one::<u64>("TIMEOUT").copied(),
+        };
+
+        if let Some(id) = args.get_one::<String>("LIST_VERSIONS") {
+            let binding_root = service_binding_root(args, &self.config)?;
+            for version in deps::list_versions(id, path::Path::new(&binding_root), timeouts)? {
+                writeln!(self.output, "{version}")?;
+            }
+            return Ok(());
+        }
+
+        let many = |id: &str| -> Vec<&String> { args.get_many(id).unwrap_or_default().collect() };
+        let buildpack_refs = many("BUILDPACK");
+        let toml_files = many("TOML");
+        let toml_urls = many("URL");
+        let project = args.get_one::<String>("PROJECT");
+        let has_source = !buildpack_refs.is_empty()
+            || !toml_files.is_empty()
+            || !toml_urls.is_empty()
+            || project.is_some();
+        let refresh = args.get_flag("REFRESH");
+        // a refresh without a buildpack.toml repairs from the lockfile
+        let from_lock = args
+            .get_one::<String>("FROM_LOCK")
+            .map(|l| l.as_str())
+            .or_else(|| (refresh && !has_source).then_some(lock::LOCKFILE));
+
+        let bindings_home = service_binding_root(args, &self.config)?;
+        let binding_name = args
+            .get_one::<String>("NAME")
+            .map(|s| s.as_str())
+            .unwrap_or("dependency-mapping");
+        // existing keys have always been replaced here, unless on-conflict says otherwise
+        let confirmer = BindingConfirmers::for_conflicts(
+            args.get_flag("FORCE"),
+            BindingConfirmers::Always,
+            &self.config,
+        )
+        .assume_yes(args);
+
+        // process bindings
+        let btp = BindingProcessor::new(
+            &bindings_home,
+            Some("dependency-mapping"),
+            Some(binding_name),
+            confirmer,
+        );
+
+        let binding_path = path::Path::new(&bindings_home).join(binding_name);
+
+        // listen first, so a busy port fails before anything is downloaded
+        let server = args
+            .get_one::<String>("SERVE")
+            .map(|addr| serve::Server::bind(addr))
+            .transpose()?;
+        let keep_latest = args.get_one::<u16>("KEEP_LATEST").map(|n| *n as usize);
+        let bundle = args.get_one::<String>("BUNDLE");
+        let sbom = args.get_one::<String>("SBOM");
+
+        // without a buildpack.toml, only the binding that's already there is pruned, described,
+        // bundled or served
+        if !has_source
+            && from_lock.is_none()
+            && (keep_latest.is_some() || sbom.is_some() || bundle.is_some() || server.is_some())
+        {
+            if let Some(keep) = keep_latest {
+                self.keep_latest(&binding_path, keep)?;
+            }
+            if let Some(sbom) = sbom {
+                self.write_json(&deps::sbom(&binding_path)?, sbom, "SBOM")?;
+            }
+            if let Some(bundle) = bundle {
+                self.bundle(&binding_path, bundle)?;
+            }
+            return match &server {
+                Some(server) => self.serve(server, &binding_path),
+                None => Ok(()),
+            };
+        }
+
+        let cache_dir = if args.get_flag("NO_CACHE") {
+            None
+        } else {
+            deps::cache_dir()
+        };
+
+        let mut buildpacks = if let Some(lockfile) = from_lock {
+            ensure!(
+                path::Path::new(lockfile).exists(),
+                "lockfile {lockfile} does not exist"
+            );
+            let deps = lock::Lockfile::load(path::Path::new(lockfile))?.dependencies(binding_name);
+            ensure!(
+                !deps.is_empty(),
+                "no dependencies for binding {binding_name} in {lockfile}"
+            );
+            Ok(vec![deps::Buildpack {
+                dependencies: deps,
+                ..Default::default()
+            }])
+        } else if let Some(project) = project {
+            deps::parse_project_toml(
+                path::Path::new(project),
+                cache_dir.as_deref(),
+                path::Path::new(&bindings_home),
+                timeouts,
+            )
+        } else {
+            // every source goes into the same binding, dependencies they share are merged
+            let components = many("COMPONENT").into_iter().cloned().collect::<Vec<_>>();
+            ensure!(
+                components.is_empty() || buildpack_refs.len() == 1,
+                "components can only be picked from a single buildpack"
+            );
+
+            let mut buildpacks = vec![];
+            for buildpack in &buildpack_refs {
+                buildpacks.push(deps::parse_buildpack_toml_from_network(
+                    buildpack,
+                    &components,
+                    cache_dir.as_deref(),
+                    path::Path::new(&bindings_home),
+                    timeouts,
+                )?);
+            }
+            for toml_file in &toml_files {
+                buildpacks.push(deps::parse_buildpack_toml_from_disk(path::Path::new(
+                    toml_file,
+                ))?);
+            }
+            for toml_url in &toml_urls {
+                buildpacks.push(deps::parse_buildpack_toml_from_url(
+                    toml_url,
+                    cache_dir.as_deref(),
+                    path::Path::new(&bindings_home),
+                    timeouts,
+                )?);
+            }
+            ensure!(!buildpacks.is_empty(), "must have a buildpack.toml file");
+            Ok(buildpacks)
+        }?;
+
+        let filter = deps::DependencyFilter {
+            include: many("INCLUDE").into_iter().cloned().collect(),
+            exclude: many("EXCLUDE").into_iter().cloned().collect(),
+        };
+        // dependencies the patterns leave out are still in the buildpacks, they aren't retired
+        let current = deps::merge_dependencies(&buildpacks);
+        filter.apply(&mut buildpacks)?;
+        let mut deps = deps::merge_dependencies(&buildpacks);
+        deps::assign_filenames(&mut deps, &binding_path)?;
+
+        // an update only adds what the binding doesn't have yet
+        let update = if args.get_flag("UPDATE") {
+            let update = deps::compare_binding(&binding_path, &deps, &current)?;
+            deps = update.added.clone();
+            Some(update)
+        } else {
+            None
+        };
+        let deps = &deps;
+
+        let mirrors = args
+            .get_many::<String>("MIRROR")
+            .unwrap_or_default()
+            .map(|m| m.parse())
+            .collect::<Result<Vec<deps::Mirror>>>()?;
+        let headers = args
+            .get_many::<String>("HEADER")
+            .unwrap_or_default()
+            .map(|h| h.parse())
+            .collect::<Result<Vec<deps::Header>>>()?;
+
+        let opts = deps::DownloadOptions {
+            jobs: args.get_one::<u16>("JOBS").map(|j| *j as usize),
+            quiet: args.get_flag("QUIET"),
+            cache_dir,
+            mirrors,
+            headers,
+            offline: args.get_flag("OFFLINE"),
+            limit_rate: args.get_one::<u64>("LIMIT_RATE").copied(),
+            verify_key: args
+                .get_one::<String>("VERIFY_KEY")
+                .map(path::PathBuf::from),
+            skip_verify: args.get_flag("INSECURE_SKIP_VERIFY"),
+            config: self.config.clone(),
+            binding_root: path::PathBuf::from(&bindings_home),
+            timeouts,
+        };
+
+        if refresh {
+            return self.refresh(deps, binding_path, &opts, args.get_one::<String>("REPORT"));
+        }
+
+        if args.get_flag("DRY_RUN") {
+            let planned = deps::plan_downloads(deps, &binding_path, &opts)?;
+            self.print_plan(&planned)?;
+            return match &update {
+                Some(update) => self.print_update(update, false),
+                None => Ok(()),
+            };
+        }
+
+        let uri_prefix = match args.get_one::<String>("URI_PREFIX") {
+            Some(prefix) => {
+                url::Url::parse(prefix).with_context(|| format!("invalid URI prefix {prefix}"))?;
+                format!("{}/", prefix.trim_end_matches('/'))
+            }
+            None => match &server {
+                Some(server) => server.uri_prefix.clone(),
+                None => format!("file:///bindings/{binding_name}/binaries/"),
+            },
+        };
+        let deps_args: Vec<String> = if args.get_flag("NO_DOWNLOAD") {
+            // the mappings point straight at where the dependencies are hosted
+            deps.iter()
+                .map(|d| Ok(format!("{}={}", d.sha256, opts.mirrored_uri(&d.uri)?)))
+                .collect::<Result<_>>()?
+        } else if deps.is_empty() {
+            vec![]
+        } else {
+            fs::create_dir_all(binding_path.join("binaries"))?;
+            let report = deps::download_dependencies(deps.clone(), binding_path.clone(), &opts)?;
+            self.write_report(&report, args.get_one::<String>("REPORT"))?;
+            report.check()?;
+
+            deps.iter()
+                .filter_map(|d| {
+                    if let Ok(filename) = d.filename() {
+                        Some(format!("{}={}{}", d.sha256, uri_prefix, filename))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+        btp.add_bindings(deps_args.iter().map(|s| &s[..]))?;
+
+        // the manifest grows with the binding, so it's merged rather than confirmed like a key
+        let manifest_path = binding_path.join(deps::MANIFEST_KEY);
+        let existing = if manifest_path.exists() {
+            Some(fs::read_to_string(&manifest_path)?)
+        } else {
+            None
+        };
+        let manifest = deps::update_manifest(existing.as_deref(), deps)?;
+        BindingWriter::new(
+            &binding_path,
+            "dependency-mapping",
+            deps::MANIFEST_KEY,
+            &manifest,
+        )
+        .write()?;
+
+        if let Some(update) = &update {
+            let remove = args.get_flag("REMOVE_RETIRED");
+            if remove {
+                deps::remove_retired(&binding_path, &update.retired)?;
+            }
+            self.print_update(update, remove)?;
+        }
+        if let Some(keep) = keep_latest {
+            self.keep_latest(&binding_path, keep)?;
+        }
+
+        // a lockfile is only written when asked for, and it isn't rewritten from itself
+        if let Some(lockfile) = args
+            .get_one::<String>("LOCK")
+            .filter(|_| from_lock.is_none())
+        {
+            let lockfile = path::Path::new(lockfile);
+            let mut lock = lock::Lockfile::load(lockfile)?;
+            for bp in &buildpacks {
+                lock.record(binding_name, bp);
+            }
+            lock.save(lockfile)?;
+        }
+
+        if let Some(sbom) = sbom {
+            self.write_json(&deps::sbom(&binding_path)?, sbom, "SBOM")?;
+        }
+        if let Some(bundle) = bundle {
+            self.bundle(&binding_path, bundle)?;
+        }
+
+        match &server {
+            Some(server) => self.serve(server, &binding_path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T> DependencyMappingCommandHandler<T>
+where
+    T: Write,
+{
+    fn keep_latest(&mut self, binding_path: &path::Path, keep: usize) -> Result<()> {
+        for d in deps::keep_latest(binding_path, keep)? {
+            writeln!(
+                self.output,
+                "Removed {} {} ({})",
+                d.id.unwrap_or_default(),
+                d.version.unwrap_or_default(),
+                d.sha256
+            )?;
+        }
+        Ok(())
+    }
+
+    fn purge(&mut self, args: &ArgMatches) -> Result<()> {
+        let binding_name = args
+            .get_one::<String>("NAME")
+            .map(|s| s.as_str())
+            .unwrap_or("dependency-mapping");
+        let binding_path =
+            path::Path::new(&service_binding_root(args, &self.config)?).join(binding_name);
+        ensure!(
+            binding_path.is_dir(),
+            "binding {} does not exist",
+            binding_path.display()
+        );
+
+        let purged = deps::purge_binding(&binding_path)?;
+        for (path, size) in &purged.binaries {
+            writeln!(
+                self.output,
+                "Removed {} ({})",
+                path.display(),
+                HumanBytes(*size)
+            )?;
+        }
+        for sha256 in &purged.keys {
+            writeln!(self.output, "Removed mapping {sha256}, its binary is gone")?;
+        }
+        writeln!(
+            self.output,
+            "Reclaimed {}",
+            HumanBytes(purged.binaries.iter().map(|(_, size)| size).sum())
+        )?;
+        Ok(())
+    }
+
+    fn push(&mut self, args: &ArgMatches) -> Result<()> {
+        let binding_name = args
+            .get_one::<String>("NAME")
+            .map(|s| s.as_str())
+            .unwrap_or("dependency-mapping");
+        let binding_root = service_binding_root(args, &self.config)?;
+        let binding_root = path::Path::new(&binding_root);
+        let binding_path = binding_root.join(binding_name);
+        ensure!(
+            binding_path.is_dir(),
+            "binding {} does not exist",
+            binding_path.display()
+        );
+
+        let image = args
+            .get_one::<String>("IMAGE")
+            .ok_or_else(|| anyhow!("missing image"))?;
+        let digest = deps::push_binding(&binding_path, image, binding_root)?;
+        writeln!(
+            self.output,
+            "Pushed {} to {image}@{digest}",
+            binding_path.display()
+        )?;
+        Ok(())
+    }
+
+    fn pull(&mut self, args: &ArgMatches) -> Result<()> {
+        let binding_name = args
+            .get_one::<String>("NAME")
+            .map(|s| s.as_str())
+            .unwrap_or("dependency-mapping");
+        let binding_root = service_binding_root(args, &self.config)?;
+        let binding_root = path::Path::new(&binding_root);
+        let binding_path = binding_root.join(binding_name);
+
+        let image = args
+            .get_one::<String>("IMAGE")
+            .ok_or_else(|| anyhow!("missing image"))?;
+        let confirmer = BindingConfirmers::for_conflicts(
+            args.get_flag("FORCE"),
+            BindingConfirmers::Console,
+            &self.config,
+        )
+        .assume_yes(args);
+        let pulled = deps::pull_binding(&binding_path, image, binding_root, |file| {
+            confirmer.confirm(&format!("Replace {file} in {}?", binding_path.display()))
+        })?;
+        writeln!(
+            self.output,
+            "Pulled {image} into {} ({} downloaded, {} already present)",
+            binding_path.display(),
+            pulled.iter().filter(|(_, downloaded)| *downloaded).count(),
+            pulled.iter().filter(|(_, downloaded)| !*downloaded).count()
+        )?;
+        Ok(())
+    }
+
+    fn bundle(&mut self, binding_path: &path::Path, dest: &str) -> Result<()> {
+        ensure!(
+            binding_path.is_dir(),
+            "binding {} does not exist",
+            binding_path.display()
+        );
+
+        let count = deps::write_bundle(binding_path, path::Path::new(dest))?;
+        writeln!(
+            self.output,
+            "Bundled {} ({count} files) into {dest}",
+            binding_path.display()
+        )?;
+        Ok(())
+    }
+
+    fn import(&mut self, args: &ArgMatches) -> Result<()> {
+        let bundle = args
+            .get_one::<String>("BUNDLE")
+            .ok_or_else(|| anyhow!("missing bundle"))?;
+        let (binding_path, count) = deps::import_bundle(
+            path::Path::new(bundle),
+            path::Path::new(&service_binding_root(args, &self.config)?),
+            args.get_one::<String>("NAME").map(|s| s.as_str()),
+        )?;
+        writeln!(
+            self.output,
+            "Imported {bundle} into {} ({count} files)",
+            binding_path.display()
+        )?;
+        Ok(())
+    }
+
+    fn serve(&mut self, server: &serve::Server, binding_path: &path::Path) -> Result<()> {
+        let binaries = binding_path.join("binaries");
+        ensure!(
+            binaries.is_dir(),
+            "binding {} has no binaries to serve",
+            binding_path.display()
+        );
+
+        writeln!(
+            self.output,
+            "Serving {} at {}",
+            binaries.display(),
+            server.uri_prefix
+        )?;
+        writeln!(
+            self.output,
+            "Map dependencies to it with `--uri-prefix {}`, press Ctrl-C to stop",
+            server.uri_prefix
+        )?;
+        self.output.flush()?;
+
+        server.serve(&binaries)
+    }
+
+    /// Re-downloads the binaries of the binding that are missing or don't match their key,
+    /// using `deps` to find where they came from
+    fn refresh(
+        &mut self,
+        deps: &[deps::Dependency],
+        binding_path: path::PathBuf,
+        opts: &deps::DownloadOptions,
+        report_dest: Option<&String>,
+    ) -> Result<()> {
+        ensure!(
+            binding_path.is_dir(),
+            "binding {} does not exist",
+            binding_path.display()
+        );
+
+        let damaged = deps::damaged_binaries(&binding_path)?;
+        if damaged.is_empty() {
+            writeln!(
+                self.output,
+                "All binaries in {} match their checksums",
+                binding_path.display()
+            )?;
+            return Ok(());
+        }
+
+        let (known, unknown): (Vec<_>, Vec<_>) = damaged
+            .iter()
+            .partition(|b| deps.iter().any(|d| d.sha256 == b.sha256));
+        let repairs = deps
+            .iter()
+            .filter(|d| known.iter().any(|b| b.sha256 == d.sha256))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !repairs.is_empty() {
+            fs::create_dir_all(binding_path.join("binaries"))?;
+            let report = deps::download_dependencies(repairs, binding_path, opts)?;
+            self.write_report(&report, report_dest)?;
+            report.check()?;
+        }
+        for b in &known {
+            writeln!(
+                self.output,
+                "Repaired {} ({})",
+                b.path.display(),
+                if b.missing {
+                    "missing"
+                } else {
+                    "checksum mismatch"
+                }
+            )?;
+        }
+
+        ensure!(
+            unknown.is_empty(),
+            "cannot repair {} binaries, their checksums are not in the buildpack.toml or lockfile:\n{}",
+            unknown.len(),
+            unknown
+                .iter()
+                .map(|b| format!("  {} (sha256 {})", b.path.display(), b.sha256))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        Ok(())
+    }
+
+    /// Writes the download report as JSON to `dest`, or to the output when `dest` is `-`
+    fn write_report(&mut self, report: &deps::DownloadReport, dest: Option<&String>) -> Result<()> {
+        match dest {
+            Some(dest) => self.write_json(&report.to_json(), dest, "report"),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes `json` to the file `dest`, or to the output when `dest` is `-`
+    fn write_json(&mut self, json: &serde_json::Value, dest: &str, what: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(json)?;
+        if dest == "-" {
+            writeln!(self.output, "{json}")?;
+        } else {
+            fs::write(dest, format!("{json}\n"))
+                .with_context(|| format!("cannot write {what} {dest}"))?;
+        }
+        Ok(())
+    }
+
+    /// Lists what an update added and the dependencies it found retired
+    fn print_update(&mut self, update: &deps::BindingUpdate, removed: bool) -> Result<()> {
+        let describe = |d: &deps::Dependency| match (&d.id, &d.version) {
+            (Some(id), Some(version)) => format!("{id} {version} ({})", d.sha256),
+            (Some(id), None) => format!("{id} ({})", d.sha256),
+            _ => d.sha256.clone(),
+        };
+
+        for d in &update.added {
+            writeln!(self.output, "Added {}", describe(d))?;
+        }
+        for d in &update.retired {
+            if removed {
+                writeln!(self.output, "Removed {}", describe(d))?;
+            } else {
+                writeln!(self.output, "Retired {}", describe(d))?;
+            }
+        }
+        write!(
+            self.output,
+            "{} added, {} unchanged, {} retired",
+            update.added.len(),
+            update.unchanged,
+            update.retired.len()
+        )?;
+        if !removed && !update.retired.is_empty() {
+            write!(self.output, ", pass --remove-retired to remove them")?;
+        }
+        writeln!(self.output)?;
+        Ok(())
+    }
+
+    fn print_plan(&mut self, planned: &[deps::PlannedDownload]) -> Result<()> {
+        let mut total = 0;
+        let mut unknown = 0;
+
+        for p in planned {
+            let size = match (p.size, &p.problem) {
+                (Some(size), _) => HumanBytes(size).to_string(),
+                (None, Some(problem)) => format!("unknown ({problem})"),
+                (None, None) => String::from("unknown"),
+            };
+            if p.cached {
+                writeln!(self.output, "{size:>12}  {} (cached)", p.dest.display())?;
+                continue;
+            }
+
+            match p.size {
+                Some(size) => total += size,
+                None => unknown += 1,
+            }
+            writeln!(self.output, "{size:>12}  {} <- {}", p.dest.display(), p.uri)?;
+        }
+
+        let to_fetch = planned.iter().filter(|p| !p.cached).count();
+        write!(
+            self.output,
+            "\nWould download {to_fetch} of {} dependencies, {} total",
+            planned.len(),
+            HumanBytes(total)
+        )?;
+        if unknown > 0 {
+            write!(self.output, " plus {unknown} of unknown size")?;
+        }
+        writeln!(self.output)?;
+        Ok(())
+    }
+}
+
+struct ArgsCommandHandler<T> {
+    output: T,
+    config: config::Config,
+}
+
+impl<T> CommandHandler for ArgsCommandHandler<T>
+where
+    T: Write,
+{
+    fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
+        ensure!(args.is_some(), "missing required args");
+        let args = args.unwrap();
+
+        // binding root = SERVICE_BINDING_ROOT (or default to "./bindings")
+        let bindings_root = service_binding_root(args, &self.config)?;
+        let bindings_home = path::Path::new(&bindings_root);
+
+        // stdout stays empty so the output can still be eval'd, but nothing being mounted
+        // shouldn't go unnoticed
+        let binding_count = if bindings_home.exists() {
+            binding_names(bindings_home)?.len()
+        } else {
+            0
+        };
+        if binding_count == 0 {
+            let reason = if bindings_home.exists() {
+                format!("there are no bindings in {bindings_root}")
+            } else {
+                format!("the binding root {bindings_root} doesn't exist")
+            };
+            ensure!(!args.get_flag("STRICT"), "{reason}, there are no args");
+            eprintln!("Warning: {reason}, there are no args");
+            return Ok(());
+        }
+
+        // the wrappers pass on the args of the command they wrap, the bindings go where those
+        // already point SERVICE_BINDING_ROOT and aren't mounted again where they mount something
+        let (user_root, user_targets) = user_flags(
+            &args
+                .get_many::<String>("USER_ARGS")
+                .unwrap_or_default()
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+        let container_path = match &user_root {
+            Some(root) => Some(root.clone()),
+            None => container_path(args, &self.config)?,
+        };
+
+        let mut selected = args
+            .get_many::<String>("NAME")
+            .unwrap_or_default()
+            .cloned()
+            .collect::<Vec<_>>();
+        // mounting each binding on its own keeps anything else in the root out of the container,
+        // and a template with the name of the binding can only be rendered for each of them
+        let template = args
+            .get_one::<String>("FORMAT")
+            .filter(|t| !matches!(t.as_str(), "tilt" | "skaffold"));
+        let per_binding =
+            args.get_flag("PER_BINDING") || template.map(|t| t.contains("{name}")).unwrap_or(false);
+        if selected.is_empty() && per_binding {
+            selected = binding_names(bindings_home)?;
+        }
+        selected.sort();
+        selected.dedup();
+        for name in &selected {
+            ensure!(
+                !name.contains(['/', '\\']) && bindings_home.join(name).join("type").exists(),
+                "there is no binding named {name} in {bindings_root}"
+            );
+            ensure!(
+                !name.contains(':'),
+                "the binding {name} can't be mounted on its own, its name has a `:`"
+            );
+        }
+
+        let host_root = match args.get_one::<String>("VOLUME_NAME") {
+            // a remote daemon can't see the paths of this host, but it can mount a named volume
+            // that the bindings were copied into
+            Some(volume) => {
+                let engine = if args.get_flag("PODMAN") {
+                    "podman"
+                } else {
+                    "docker"
+                };
+                // a question would end up in the args that are eval'd, only --yes answers it
+                let confirmer = if args.get_flag("YES") {
+                    BindingConfirmers::Always
+                } else {
+                    BindingConfirmers::Never
+                };
+                populate_volume(engine, volume, bindings_home, confirmer)?;
+                volume.clone()
+            }
+            None => {
+                let uses_docker = args.get_flag("DOCKER")
+                    || args.get_flag("PACK")
+                    || args.get_flag("COMPOSE")
+                    || args.contains_id("TESTCONTAINERS");
+                if let Some(host) = docker_config::daemon_host()
+                    .filter(|host| uses_docker && docker_config::is_remote(host))
+                {
+                    eprintln!(
+                        "Warning: the docker daemon at {host} may not see {bindings_root}, pass \
+                        --volume-name <volume> to copy the bindings into a volume it can mount"
+                    );
+                }
+                mountable_path(&bindings_root, cfg!(windows))
+            }
+        };
+        // all of the bindings are mounted with the root, unless only some are selected, then each
+        // of them is mounted on its own so the others stay out of the container
+        let mounts = |target: &str| -> Vec<(String, String)> {
+            if selected.is_empty() {
+                vec![(host_root.clone(), target.to_owned())]
+            } else {
+                selected
+                    .iter()
+                    .map(|name| {
+                        (
+                            mountable_path(
+                                &bindings_home.join(name).to_string_lossy(),
+                                cfg!(windows),
+                            ),
+                            format!("{target}/{name}"),
+                        )
+                    })
+                    .collect()
+            }
+        };
+
+        if args.get_flag("ENV_FILE") {
+            let mut warnings = vec![];
+            let lines = env_file_lines(bindings_home, &selected, &mut warnings)?;
+            for warning in &warnings {
+                eprintln!("Warning: {warning}");
+            }
+            for line in lines {
+                writeln!(self.output, "{line}")?;
+            }
+            return Ok(());
+        }
+
+        // a Dockerfile build only sees the bindings through BuildKit secrets, one for each key
+        if args.get_flag("BUILDKIT_SECRET") {
+            let words = buildkit_secret_words(bindings_home, &selected)?;
+            write!(self.output, "{}", join_words(args, &words)?)?;
+            return Ok(());
+        }
+
+        // nothing on the nodes of a cluster is at the binding root, the bindings are projected
+        // from Secrets instead
+        if args.get_flag("SECRETS") {
+            write!(
+                self.output,
+                "{}",
+                kube::secret_manifests(bindings_home, &selected)?
+            )?;
+            return Ok(());
+        }
+        if args.get_flag("KUBERNETES") {
+            let path = container_path.unwrap_or_else(|| String::from("/bindings"));
+            write!(
+                self.output,
+                "{}",
+                kube::pod_fragment(bindings_home, &selected, &path)?
+            )?;
+            return Ok(());
+        }
+
+        // containers have no business changing the bindings, unless asked to they're read-only
+        let writable = args.get_flag("WRITABLE");
+        // SELinux hosts only let containers read files labeled for them, without a label the
+        // bindings show up empty. `z` relabels them so they're shared by every container and
+        // it's ignored where SELinux is disabled, so podman, common on such hosts, always has one
+        let label = args
+            .get_one::<String>("SELINUX_LABEL")
+            .map(|l| l.as_str())
+            .or_else(|| args.get_flag("PODMAN").then_some("z"));
+        let volume_options = |mode: Option<&str>| {
+            let options = mode.into_iter().chain(label).collect::<Vec<_>>();
+            if options.is_empty() {
+                String::new()
+            } else {
+                format!(":{}", options.join(","))
+            }
+        };
+
+        // compose files aren't read by a shell, the values are YAML strings instead, which JSON
+        // strings are too
+        if args.get_flag("COMPOSE") {
+            let path = container_path.unwrap_or_else(|| String::from("/bindings"));
+            let options = volume_options((!writable).then_some("ro"));
+            writeln!(self.output, "volumes:")?;
+            for (host, container) in mounts(&path) {
+                writeln!(
+                    self.output,
+                    "  - {}",
+                    serde_json::to_string(&format!("{host}:{container}{options}"))?
+                )?;
+            }
+            writeln!(
+                self.output,
+                "environment:\n  SERVICE_BINDING_ROOT: {}",
+                serde_json::to_string(&path)?
+            )?;
+            return Ok(());
+        }
+
+        // dev loops deploy to Kubernetes, where the bindings are projected from Secrets
+        if let Some(tool) = args
+            .get_one::<String>("FORMAT")
+            .filter(|t| matches!(t.as_str(), "tilt" | "skaffold"))
+        {
+            write!(
+                self.output,
+                "{}",
+                dev_loop_snippet(tool, &bindings_root, &selected)
+            )?;
+            return Ok(());
+        }
+
+        // JSON strings are valid string literals in Java and Go too
+        if let Some(language) = args.get_one::<String>("TESTCONTAINERS") {
+            let path = container_path.unwrap_or_else(|| String::from("/bindings"));
+            let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+            if language == "go" {
+                let options = volume_options((!writable).then_some("ro"));
+                writeln!(
+                    self.output,
+                    "HostConfigModifier: func(hc *container.HostConfig) {{"
+                )?;
+                for (host, container) in mounts(&path) {
+                    writeln!(
+                        self.output,
+                        "\thc.Binds = append(hc.Binds, {})",
+                        quote(&format!("{host}:{container}{options}"))
+                    )?;
+                }
+                writeln!(
+                    self.output,
+                    "}},\nEnv: map[string]string{{\"SERVICE_BINDING_ROOT\": {}}},",
+                    quote(&path)
+                )?;
+            } else {
+                let mode = if writable { "READ_WRITE" } else { "READ_ONLY" };
+                for (host, container) in mounts(&path) {
+                    writeln!(
+                        self.output,
+                        ".withFileSystemBind({}, {}, BindMode.{mode})",
+                        quote(&host),
+                        quote(&container)
+                    )?;
+                }
+                writeln!(
+                    self.output,
+                    ".withEnv(\"SERVICE_BINDING_ROOT\", {})",
+                    quote(&path)
+                )?;
+            }
+            return Ok(());
+        }
+
+        if let Some(template) = template {
+            let path = container_path.unwrap_or_else(|| String::from("/bindings"));
+            let names = if selected.is_empty() {
+                vec![String::new()]
+            } else {
+                selected.clone()
+            };
+            let mut words = vec![];
+            for ((host, container), name) in mounts(&path).into_iter().zip(names) {
+                // the template is split before it's rendered, so a path is always a single word
+                for word in template.split_whitespace() {
+                    words.push(render_format(word, &host, &container, &name)?);
+                }
+            }
+            write!(self.output, "{}", join_words(args, &words)?)?;
+            return Ok(());
+        }
+
+        let words = match (args.get_flag("PACK"), container_path) {
+            // buildpacks look for bindings in the platform directory of the build container
+            // when SERVICE_BINDING_ROOT isn't set, and pack mounts volumes read-only
+            (true, None) => {
+                let options = volume_options(writable.then_some("rw"));
+                mounts("/platform/bindings")
+                    .into_iter()
+                    .filter(|(_, container)| !user_targets.contains(container))
+                    .flat_map(|(host, container)| {
+                        [
+                            String::from("--volume"),
+                            format!("{host}:{container}{options}"),
+                        ]
+                    })
+                    .collect()
+            }
+            // the application only finds bindings through SERVICE_BINDING_ROOT at runtime, as
+            // do buildpacks anywhere but the platform directory
+            (_, path) => {
+                let path = path.unwrap_or_else(|| String::from("/bindings"));
+                let options = volume_options((!writable).then_some("ro"));
+                let mut words = mounts(&path)
+                    .into_iter()
+                    .filter(|(_, container)| !user_targets.contains(container))
+                    .flat_map(|(host, container)| {
+                        [
+                            String::from("--volume"),
+                            format!("{host}:{container}{options}"),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+                if user_root.is_none() {
+                    words.push(String::from("--env"));
+                    words.push(format!("SERVICE_BINDING_ROOT={path}"));
+                }
+                words
+            }
+        };
+
+        write!(self.output, "{}", join_words(args, &words)?)?;
+
+        Ok(())
+    }
+}
+
+/// The SERVICE_BINDING_ROOT set by the args of `docker run` or `pack build`, and the container
+/// paths of the volumes they mount
+fn user_flags(user_args: &[String]) -> (Option<String>, Vec<String>) {
+    let mut root = None;
+    let mut targets = vec![];
+    let mut words = user_args.iter();
+    while let Some(word) = words.next() {
+        let (flag, value) = match word.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_owned())),
+            _ => (word.as_str(), None),
+        };
+        let mut value = || value.clone().or_else(|| words.next().cloned());
+        match flag {
+            "-e" | "--env" => {
+                if let Some(path) =
+                    value().and_then(|v| v.strip_prefix("SERVICE_BINDING_ROOT=").map(String::from))
+                {
+                    root = Some(path);
+                }
+            }
+            "-v" | "--volume" => {
+                if let Some(spec) = value() {
+                    let parts = spec.split(':').collect::<Vec<_>>();
+                    // a Windows host path has a `:` after the drive
+                    let target = match parts.as_slice() {
+                        [drive, _, target, ..] if drive.len() == 1 => target,
+                        [_, target, ..] => target,
+                        _ => continue,
+                    };
+                    targets.push(target.to_string());
+                }
+            }
+            "--mount" => {
+                if let Some(spec) = value() {
+                    targets.extend(spec.split(',').find_map(|option| {
+                        let (key, value) = option.split_once('=')?;
+                        matches!(key, "dst" | "destination" | "target").then(|| value.to_owned())
+                    }));
+                }
+            }
+            _ => {}
+        }
+    }
+    (root, targets)
+}
+
+/// The host path of a volume, on `windows` the `:` of a drive would be taken for the separator
+/// of the container path, so `C:\Users\me` is written the way Docker Desktop takes it,
+/// `//c/Users/me`
+fn mountable_path(path: &str, windows: bool) -> String {
+    let mut chars = path.chars();
+    match (windows, chars.next(), chars.next()) {
+        (true, Some(drive), Some(':')) if drive.is_ascii_alphabetic() => format!(
+            "//{}{}",
+            drive.to_ascii_lowercase(),
+            path[2..].replace('\\', "/")
+        ),
+        (true, _, _) => path.replace('\\', "/"),
+        _ => path.to_owned(),
+    }
+}
+
+/// Replaces `{root}`, `{dst}` and `{name}` in a word of an args template with the host path,
+/// the container path and the name of the binding that's mounted
+fn render_format(word: &str, root: &str, dst: &str, name: &str) -> Result<String> {
+    let mut rendered = String::new();
+    let mut rest = word;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unclosed placeholder in {word}"))?;
+        rendered.push_str(&rest[..start]);
+        match &rest[start + 1..start + end] {
+            "root" => rendered.push_str(root),
+            "dst" => rendered.push_str(dst),
+            "name" => rendered.push_str(name),
+            other => {
+                bail!("unknown placeholder {{{other}}}, expected {{root}}, {{dst}} or {{name}}")
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// The `NAME=value` lines of a dotenv file with the keys of the bindings under `root`, or the
+/// `selected` ones, named `<BINDING>_<KEY>`. `--env-file` takes the rest of the line as the
+/// value, so values spanning several lines or that aren't text are left out with a warning.
+fn env_file_lines(
+    root: &path::Path,
+    selected: &[String],
+    warnings: &mut Vec<String>,
+) -> Result<Vec<String>> {
+    let mut lines = vec![];
+    for (binding_name, key_name, key) in binding_keys(root, selected, false)? {
+        let contents = fs::read(&key).with_context(|| format!("cannot read {key:?}"))?;
+        let value = match String::from_utf8(contents) {
+            Ok(value) => value,
+            Err(_) => {
+                warnings.push(format!("{binding_name}/{key_name} isn't text, skipping it"));
+                continue;
+            }
+        };
+        let value = value.strip_suffix('\n').unwrap_or(&value);
+        let value = value.strip_suffix('\r').unwrap_or(value);
+        if value.contains('\n') {
+            warnings.push(format!(
+                "{binding_name}/{key_name} has more than one line, skipping it"
+            ));
+            continue;
+        }
+
+        lines.push(format!("{}={value}", env_name(&binding_name, &key_name)));
+    }
+    Ok(lines)
+}
+
+/// The part of a Tiltfile or `skaffold.yaml` that applies the Secrets of the bindings under
+/// `root`, or of the `selected` ones, before the workloads are deployed
+fn dev_loop_snippet(tool: &str, root: &str, selected: &[String]) -> String {
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    let mut secrets = String::from("bt args --kubernetes --secrets");
+    for name in selected {
+        secrets.push_str(&format!(" --name {}", shell_quote("posix", name)));
+    }
+    let fragment = secrets.replace(" --secrets", "");
+
+    if tool == "tilt" {
+        format!(
+            "# add the output of `{fragment}` to the Pod spec of the workloads\n\
+            k8s_yaml(local({}, quiet=True, env={{\"SERVICE_BINDING_ROOT\": {}}}))\n\
+            watch_file({})\n",
+            quote(&secrets),
+            quote(root),
+            quote(root)
+        )
+    } else {
+        let command = format!(
+            "SERVICE_BINDING_ROOT={} {secrets} | kubectl apply -f -",
+            shell_quote("posix", root)
+        );
+        format!(
+            "# add the output of `{fragment}` to the Pod spec of the workloads\n\
+            deploy:\n  kubectl:\n    hooks:\n      before:\n        - host:\n            \
+            command: [\"sh\", \"-c\", {}]\n",
+            quote(&command)
+        )
+    }
+}
+
+/// The `--secret id=binding-<name>-<key>,src=<path>` args of `docker buildx build` for each key
+/// of the bindings under `root`, or of the `selected` ones
+fn buildkit_secret_words(root: &path::Path, selected: &[String]) -> Result<Vec<String>> {
+    let id_part = |s: &str| s.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "-");
+    let mut words = vec![];
+    for (binding_name, key_name, key) in binding_keys(root, selected, true)? {
+        // buildx splits the value of --secret on commas
+        let src = key.to_string_lossy();
+        ensure!(
+            !src.contains(','),
+            "{src} can't be passed as a secret, its path has a `,`"
+        );
+        words.push(String::from("--secret"));
+        words.push(format!(
+            "id=binding-{}-{},src={src}",
+            id_part(&binding_name),
+            id_part(&key_name)
+        ));
+    }
+    Ok(words)
+}
+
+/// The keys of the bindings under `root`, or of the `selected` ones, as `(binding, key, path)`
+/// sorted by binding and key. Hidden files are left out, and so are `type` and `provider` unless
+/// `with_metadata` is set.
+fn binding_keys(
+    root: &path::Path,
+    selected: &[String],
+    with_metadata: bool,
+) -> Result<Vec<(String, String, path::PathBuf)>> {
+    let mut keys = vec![];
+    for binding in root.read_dir()?.filter_map(|res| res.ok()) {
+        let binding_name = binding.file_name().to_string_lossy().into_owned();
+        let binding = binding.path();
+        if !binding.is_dir()
+            || !binding.join("type").exists()
+            || !(selected.is_empty() || selected.contains(&binding_name))
+        {
+            continue;
+        }
+
+        for key in binding.read_dir()?.filter_map(|res| res.ok()) {
+            let key_name = key.file_name().to_string_lossy().into_owned();
+            let key = key.path();
+            // type and provider describe the binding, they aren't settings of the app
+            let metadata = key_name == "type" || key_name == "provider";
+            if !key.is_file() || key_name.starts_with('.') || (metadata && !with_metadata) {
+                continue;
+            }
+            keys.push((binding_name.clone(), key_name, key));
+        }
+    }
+    keys.sort();
+    Ok(keys)
+}
+
+/// Names of the bindings under `root`, sorted
+fn binding_names(root: &path::Path) -> Result<Vec<String>> {
+    let mut names = binding_keys(root, &[], true)?
+        .into_iter()
+        .map(|(binding, _, _)| binding)
+        .collect::<Vec<_>>();
+    names.dedup();
+    Ok(names)
+}
+
+/// `binding` and `key` in upper case joined by `_`, with any other characters replaced by `_`
+fn env_name(binding: &str, key: &str) -> String {
+    let name = format!("{binding}_{key}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// Joins the words of args for a shell, or as a JSON array or NUL terminated words for scripts
+/// that pass them on without a shell
+fn join_words(args: &ArgMatches, words: &[String]) -> Result<String> {
+    if args.get_flag("JSON") {
+        return Ok(serde_json::to_string(words)?);
+    }
+    if args.get_flag("NULL") {
+        return Ok(words.iter().map(|w| format!("{w}\0")).collect());
+    }
+
+    let shell = args
+        .get_one::<String>("SHELL")
+        .map(|s| s.as_str())
+        .unwrap_or("posix");
+    Ok(words
+        .iter()
+        .map(|w| shell_quote(shell, w))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Quotes `word` for `shell`, `posix` or `powershell`, unless it only has characters that mean
+/// nothing special to either
+fn shell_quote(shell: &str, word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-./:=".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        return word.to_owned();
+    }
+
+    match shell {
+        // single quotes are doubled inside single quotes
+        "powershell" => format!("'{}'", word.replace('\'', "''")),
+        // nothing is special inside single quotes, so quotes are closed, escaped and reopened
+        _ => format!("'{}'", word.replace('\'', r"'\''")),
+    }
+}
+
+/// Where the bindings go in the container, from `--container-path`, `BT_CONTAINER_PATH` or
+/// config.toml
+fn container_path(args: &ArgMatches, config: &config::Config) -> Result<Option<String>> {
+    // the shell functions can't pass flags, so the path may come from the environment too
+    let container_path = match args
+        .get_one::<String>("CONTAINER_PATH")
+        .cloned()
+        .or_else(|| env::var("BT_CONTAINER_PATH").ok().filter(|p| !p.is_empty()))
+    {
+        Some(path) => Some(path),
+        None => config.container_path.clone(),
+    };
+    if let Some(path) = &container_path {
+        ensure!(
+            path.starts_with('/') && !path.contains(':'),
+            "the container path {path} must be absolute and without `:`"
+        );
+    }
+    Ok(container_path)
+}
+
+/// The image of the container that copies bindings into a volume, it's never started
+const VOLUME_HELPER_IMAGE: &str = "busybox";
+
+/// The label of the volumes that bt created, only those are removed and created again
+const VOLUME_LABEL: &str = "com.github.dmikusa.binding-tool";
+
+/// Replaces the contents of the named `volume` with the bindings under `root`, by copying them
+/// into a helper container that mounts it. A volume that bt didn't create is never removed, the
+/// bindings are only copied into it when the `confirmer` agrees.
+fn populate_volume(
+    engine: &str,
+    volume: &str,
+    root: &path::Path,
+    confirmer: BindingConfirmers,
+) -> Result<()> {
+    // the output of the engine would end up in args that are eval'd
+    let run = |args: &[&str]| -> Result<String> {
+        let output = std::process::Command::new(engine)
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .with_context(|| format!("cannot run {engine}, is it installed and on the PATH?"))?;
+        ensure!(
+            output.status.success(),
+            "{engine} {} failed: {}",
+            args[..2].join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    };
+
+    let labels = run(&[
+        "volume",
+        "inspect",
+        "--format",
+        "{{range $k, $v := .Labels}}{{$k}} {{end}}",
+        volume,
+    ]);
+    let label = format!("{VOLUME_LABEL}=bindings");
+    match labels {
+        // removing the volume drops bindings that were deleted since, it fails while a container
+        // still uses it and then they're copied over the old ones
+        Ok(labels) if labels.split_whitespace().any(|l| l == VOLUME_LABEL) => {
+            let _ = run(&["volume", "rm", volume]);
+            run(&["volume", "create", "--label", &label, volume])?;
+        }
+        Ok(_) => ensure!(
+            confirmer.confirm(&format!("Copy the bindings into the volume {volume}?")),
+            "the volume {volume} wasn't created by bt, pass --yes to copy the bindings into it"
+        ),
+        Err(_) => {
+            run(&["volume", "create", "--label", &label, volume])?;
+        }
+    }
+
+    let mount = format!("{volume}:/bindings");
+    let helper = run(&[
+        "container",
+        "create",
+        "--volume",
+        &mount,
+        VOLUME_HELPER_IMAGE,
+    ])?;
+    let src = root.join(".");
+    let copied = run(&["cp", &src.to_string_lossy(), &format!("{helper}:/bindings")]);
+    run(&["container", "rm", &helper])?;
+    copied.map(|_| ())
+}
+
+struct InjectCommandHandler<T> {
+    output: T,
+    config: config::Config,
+}
+
+impl<T> CommandHandler for InjectCommandHandler<T>
+where
+    T: Write,
+{
+    fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
+        ensure!(args.is_some(), "missing required args");
+        let args = args.unwrap();
+
+        let bindings_root = service_binding_root(args, &self.config)?;
+        let bindings_home = path::Path::new(&bindings_root);
+        let binding_count = if bindings_home.exists() {
+            binding_names(bindings_home)?.len()
+        } else {
+            0
+        };
+        ensure!(
+            binding_count > 0,
+            "there are no bindings in {bindings_root}"
+        );
+
+        let container = args.get_one::<String>("CONTAINER").unwrap();
+        let path = container_path(args, &self.config)?.unwrap_or_else(|| String::from("/bindings"));
+        let engine = if args.get_flag("PODMAN") {
+            "podman"
+        } else {
+            "docker"
+        };
+
+        // copying the contents of the root creates the container path if it's missing
+        let output = std::process::Command::new(engine)
+            .arg("cp")
+            .arg(bindings_home.join("."))
+            .arg(format!("{container}:{path}"))
+            .stdin(std::process::Stdio::null())
+            .output()
+            .with_context(|| format!("cannot run {engine}, is it installed and on the PATH?"))?;
+        ensure!(
+            output.status.success(),
+            "{engine} cp to {container} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+
+        // the environment of processes that are already running can't be changed
+        writeln!(
+            self.output,
+            "Copied {binding_count} binding(s) to {path} in {container}. Processes that are \
+            already running don't see SERVICE_BINDING_ROOT, start the ones that need the bindings \
+            with:\n\n    {engine} exec --env SERVICE_BINDING_ROOT={} {} <command>",
+            shell_quote("posix", &path),
+            shell_quote("posix", container)
+        )?;
+        Ok(())
+    }
+}
+
+struct InitCommandHandler<T> {
+    output: T,
+}
+
+/// The shell to generate wrappers for, from the login shell in `$SHELL`. Windows has no
+/// `$SHELL` unless it's set by a Unix-like environment, otherwise it's PowerShell.
+fn detect_shell(shell_env: Option<&str>, windows: bool) -> Result<&'static str> {
+    let name = shell_env
+        .filter(|s| !s.is_empty())
+        .and_then(|s| s.rsplit(['/', '\\']).next())
+        .map(|s| s.trim_end_matches(".exe"));
+    match name {
+        Some("bash") => Ok("bash"),
+        Some("zsh") => Ok("zsh"),
+        Some("fish") => Ok("fish"),
+        Some("nu") => Ok("nu"),
+        Some("pwsh" | "powershell") => Ok("powershell"),
+        None if windows => Ok("powershell"),
+        Some(other) => {
+            bail!("unsupported shell {other}, pass one of bash, fish, zsh, powershell or nu")
+        }
+        None => bail!(
+            "cannot tell the shell from $SHELL, pass one of bash, fish, zsh, powershell or nu"
+        ),
+    }
+}
+
+impl<T> CommandHandler for InitCommandHandler<T>
+where
+    T: Write,
+{
+    fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
+        ensure!(args.is_some(), "missing required args");
+        let args = args.unwrap();
+
+        let shell = match args.get_one::<String>("SHELL") {
+            Some(shell) => shell.as_str(),
+            None => detect_shell(env::var("SHELL").ok().as_deref(), cfg!(windows))?,
+        };
+
+        if shell == "direnv" {
+            ensure!(
+                !args.contains_id("PREFIX") && !args.get_flag("NO_SHADOW"),
+                "direnv has no wrappers to name, --prefix and --no-shadow only apply to shells"
+            );
+            return self
+                .output
+                .write_all(direnv_snippet(args.get_flag("WATCH")).as_bytes())
+                .map_err(|e| anyhow!(e));
+        }
+        ensure!(!args.get_flag("WATCH"), "--watch only applies to direnv");
+
+        let prefix = match args.get_one::<String>("PREFIX") {
+            Some(prefix) => prefix.as_str(),
+            None if args.get_flag("NO_SHADOW") => "bt-",
+            None => "",
+        };
+        ensure!(
+            prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "prefix {} may only contain letters, digits, `-` and `_`",
+            prefix
+        );
+
+        let script = match shell {
+            "fish" => include_str!("scripts/fish.sh"),
+            "bash" => include_str!("scripts/bash.sh"),
+            "zsh" => include_str!("scripts/zsh.sh"),
+            "powershell" => include_str!("scripts/powershell.ps1"),
+            "nu" => include_str!("scripts/nu.nu"),
+            _ => bail!("unsupported shell {}", shell),
+        };
+        writeln!(self.output, "{}", rename_wrappers(script, prefix))?;
+
+        self.output
+            .write_all(&completions(shell)?)
+            .map_err(|e| anyhow!(e))
+    }
+}
+
+/// The `.envrc` lines that point `bt` and the wrappers at the project's bindings. `expand_path`
+/// resolves against the `.envrc`, so it's the same root from any subdirectory.
+fn direnv_snippet(watch: bool) -> String {
+    let mut snippet = String::from("export SERVICE_BINDING_ROOT=\"$(expand_path bindings)\"\n");
+    if watch {
+        snippet.push_str("watch_dir bindings\n");
+    }
+    snippet
+}
+
+/// Renames the wrapper functions to `<prefix>docker` and so on. The wrappers run the real
+/// commands by path or with `^`, so only the definitions change.
+fn rename_wrappers(script: &str, prefix: &str) -> String {
+    ["docker", "pack", "podman"]
+        .iter()
+        .fold(script.to_string(), |script, name| {
+            script
+                .replace(
+                    &format!("function {}", name),
+                    &format!("function {}{}", prefix, name),
+                )
+                .replace(
+                    &format!("def --wrapped {} ", name),
+                    &format!("def --wrapped {}{} ", prefix, name),
+                )
+        })
+}
+
+/// Completions for `bt` itself, so the line that loads the wrappers sets up completion too
+fn completions(shell: &str) -> Result<Vec<u8>> {
+    let mut cmd = args::Parser::new().into_command();
+    let mut script = vec![];
+    match shell {
+        "bash" => generate(Shell::Bash, &mut cmd, "bt", &mut script),
+        "zsh" => generate(Shell::Zsh, &mut cmd, "bt", &mut script),
+        "powershell" => generate(Shell::PowerShell, &mut cmd, "bt", &mut script),
+        "nu" => generate(Nushell, &mut cmd, "bt", &mut script),
+        "fish" => {
+            generate(Shell::Fish, &mut cmd, "bt", &mut script);
+            // `eval (bt init fish)` joins the lines with spaces, so hand them to `source` as
+            // quoted args on one line, which keeps the line breaks inside the script intact
+            let lines = String::from_utf8(script)?
+                .lines()
+                .map(|l| format!("'{}'", l.replace('\\', "\\\\").replace('\'', "\\'")))
+                .collect::<Vec<String>>();
+            return Ok(format!("printf '%s\\n' {} | source;\n", lines.join(" ")).into_bytes());
+        }
+        _ => bail!("unsupported shell {}", shell),
+    }
+    Ok(script)
+}
+
+struct GenerateCommandHandler<T> {
+    output: T,
+}
+
+impl<T> CommandHandler for GenerateCommandHandler<T>
+where
+    T: Write,
+{
+    fn handle(&mut self, args: Option<&ArgMatches>) -> Result<()> {
+        ensure!(args.is_some(), "missing required args");
+        let args = args.unwrap();
+
+        if args.subcommand_matches("entrypoint").is_some() {
+            return self
+                .output
+                .write_all(include_str!("scripts/entrypoint.sh").as_bytes())
+                .map_err(|e| anyhow!(e));
+        }
+        bail!("nothing to generate")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+    use std::str::Utf8Error;
+
+    use super::*;
+
+    struct TestBuffer {
+        buffer: Vec<u8>,
+    }
+
+    impl Write for TestBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.buffer.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.buffer.flush()
+        }
+    }
+
+    impl TestBuffer {
+        fn new() -> TestBuffer {
+            TestBuffer { buffer: vec![] }
+        }
+
+        fn writer(&mut self) -> &mut impl Write {
+            &mut self.buffer
+        }
+
+        fn string(&self) -> Result<&str, Utf8Error> {
+            str::from_utf8(&self.buffer)
+        }
+    }
+
+    #[test]
+    #[serial(requires_cwd)]
+    fn given_no_bindings_root_set_it_returns_current_working_directory() {
+        let args = args::Parser::new().parse_args(vec!["bt", "args", "-d"]);
+        let cmd = args.subcommand_matches("args").unwrap();
+        temp_env::with_var_unset("SERVICE_BINDING_ROOT", || {
+            let root = super::service_binding_root(cmd, &Default::default()).unwrap();
+            assert!(root.starts_with(env::current_dir().unwrap().to_str().unwrap()));
+        });
+    }
+
+    #[test]
+    fn given_a_config_file_it_is_used_without_env_or_flags() {
+        let config: config::Config = r#"
+            binding-root = "/from/config"
+            on-conflict = "fail"
+            container-path = "/config/bindings"
+            "#
+        .parse()
+        .unwrap();
+
+        temp_env::with_vars(
+            [
+                ("SERVICE_BINDING_ROOT", None::<&str>),
+                ("BT_CONTAINER_PATH", None),
+            ],
+            || {
+                let args = args::Parser::new().parse_args(vec!["bt", "args", "-d"]);
+                let cmd = args.subcommand_matches("args").unwrap();
+                assert_eq!(
+                    super::service_binding_root(cmd, &config).unwrap(),
+                    "/from/config"
+                );
+                assert!(matches!(
+                    BindingConfirmers::for_conflicts(false, BindingConfirmers::Always, &config),
+                    BindingConfirmers::Never
+                ));
+                assert!(matches!(
+                    BindingConfirmers::for_conflicts(true, BindingConfirmers::Console, &config),
+                    BindingConfirmers::Always
+                ));
+
+                assert_eq!(
+                    container_path(cmd, &config).unwrap().as_deref(),
+                    Some("/config/bindings")
+                );
+                let args = args::Parser::new().parse_args(vec![
+                    "bt",
+                    "args",
+                    "-d",
+                    "--container-path",
+                    "/flag/bindings",
+                ]);
+                let cmd = args.subcommand_matches("args").unwrap();
+                assert_eq!(
+                    container_path(cmd, &config).unwrap().as_deref(),
+                    Some("/flag/bindings")
+                );
+            },
+        );
+
+        temp_env::with_vars(
+            [
+                ("SERVICE_BINDING_ROOT", Some("/from/env")),
+                ("BT_CONTAINER_PATH", Some("/env/bindings")),
+            ],
+            || {
+                let args = args::Parser::new().parse_args(vec!["bt", "args", "-d"]);
+                let cmd = args.subcommand_matches("args").unwrap();
+                assert_eq!(
+                    super::service_binding_root(cmd, &config).unwrap(),
+                    "/from/env"
+                );
+                assert_eq!(
+                    container_path(cmd, &config).unwrap().as_deref(),
+                    Some("/env/bindings")
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn given_bindings_root_set_it_returns_bindings_root_dir() {
+        let args = args::Parser::new().parse_args(vec!["bt", "args", "-d"]);
+        let cmd = args.subcommand_matches("args").unwrap();
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some("/bindings"), || {
+            let root = super::service_binding_root(cmd, &Default::default()).unwrap();
+            assert!(root.starts_with("/bindings"));
+        });
+    }
+
+    #[test]
+    fn given_binding_root_flag_it_wins_over_env_and_config() {
+        let config: config::Config = r#"binding-root = "/from/config""#.parse().unwrap();
+        let args =
+            args::Parser::new().parse_args(vec!["bt", "-r", "/from/flag", "ca-certs", "list"]);
+        let cmd = args
+            .subcommand_matches("ca-certs")
+            .and_then(|c| c.subcommand_matches("list"))
+            .unwrap();
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some("/from/env"), || {
+            assert_eq!(
+                super::service_binding_root(cmd, &config).unwrap(),
+                "/from/flag"
+            );
+        });
+    }
+
+    #[test]
+    fn given_binding_root_flag_add_writes_there() {
+        let flag_root = tempfile::tempdir().unwrap();
+        let env_root = tempfile::tempdir().unwrap();
+
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(env_root.path()), || {
+            let args = args::Parser::new().parse_args(vec![
+                "bt",
+                "add",
+                "-r",
+                flag_root.path().to_str().unwrap(),
+                "-t",
+                "my-type",
+                "-n",
+                "my-db",
+                "-p",
+                "key=val",
+            ]);
+            let cmd = args.subcommand_matches("add").unwrap();
+            let res = AddCommandHandler {
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "{:?}", res);
+            assert!(flag_root.path().join("my-db/key").exists());
+            assert!(!env_root.path().join("my-db").exists());
+
+            // the flag isn't passed on to the commands bt runs through the environment
+            assert_eq!(
+                env::var_os("SERVICE_BINDING_ROOT").as_deref(),
+                Some(env_root.path().as_os_str())
+            );
+        });
+    }
+
+    #[test]
+    fn given_binding_args_it_creates_binding() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let bp = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
+        let res = bp.add_binding("key=val");
+
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("testType/type").exists());
+        assert!(tmpdir.path().join("testType/key").exists());
+
+        let data = fs::read(tmpdir.path().join("testType/type"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"testType");
+
+        let data = fs::read(tmpdir.path().join("testType/key"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"val");
+    }
+
+    #[test]
+    fn given_duplicate_binding_key_it_doesnt_overwrite_binding() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
+        let res = bp1.add_binding("key=val");
+
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("testType/type").exists());
+        assert!(tmpdir.path().join("testType/key").exists());
+
+        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
+        let res = bp1.add_binding("key=other_val");
+        assert!(res.is_err());
+
+        let data = fs::read(tmpdir.path().join("testType/type"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"testType");
+
+        let data = fs::read(tmpdir.path().join("testType/key"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"val");
+    }
+
+    #[test]
+    fn given_duplicate_binding_but_different_key_adds_key_to_binding() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
+        let res = bp1.add_binding("key=val");
+
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("testType/type").exists());
+        assert!(tmpdir.path().join("testType/key").exists());
+
+        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
+        let res = bp1.add_binding("other_key=other_val");
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("testType/other_key").exists());
+
+        let data = fs::read(tmpdir.path().join("testType/type"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"testType");
+
+        let data = fs::read(tmpdir.path().join("testType/other_key"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"other_val");
+    }
+
+    #[test]
+    fn given_duplicate_binding_and_same_key_confirm_updates_key() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
+        let res = bp1.add_binding("key=val");
+
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("testType/type").exists());
+        assert!(tmpdir.path().join("testType/key").exists());
+
+        let bp1 =
+            BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Always);
+        let res = bp1.add_binding("key=new_val");
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("testType/key").exists());
+
+        let data = fs::read(tmpdir.path().join("testType/type"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"testType");
+
+        let data = fs::read(tmpdir.path().join("testType/key"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"new_val");
+    }
+
+    #[test]
+    fn given_binding_args_with_name_it_creates_binding_using_name() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let bp = BindingProcessor::new(
+            &tmppath,
+            Some("testType"),
+            Some("diff-name"),
+            BindingConfirmers::Never,
+        );
+        let res = bp.add_binding("key=val");
+
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("diff-name/type").exists());
+        assert!(tmpdir.path().join("diff-name/key").exists());
+
+        let data = fs::read(tmpdir.path().join("diff-name/type"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"testType");
+
+        let data = fs::read(tmpdir.path().join("diff-name/key"));
+        assert!(data.is_ok());
         assert_eq!(data.unwrap(), b"val");
     }
 
     #[test]
-    fn given_duplicate_binding_key_it_doesnt_overwrite_binding() {
+    #[serial(requires_cwd)]
+    fn given_binding_args_with_value_relative_file_creates_binding_using_file_contents() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let res = fs::write(tmpdir.path().join("val"), "actual value");
+        assert!(res.is_ok());
+
+        let cur_dir = env::current_dir();
+        assert!(res.is_ok());
+
+        let res = env::set_current_dir(&tmpdir);
+        assert!(res.is_ok());
+
+        let bp = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
+        let res = bp.add_binding("key=@val");
+
+        {
+            let res = env::set_current_dir(cur_dir.unwrap());
+            assert!(res.is_ok());
+        }
+
+        assert!(res.is_ok(), "{}", res.unwrap_err());
+        assert!(tmpdir.path().join("testType/type").exists());
+        assert!(tmpdir.path().join("testType/key").exists());
+
+        let data = fs::read(tmpdir.path().join("testType/type"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"testType");
+
+        let data = fs::read(tmpdir.path().join("testType/key"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"actual value");
+    }
+
+    #[test]
+    fn given_binding_args_with_value_full_file_path_creates_binding_using_file_contents() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let res = fs::create_dir_all(tmpdir.path().join("test"));
+        assert!(res.is_ok());
+
+        let val_path = tmpdir.path().join("test/val");
+        let res = fs::write(tmpdir.path().join("test/val"), "actual value");
+        assert!(res.is_ok());
+
+        let bp = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
+        let res = bp.add_binding(format!("key=@{}", val_path.to_string_lossy()));
+
+        assert!(res.is_ok(), "{}", res.unwrap_err());
+        assert!(tmpdir.path().join("testType/type").exists());
+        assert!(tmpdir.path().join("testType/key").exists());
+
+        let data = fs::read(tmpdir.path().join("testType/type"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"testType");
+
+        let data = fs::read(tmpdir.path().join("testType/key"));
+        assert!(data.is_ok());
+        assert_eq!(data.unwrap(), b"actual value");
+    }
+
+    #[test]
+    fn given_binding_it_deletes_the_binding() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let bp = BindingProcessor::new(
+            &tmppath,
+            Some("some-type"),
+            Some("diff-name"),
+            BindingConfirmers::Always,
+        );
+        let res = bp.add_binding("key=val");
+
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("diff-name/type").exists());
+        assert!(tmpdir.path().join("diff-name/key").exists());
+
+        let tmp: Vec<&str> = vec![];
+        let res = bp.delete_bindings(tmp.into_iter());
+        assert!(res.is_ok());
+        assert!(!tmpdir.path().join("diff-name/type").exists());
+        assert!(!tmpdir.path().join("diff-name/key").exists());
+    }
+
+    #[test]
+    fn given_a_binding_and_user_declines_it_doesnt_delete_the_binding() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let bp = BindingProcessor::new(
+            &tmppath,
+            Some("some-type"),
+            Some("diff-name"),
+            BindingConfirmers::Never,
+        );
+        let res = bp.add_binding("key=val");
+
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("diff-name/type").exists());
+        assert!(tmpdir.path().join("diff-name/key").exists());
+
+        let tmp: Vec<&str> = vec![];
+        let res = bp.delete_bindings(tmp.into_iter());
+        assert!(res.is_err());
+        assert!(tmpdir.path().join("diff-name/type").exists());
+        assert!(tmpdir.path().join("diff-name/key").exists());
+    }
+
+    #[test]
+    fn given_binding_and_key_it_deletes_the_specific_binding_key_only() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let bp = BindingProcessor::new(
+            &tmppath,
+            Some("some-type"),
+            Some("diff-name"),
+            BindingConfirmers::Always,
+        );
+        let res = bp.add_binding("key1=val1");
+        assert!(res.is_ok());
+
+        let res = bp.add_binding("key2=val2");
+        assert!(res.is_ok());
+
+        assert!(tmpdir.path().join("diff-name/type").exists());
+        assert!(tmpdir.path().join("diff-name/key1").exists());
+        assert!(tmpdir.path().join("diff-name/key2").exists());
+
+        let tmp: Vec<&str> = vec!["key1"];
+        let res = bp.delete_bindings(tmp.into_iter());
+        assert!(res.is_ok());
+        assert!(tmpdir.path().join("diff-name/type").exists());
+        assert!(!tmpdir.path().join("diff-name/key1").exists());
+        assert!(tmpdir.path().join("diff-name/key2").exists());
+    }
+
+    #[test]
+    fn given_binding_and_key_and_user_declines_it_doesnt_delete_the_specific_binding_key() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        let bp = BindingProcessor::new(
+            &tmppath,
+            Some("some-type"),
+            Some("diff-name"),
+            BindingConfirmers::Never,
+        );
+        let res = bp.add_binding("key1=val1");
+        assert!(res.is_ok());
+
+        let res = bp.add_binding("key2=val2");
+        assert!(res.is_ok());
+
+        assert!(tmpdir.path().join("diff-name/type").exists());
+        assert!(tmpdir.path().join("diff-name/key1").exists());
+        assert!(tmpdir.path().join("diff-name/key2").exists());
+
+        let tmp: Vec<&str> = vec!["key1"];
+        let res = bp.delete_bindings(tmp.into_iter());
+        assert!(res.is_err());
+        assert!(tmpdir.path().join("diff-name/type").exists());
+        assert!(tmpdir.path().join("diff-name/key1").exists());
+        assert!(tmpdir.path().join("diff-name/key2").exists());
+    }
+
+    #[test]
+    fn given_force_delete_removes_without_asking() {
+        let args = args::Parser::new().parse_args(vec!["bt", "delete", "-n", "my-db"]);
+        let cmd = args.subcommand_matches("delete").unwrap();
+        let handler = DeleteCommandHandler {
+            config: Default::default(),
+        };
+        assert!(matches!(handler.confirmer(cmd), BindingConfirmers::Console));
+
+        // on-conflict decides when --force isn't passed
+        let handler = DeleteCommandHandler {
+            config: config::Config {
+                on_conflict: Some(config::Conflict::Fail),
+                ..Default::default()
+            },
+        };
+        assert!(matches!(handler.confirmer(cmd), BindingConfirmers::Never));
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+        let bp = BindingProcessor::new(
+            &tmppath,
+            Some("some-type"),
+            Some("my-db"),
+            BindingConfirmers::Never,
+        );
+        assert!(bp
+            .add_bindings(["key1=val1", "key2=val2"].iter().copied())
+            .is_ok());
+
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let args = args::Parser::new()
+                .parse_args(vec!["bt", "delete", "-f", "-n", "my-db", "-k", "key1"]);
+            let cmd = args.subcommand_matches("delete").unwrap();
+            let mut handler = DeleteCommandHandler {
+                config: config::Config {
+                    on_conflict: Some(config::Conflict::Fail),
+                    ..Default::default()
+                },
+            };
+            assert!(matches!(handler.confirmer(cmd), BindingConfirmers::Always));
+            let res = handler.handle(Some(cmd));
+            assert!(res.is_ok(), "{:?}", res);
+            assert!(!tmpdir.path().join("my-db/key1").exists());
+
+            // on-conflict = "fail" declines without --force
+            let args =
+                args::Parser::new().parse_args(vec!["bt", "delete", "-n", "my-db", "-k", "key2"]);
+            let cmd = args.subcommand_matches("delete").unwrap();
+            let res = handler.handle(Some(cmd));
+            assert!(res.is_err());
+            assert!(tmpdir.path().join("my-db/key2").exists());
+        });
+    }
+
+    #[test]
+    fn given_yes_console_confirmations_are_answered() {
+        let args = args::Parser::new().parse_args(vec!["bt", "-y", "delete", "-n", "my-db"]);
+        let cmd = args.subcommand_matches("delete").unwrap();
+        assert!(matches!(
+            BindingConfirmers::Console.assume_yes(cmd),
+            BindingConfirmers::Always
+        ));
+        assert!(matches!(
+            BindingConfirmers::Never.assume_yes(cmd),
+            BindingConfirmers::Never
+        ));
+
+        let args = args::Parser::new().parse_args(vec!["bt", "delete", "-n", "my-db"]);
+        let cmd = args.subcommand_matches("delete").unwrap();
+        assert!(matches!(
+            BindingConfirmers::Console.assume_yes(cmd),
+            BindingConfirmers::Console
+        ));
+    }
+
+    #[test]
+    fn given_yes_delete_removes_the_key() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp = BindingProcessor::new(
+                &tmppath,
+                Some("some-type"),
+                Some("my-db"),
+                BindingConfirmers::Never,
+            );
+            assert!(bp
+                .add_bindings(["key1=val1", "key2=val2"].iter().copied())
+                .is_ok());
+
+            let args = args::Parser::new()
+                .parse_args(vec!["bt", "delete", "--yes", "-n", "my-db", "-k", "key1"]);
+            let cmd = args.subcommand_matches("delete").unwrap();
+            let res = DeleteCommandHandler {
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "{:?}", res);
+            assert!(!tmpdir.path().join("my-db/key1").exists());
+            assert!(tmpdir.path().join("my-db/key2").exists());
+
+            // --force and --yes together still delete
+            let args = args::Parser::new().parse_args(vec![
+                "bt", "delete", "-y", "-f", "-n", "my-db", "-k", "key2",
+            ]);
+            let cmd = args.subcommand_matches("delete").unwrap();
+            let res = DeleteCommandHandler {
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "{:?}", res);
+            assert!(!tmpdir.path().join("my-db/key2").exists());
+        });
+    }
+
+    #[test]
+    fn given_a_binding_init_outputs_fish_script() {
+        // check args
+        let args = args::Parser::new().parse_args(vec!["bt", "init", "fish"]);
+        let cmd = args.subcommand_matches("init").unwrap();
+        let mut tb = TestBuffer::new();
+        let res = InitCommandHandler {
+            output: tb.writer(),
+        }
+        .handle(Some(cmd));
+        assert!(res.is_ok(), "init handler should succeed");
+        let out = tb.string().unwrap();
+        assert!(out.starts_with(include_str!("scripts/fish.sh")));
+        assert!(out.contains("'complete -c bt "), "should load completions");
+        assert!(
+            out.ends_with(" | source;\n"),
+            "should source them from one line"
+        );
+    }
+
+    #[test]
+    fn given_a_binding_init_outputs_bash_script() {
+        // check args
+        let args = args::Parser::new().parse_args(vec!["bt", "init", "bash"]);
+        let cmd = args.subcommand_matches("init").unwrap();
+        let mut tb = TestBuffer::new();
+        let res = InitCommandHandler {
+            output: tb.writer(),
+        }
+        .handle(Some(cmd));
+        assert!(res.is_ok(), "init handler should succeed");
+        let out = tb.string().unwrap();
+        assert!(out.starts_with(include_str!("scripts/bash.sh")));
+        assert!(out.contains("complete -F _bt "), "should load completions");
+    }
+
+    #[test]
+    fn given_a_binding_init_outputs_zsh_script() {
+        // check args
+        let args = args::Parser::new().parse_args(vec!["bt", "init", "zsh"]);
+        let cmd = args.subcommand_matches("init").unwrap();
+        let mut tb = TestBuffer::new();
+        let res = InitCommandHandler {
+            output: tb.writer(),
+        }
+        .handle(Some(cmd));
+        assert!(res.is_ok(), "init handler should succeed");
+        let out = tb.string().unwrap();
+        assert!(out.starts_with(include_str!("scripts/zsh.sh")));
+        assert!(out.contains("compdef _bt bt"), "should load completions");
+    }
+
+    #[test]
+    fn given_a_binding_init_outputs_powershell_script() {
+        let args = args::Parser::new().parse_args(vec!["bt", "init", "powershell"]);
+        let cmd = args.subcommand_matches("init").unwrap();
+        let mut tb = TestBuffer::new();
+        let res = InitCommandHandler {
+            output: tb.writer(),
+        }
+        .handle(Some(cmd));
+        assert!(res.is_ok(), "init handler should succeed");
+        let out = tb.string().unwrap();
+        assert!(out.starts_with(include_str!("scripts/powershell.ps1")));
+        assert!(
+            out.contains("Register-ArgumentCompleter -Native -CommandName 'bt'"),
+            "should load completions"
+        );
+    }
+
+    #[test]
+    fn given_a_binding_init_outputs_nu_script() {
+        let args = args::Parser::new().parse_args(vec!["bt", "init", "nu"]);
+        let cmd = args.subcommand_matches("init").unwrap();
+        let mut tb = TestBuffer::new();
+        let res = InitCommandHandler {
+            output: tb.writer(),
+        }
+        .handle(Some(cmd));
+        assert!(res.is_ok(), "init handler should succeed");
+        let out = tb.string().unwrap();
+        assert!(out.starts_with(include_str!("scripts/nu.nu")));
+        assert!(
+            out.contains("export extern bt ["),
+            "should load completions"
+        );
+    }
+
+    #[test]
+    fn given_a_prefix_init_outputs_wrappers_that_dont_shadow() {
+        for shell in ["bash", "zsh", "fish", "powershell", "nu"] {
+            let args = args::Parser::new().parse_args(vec!["bt", "init", shell, "--no-shadow"]);
+            let cmd = args.subcommand_matches("init").unwrap();
+            let mut tb = TestBuffer::new();
+            let res = InitCommandHandler {
+                output: tb.writer(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "init handler should succeed");
+            let out = tb.string().unwrap();
+            for name in ["docker", "pack", "podman"] {
+                assert!(out.contains(&format!(" bt-{}", name)), "{} {}", shell, name);
+                assert!(!out.contains(&format!("function {} ", name)), "{}", shell);
+                assert!(!out.contains(&format!("function {}(", name)), "{}", shell);
+                assert!(!out.contains(&format!("function {};", name)), "{}", shell);
+                assert!(!out.contains(&format!("--wrapped {} ", name)), "{}", shell);
+            }
+        }
+
+        let args = args::Parser::new().parse_args(vec!["bt", "init", "bash", "--prefix", "a b"]);
+        let cmd = args.subcommand_matches("init").unwrap();
+        let res = InitCommandHandler {
+            output: TestBuffer::new().writer(),
+        }
+        .handle(Some(cmd));
+        assert!(res.is_err(), "should reject a prefix the shell would split");
+    }
+
+    #[test]
+    fn given_direnv_init_outputs_envrc() {
+        let args = args::Parser::new().parse_args(vec!["bt", "init", "direnv"]);
+        let cmd = args.subcommand_matches("init").unwrap();
+        let mut tb = TestBuffer::new();
+        let res = InitCommandHandler {
+            output: tb.writer(),
+        }
+        .handle(Some(cmd));
+        assert!(res.is_ok(), "init handler should succeed");
+        assert_eq!(
+            tb.string().unwrap(),
+            "export SERVICE_BINDING_ROOT=\"$(expand_path bindings)\"\n"
+        );
+
+        let args = args::Parser::new().parse_args(vec!["bt", "init", "direnv", "--watch"]);
+        let cmd = args.subcommand_matches("init").unwrap();
+        let mut tb = TestBuffer::new();
+        let res = InitCommandHandler {
+            output: tb.writer(),
+        }
+        .handle(Some(cmd));
+        assert!(res.is_ok(), "init handler should succeed");
+        assert!(tb.string().unwrap().ends_with("\nwatch_dir bindings\n"));
+
+        for flags in [
+            vec!["bt", "init", "direnv", "--no-shadow"],
+            vec!["bt", "init", "bash", "--watch"],
+        ] {
+            let args = args::Parser::new().parse_args(flags);
+            let cmd = args.subcommand_matches("init").unwrap();
+            let res = InitCommandHandler {
+                output: TestBuffer::new().writer(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_err(), "should reject flags that don't apply");
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn given_a_binding_entrypoint_exports_it() {
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let args = args::Parser::new().parse_args(vec!["bt", "generate", "entrypoint"]);
+        let cmd = args.subcommand_matches("generate").unwrap();
+        let mut tb = TestBuffer::new();
+        let res = GenerateCommandHandler {
+            output: tb.writer(),
+        }
+        .handle(Some(cmd));
+        assert!(res.is_ok(), "generate handler should succeed");
+        let script = tmpdir.path().join("entrypoint.sh");
+        fs::write(&script, tb.string().unwrap()).unwrap();
+
+        let root = tmpdir.path().join("bindings");
+        let rootpath = root.to_string_lossy();
+        let bp = BindingProcessor::new(
+            &rootpath,
+            Some("postgresql"),
+            Some("my-db"),
+            BindingConfirmers::Never,
+        );
+        assert!(bp.add_binding("pass.word=top secret").is_ok());
+        assert!(bp.add_binding("provider=bitnami").is_ok());
+
+        let out = std::process::Command::new("sh")
+            .arg(&script)
+            .args([
+                "sh",
+                "-c",
+                "echo \"$POSTGRESQL_PASS_WORD:${POSTGRESQL_TYPE-none}:${POSTGRESQL_PROVIDER-none}\"",
+            ])
+            .env("SERVICE_BINDING_ROOT", &root)
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "{:?}", out);
+        assert_eq!(
+            String::from_utf8_lossy(&out.stdout),
+            "top secret:none:none\n"
+        );
+    }
+
+    #[test]
+    #[serial(requires_cwd)]
+    fn given_no_dependencies_dependency_mapping_only_fails_with_filters() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let cur_dir = env::current_dir().unwrap();
+        env::set_current_dir(&tmpdir).unwrap();
+        let toml = tmpdir.path().join("buildpack.toml");
+        fs::write(
+            &toml,
+            "[buildpack]\nid = \"empty\"\nversion = \"1.0.0\"\n[metadata]\ndependencies = []\n",
+        )
+        .unwrap();
+        let toml = toml.to_string_lossy();
+        let root = tmpdir.path().join("bindings");
+
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(&root), || {
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+                DependencyMappingCommandHandler {
+                    output: TestBuffer::new().writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd))
+            };
+
+            let res = run(vec![
+                "bt",
+                "dependency-mapping",
+                "--no-download",
+                "-t",
+                &toml,
+            ]);
+            assert!(res.is_ok(), "{:?}", res);
+
+            let res = run(vec![
+                "bt",
+                "dependency-mapping",
+                "--no-download",
+                "-t",
+                &toml,
+                "--include",
+                "jdk",
+            ]);
+            assert!(res.is_err(), "the patterns should match nothing");
+        });
+
+        env::set_current_dir(cur_dir).unwrap();
+    }
+
+    #[test]
+    #[serial(requires_cwd)]
+    fn given_lock_dependency_mapping_writes_the_lockfile() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let cur_dir = env::current_dir().unwrap();
+        env::set_current_dir(&tmpdir).unwrap();
+
+        fs::write(
+            "buildpack.toml",
+            r#"[buildpack]
+            id = "jvm"
+            version = "1.0.0"
+
+            [[metadata.dependencies]]
+            id = "jdk"
+            version = "17.0.1"
+            uri = "https://example.com/jdk.tar.gz"
+            sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+            "#,
+        )
+        .unwrap();
+        let run = |extra: &[&str]| {
+            let mut argv = vec![
+                "bt",
+                "dependency-mapping",
+                "-r",
+                "bindings",
+                "--no-download",
+                "-t",
+                "buildpack.toml",
+            ];
+            argv.extend(extra);
+            let args = args::Parser::new().parse_args(argv);
+            let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+            DependencyMappingCommandHandler {
+                output: TestBuffer::new().writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd))
+        };
+
+        let res = run(&[]);
+        let unlocked = path::Path::new(lock::LOCKFILE).exists();
+        let locked = run(&["--lock", "ci.lock"]);
+        let lockfile = fs::read_to_string("ci.lock");
+        env::set_current_dir(cur_dir).unwrap();
+
+        assert!(res.is_ok(), "{:?}", res);
+        assert!(!unlocked, "the lockfile should only be written with --lock");
+        assert!(locked.is_ok(), "{:?}", locked);
+        assert!(lockfile.unwrap().contains("jdk"));
+    }
+
+    #[test]
+    fn given_patterns_remove_retired_keeps_what_they_leave_out() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let toml = tmpdir.path().join("buildpack.toml");
+        let jdk = "a".repeat(64);
+        let jre = "b".repeat(64);
+        fs::write(
+            &toml,
+            format!(
+                r#"[buildpack]
+                id = "jvm"
+                version = "1.0.0"
+
+                [[metadata.dependencies]]
+                id = "jdk"
+                version = "17.0.1"
+                uri = "https://example.com/jdk.tar.gz"
+                sha256 = "{jdk}"
+
+                [[metadata.dependencies]]
+                id = "jre"
+                version = "17.0.1"
+                uri = "https://example.com/jre.tar.gz"
+                sha256 = "{jre}"
+                "#
+            ),
+        )
+        .unwrap();
+        let toml = toml.to_string_lossy();
+        let root = tmpdir.path().join("bindings");
+        let root = root.to_string_lossy();
+
+        let run = |extra: &[&str]| {
+            let mut argv = vec![
+                "bt",
+                "dependency-mapping",
+                "-r",
+                &root,
+                "--no-download",
+                "-t",
+                &toml,
+            ];
+            argv.extend(extra);
+            let args = args::Parser::new().parse_args(argv);
+            let cmd = args.subcommand_matches("dependency-mapping").unwrap();
+            DependencyMappingCommandHandler {
+                output: TestBuffer::new().writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd))
+        };
+
+        let res = run(&[]);
+        assert!(res.is_ok(), "{:?}", res);
+        let res = run(&["--update", "--remove-retired", "--include", "jdk*"]);
+        assert!(res.is_ok(), "{:?}", res);
+
+        let binding = tmpdir.path().join("bindings/dependency-mapping");
+        assert!(binding.join(&jdk).exists());
+        assert!(
+            binding.join(&jre).exists(),
+            "jre is still in buildpack.toml"
+        );
+    }
+
+    #[test]
+    fn shell_detection() {
+        assert_eq!(detect_shell(Some("/bin/bash"), false).unwrap(), "bash");
+        assert_eq!(
+            detect_shell(Some("/usr/local/bin/fish"), false).unwrap(),
+            "fish"
+        );
+        assert_eq!(
+            detect_shell(Some("/opt/homebrew/bin/nu"), false).unwrap(),
+            "nu"
+        );
+        assert_eq!(
+            detect_shell(Some(r"C:\Program Files\PowerShell\7\pwsh.exe"), true).unwrap(),
+            "powershell"
+        );
+        assert_eq!(detect_shell(None, true).unwrap(), "powershell");
+        assert!(detect_shell(Some("/bin/tcsh"), false).is_err());
+        assert!(detect_shell(None, false).is_err());
+    }
+
+    #[test]
+    fn given_a_binding_args_outputs() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            // make some bindings, required
+
+            let bp = BindingProcessor::new(
+                &tmppath,
+                Some("some-type"),
+                Some("diff-name"),
+                BindingConfirmers::Never,
+            );
+            let res = bp.add_binding("key1=val1");
+            assert!(res.is_ok());
+
+            // check args
+            let args = args::Parser::new().parse_args(vec!["bt", "args", "--docker"]);
+            let cmd = args.subcommand_matches("args").unwrap();
+            let mut tb = TestBuffer::new();
+            let res = ArgsCommandHandler {
+                output: tb.writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            dbg!(&res);
+            assert!(res.is_ok(), "args handler should succeed");
+            assert_eq!(
+                tb.string().unwrap(),
+                format!(
+                    r#"--volume {}:/bindings:ro --env SERVICE_BINDING_ROOT=/bindings"#,
+                    tmppath
+                )
+            );
+        });
+    }
+
+    #[test]
+    fn given_no_bindings_args_outputs_nothing_unless_strict() {
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        for root in [tmpdir.path().to_path_buf(), tmpdir.path().join("missing")] {
+            temp_env::with_var("SERVICE_BINDING_ROOT", Some(&root), || {
+                let run = |argv: Vec<&str>| {
+                    let args = args::Parser::new().parse_args(argv);
+                    let cmd = args.subcommand_matches("args").unwrap();
+                    let mut tb = TestBuffer::new();
+                    let res = ArgsCommandHandler {
+                        output: tb.writer(),
+                        config: Default::default(),
+                    }
+                    .handle(Some(cmd));
+                    res.map(|_| tb.string().unwrap().to_owned())
+                };
+
+                assert_eq!(run(vec!["bt", "args", "-d"]).unwrap(), "");
+                assert!(
+                    run(vec!["bt", "args", "-d", "--strict"]).is_err(),
+                    "should fail without bindings"
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn given_writable_args_mount_the_bindings_read_write() {
         let tmpdir = tempfile::tempdir().unwrap();
         let tmppath = tmpdir.path().to_string_lossy();
 
-        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
-        let res = bp1.add_binding("key=val");
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp =
+                BindingProcessor::new(&tmppath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
+
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                assert!(res.is_ok(), "args handler should succeed");
+                tb.string().unwrap().to_owned()
+            };
 
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("testType/type").exists());
-        assert!(tmpdir.path().join("testType/key").exists());
+            assert_eq!(
+                run(vec!["bt", "args", "-d", "--read-only"]),
+                format!("--volume {tmppath}:/bindings:ro --env SERVICE_BINDING_ROOT=/bindings")
+            );
+            assert_eq!(
+                run(vec!["bt", "args", "-d", "--writable"]),
+                format!("--volume {tmppath}:/bindings --env SERVICE_BINDING_ROOT=/bindings")
+            );
+            assert_eq!(
+                run(vec!["bt", "args", "-p", "--writable"]),
+                format!("--volume {tmppath}:/platform/bindings:rw")
+            );
+        });
+    }
 
-        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
-        let res = bp1.add_binding("key=other_val");
-        assert!(res.is_err());
+    #[test]
+    fn given_a_selinux_label_args_relabel_the_bindings() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
 
-        let data = fs::read(tmpdir.path().join("testType/type"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"testType");
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp =
+                BindingProcessor::new(&tmppath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
+
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                assert!(res.is_ok(), "args handler should succeed");
+                tb.string().unwrap().to_owned()
+            };
 
-        let data = fs::read(tmpdir.path().join("testType/key"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"val");
+            assert_eq!(
+                run(vec!["bt", "args", "-d", "--selinux-label", "Z"]),
+                format!("--volume '{tmppath}:/bindings:ro,Z' --env SERVICE_BINDING_ROOT=/bindings")
+            );
+            assert_eq!(
+                run(vec!["bt", "args", "--podman", "--selinux-label", "Z"]),
+                format!("--volume '{tmppath}:/bindings:ro,Z' --env SERVICE_BINDING_ROOT=/bindings")
+            );
+            assert_eq!(
+                run(vec!["bt", "args", "-p", "--selinux-label", "z"]),
+                format!("--volume {tmppath}:/platform/bindings:z")
+            );
+        });
     }
 
     #[test]
-    fn given_duplicate_binding_but_different_key_adds_key_to_binding() {
+    fn given_a_binding_pack_args_mount_the_platform_bindings() {
         let tmpdir = tempfile::tempdir().unwrap();
         let tmppath = tmpdir.path().to_string_lossy();
 
-        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
-        let res = bp1.add_binding("key=val");
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp =
+                BindingProcessor::new(&tmppath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
 
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("testType/type").exists());
-        assert!(tmpdir.path().join("testType/key").exists());
+            let args = args::Parser::new().parse_args(vec!["bt", "args", "--pack"]);
+            let cmd = args.subcommand_matches("args").unwrap();
+            let mut tb = TestBuffer::new();
+            let res = ArgsCommandHandler {
+                output: tb.writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "args handler should succeed");
+            assert_eq!(
+                tb.string().unwrap(),
+                format!(r#"--volume {}:/platform/bindings"#, tmppath)
+            );
+        });
+    }
 
-        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
-        let res = bp1.add_binding("other_key=other_val");
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("testType/other_key").exists());
+    #[test]
+    fn given_a_binding_podman_args_relabel_the_bindings() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
 
-        let data = fs::read(tmpdir.path().join("testType/type"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"testType");
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp =
+                BindingProcessor::new(&tmppath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
 
-        let data = fs::read(tmpdir.path().join("testType/other_key"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"other_val");
+            let args = args::Parser::new().parse_args(vec!["bt", "args", "--podman"]);
+            let cmd = args.subcommand_matches("args").unwrap();
+            let mut tb = TestBuffer::new();
+            let res = ArgsCommandHandler {
+                output: tb.writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "args handler should succeed");
+            assert_eq!(
+                tb.string().unwrap(),
+                format!(
+                    r#"--volume '{}:/bindings:ro,z' --env SERVICE_BINDING_ROOT=/bindings"#,
+                    tmppath
+                )
+            );
+        });
     }
 
     #[test]
-    fn given_duplicate_binding_and_same_key_confirm_updates_key() {
+    fn given_a_binding_compose_args_output_a_service_fragment() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().join("my bindings");
+        fs::create_dir(&tmppath).unwrap();
+        let tmppath = tmppath.to_string_lossy();
+
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmppath.as_ref()), || {
+            let bp =
+                BindingProcessor::new(&tmppath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
+
+            let args = args::Parser::new().parse_args(vec![
+                "bt",
+                "args",
+                "--compose",
+                "--container-path",
+                "/custom/bindings",
+            ]);
+            let cmd = args.subcommand_matches("args").unwrap();
+            let mut tb = TestBuffer::new();
+            let res = ArgsCommandHandler {
+                output: tb.writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "args handler should succeed");
+            assert_eq!(
+                tb.string().unwrap(),
+                format!(
+                    "volumes:\n  - \"{tmppath}:/custom/bindings:ro\"\nenvironment:\n  SERVICE_BINDING_ROOT: \"/custom/bindings\"\n"
+                )
+            );
+        });
+    }
+
+    #[test]
+    fn given_a_binding_kubernetes_args_project_it_from_a_secret() {
         let tmpdir = tempfile::tempdir().unwrap();
         let tmppath = tmpdir.path().to_string_lossy();
 
-        let bp1 = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
-        let res = bp1.add_binding("key=val");
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp =
+                BindingProcessor::new(&tmppath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
+
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                assert!(res.is_ok(), "args handler should succeed");
+                tb.string().unwrap().to_owned()
+            };
+
+            let fragment = run(vec!["bt", "args", "--kubernetes"]);
+            assert!(fragment.contains("name: \"some-type\""), "{}", fragment);
+            assert!(
+                fragment.contains("path: \"some-type/key1\""),
+                "{}",
+                fragment
+            );
+            assert!(fragment.contains("value: \"/bindings\""), "{}", fragment);
+
+            let secrets = run(vec!["bt", "args", "--kubernetes", "--secrets"]);
+            assert!(secrets.contains("kind: Secret"), "{}", secrets);
+            assert!(
+                secrets.contains("type: \"servicebinding.io/some-type\""),
+                "{}",
+                secrets
+            );
+        });
+    }
 
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("testType/type").exists());
-        assert!(tmpdir.path().join("testType/key").exists());
+    #[test]
+    fn given_a_binding_env_file_args_project_its_keys() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
 
-        let bp1 =
-            BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Always);
-        let res = bp1.add_binding("key=new_val");
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("testType/key").exists());
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp = BindingProcessor::new(
+                &tmppath,
+                Some("mysql"),
+                Some("my-db"),
+                BindingConfirmers::Never,
+            );
+            assert!(bp.add_binding("user.name=admin").is_ok());
+            assert!(bp.add_binding("provider=bitnami").is_ok());
+            assert!(bp.add_binding("cert=line1\nline2").is_ok());
 
-        let data = fs::read(tmpdir.path().join("testType/type"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"testType");
+            let args = args::Parser::new().parse_args(vec!["bt", "args", "--env-file"]);
+            let cmd = args.subcommand_matches("args").unwrap();
+            let mut tb = TestBuffer::new();
+            let res = ArgsCommandHandler {
+                output: tb.writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "args handler should succeed");
+            assert_eq!(tb.string().unwrap(), "MY_DB_USER_NAME=admin\n");
+        });
+    }
 
-        let data = fs::read(tmpdir.path().join("testType/key"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"new_val");
+    #[test]
+    fn given_a_binding_buildkit_secret_args_pass_each_key() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
+
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp = BindingProcessor::new(
+                &tmppath,
+                Some("mysql"),
+                Some("my-db"),
+                BindingConfirmers::Never,
+            );
+            assert!(bp.add_binding("pass_word=secret").is_ok());
+
+            let args = args::Parser::new().parse_args(vec!["bt", "args", "--buildkit-secret"]);
+            let cmd = args.subcommand_matches("args").unwrap();
+            let mut tb = TestBuffer::new();
+            let res = ArgsCommandHandler {
+                output: tb.writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "args handler should succeed");
+            assert_eq!(
+                tb.string().unwrap(),
+                format!(
+                    "--secret 'id=binding-my-db-pass-word,src={tmppath}/my-db/pass_word' --secret 'id=binding-my-db-type,src={tmppath}/my-db/type'"
+                )
+            );
+        });
     }
 
     #[test]
-    fn given_binding_args_with_name_it_creates_binding_using_name() {
+    fn env_names() {
+        assert_eq!(env_name("my-db", "username"), "MY_DB_USERNAME");
+        assert_eq!(env_name("1db", "tls.crt"), "_1DB_TLS_CRT");
+    }
+
+    #[test]
+    fn given_selected_bindings_args_mount_only_those() {
         let tmpdir = tempfile::tempdir().unwrap();
         let tmppath = tmpdir.path().to_string_lossy();
 
-        let bp = BindingProcessor::new(
-            &tmppath,
-            Some("testType"),
-            Some("diff-name"),
-            BindingConfirmers::Never,
-        );
-        let res = bp.add_binding("key=val");
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            for name in ["my-db", "certs", "secrets"] {
+                let bp = BindingProcessor::new(
+                    &tmppath,
+                    Some("some-type"),
+                    Some(name),
+                    BindingConfirmers::Never,
+                );
+                assert!(bp.add_binding("key1=val1").is_ok());
+            }
 
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("diff-name/type").exists());
-        assert!(tmpdir.path().join("diff-name/key").exists());
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                res.map(|_| tb.string().unwrap().to_owned())
+            };
 
-        let data = fs::read(tmpdir.path().join("diff-name/type"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"testType");
+            assert_eq!(
+                run(vec!["bt", "args", "-d", "--name", "my-db", "--name", "certs"]).unwrap(),
+                format!(
+                    "--volume {tmppath}/certs:/bindings/certs:ro --volume {tmppath}/my-db:/bindings/my-db:ro --env SERVICE_BINDING_ROOT=/bindings"
+                )
+            );
+            assert_eq!(
+                run(vec!["bt", "args", "-p", "-n", "certs"]).unwrap(),
+                format!("--volume {tmppath}/certs:/platform/bindings/certs")
+            );
+            assert_eq!(
+                run(vec!["bt", "args", "--env-file", "-n", "certs"]).unwrap(),
+                "CERTS_KEY1=val1\n"
+            );
 
-        let data = fs::read(tmpdir.path().join("diff-name/key"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"val");
+            let res = run(vec!["bt", "args", "-d", "-n", "missing"]);
+            assert!(res.is_err(), "should fail for a missing binding");
+        });
     }
 
     #[test]
-    #[serial(requires_cwd)]
-    fn given_binding_args_with_value_relative_file_creates_binding_using_file_contents() {
+    fn given_per_binding_args_mount_each_binding() {
         let tmpdir = tempfile::tempdir().unwrap();
         let tmppath = tmpdir.path().to_string_lossy();
 
-        let res = fs::write(tmpdir.path().join("val"), "actual value");
-        assert!(res.is_ok());
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            for name in ["my-db", "certs"] {
+                let bp = BindingProcessor::new(
+                    &tmppath,
+                    Some("some-type"),
+                    Some(name),
+                    BindingConfirmers::Never,
+                );
+                assert!(bp.add_binding("key1=val1").is_ok());
+            }
+            fs::write(tmpdir.path().join("notes.txt"), "not a binding").unwrap();
+            fs::create_dir(tmpdir.path().join("scratch")).unwrap();
 
-        let cur_dir = env::current_dir();
-        assert!(res.is_ok());
+            let args = args::Parser::new().parse_args(vec!["bt", "args", "-d", "--per-binding"]);
+            let cmd = args.subcommand_matches("args").unwrap();
+            let mut tb = TestBuffer::new();
+            let res = ArgsCommandHandler {
+                output: tb.writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            assert!(res.is_ok(), "args handler should succeed");
+            assert_eq!(
+                tb.string().unwrap(),
+                format!(
+                    "--volume {tmppath}/certs:/bindings/certs:ro --volume {tmppath}/my-db:/bindings/my-db:ro --env SERVICE_BINDING_ROOT=/bindings"
+                )
+            );
+        });
+    }
 
-        let res = env::set_current_dir(&tmpdir);
-        assert!(res.is_ok());
+    #[test]
+    fn given_a_format_args_render_it() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
 
-        let bp = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
-        let res = bp.add_binding("key=@val");
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            for name in ["my-db", "certs"] {
+                let bp = BindingProcessor::new(
+                    &tmppath,
+                    Some("some-type"),
+                    Some(name),
+                    BindingConfirmers::Never,
+                );
+                assert!(bp.add_binding("key1=val1").is_ok());
+            }
 
-        {
-            let res = env::set_current_dir(cur_dir.unwrap());
-            assert!(res.is_ok());
-        }
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                res.map(|_| tb.string().unwrap().to_owned())
+            };
 
-        assert!(res.is_ok(), "{}", res.unwrap_err());
-        assert!(tmpdir.path().join("testType/type").exists());
-        assert!(tmpdir.path().join("testType/key").exists());
+            assert_eq!(
+                run(vec![
+                    "bt",
+                    "args",
+                    "--format",
+                    "--mount type=bind,src={root},dst={dst}"
+                ])
+                .unwrap(),
+                format!("--mount 'type=bind,src={tmppath},dst=/bindings'")
+            );
+            assert_eq!(
+                run(vec!["bt", "args", "--format", "-v {root}:/b/{name}"]).unwrap(),
+                format!("-v {tmppath}/certs:/b/certs -v {tmppath}/my-db:/b/my-db")
+            );
+            assert!(run(vec!["bt", "args", "--format", "{src}"]).is_err());
+        });
+    }
 
-        let data = fs::read(tmpdir.path().join("testType/type"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"testType");
+    #[test]
+    fn mountable_paths() {
+        assert_eq!(
+            mountable_path(r"C:\Users\me\bindings", true),
+            "//c/Users/me/bindings"
+        );
+        assert_eq!(mountable_path(r"\\server\share", true), "//server/share");
+        assert_eq!(
+            mountable_path("/home/me/bindings", true),
+            "/home/me/bindings"
+        );
+        assert_eq!(
+            mountable_path(r"C:\Users\me\bindings", false),
+            r"C:\Users\me\bindings"
+        );
+    }
 
-        let data = fs::read(tmpdir.path().join("testType/key"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"actual value");
+    #[test]
+    fn format_rendering() {
+        assert_eq!(
+            render_format("src={root},dst={dst}", "/a", "/b", "").unwrap(),
+            "src=/a,dst=/b"
+        );
+        assert_eq!(render_format("{name}", "/a", "/b", "db").unwrap(), "db");
+        assert!(render_format("{root", "/a", "/b", "").is_err());
+        assert!(render_format("{other}", "/a", "/b", "").is_err());
     }
 
     #[test]
-    fn given_binding_args_with_value_full_file_path_creates_binding_using_file_contents() {
+    fn given_a_binding_testcontainers_args_output_code() {
         let tmpdir = tempfile::tempdir().unwrap();
         let tmppath = tmpdir.path().to_string_lossy();
 
-        let res = fs::create_dir_all(tmpdir.path().join("test"));
-        assert!(res.is_ok());
-
-        let val_path = tmpdir.path().join("test/val");
-        let res = fs::write(tmpdir.path().join("test/val"), "actual value");
-        assert!(res.is_ok());
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp =
+                BindingProcessor::new(&tmppath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
+
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                assert!(res.is_ok(), "args handler should succeed");
+                tb.string().unwrap().to_owned()
+            };
 
-        let bp = BindingProcessor::new(&tmppath, Some("testType"), None, BindingConfirmers::Never);
-        let res = bp.add_binding(format!("key=@{}", val_path.to_string_lossy()));
+            assert_eq!(
+                run(vec!["bt", "args", "--testcontainers", "java"]),
+                format!(
+                    ".withFileSystemBind(\"{tmppath}\", \"/bindings\", BindMode.READ_ONLY)\n.withEnv(\"SERVICE_BINDING_ROOT\", \"/bindings\")\n"
+                )
+            );
+            assert_eq!(
+                run(vec!["bt", "args", "--testcontainers", "go"]),
+                format!(
+                    "HostConfigModifier: func(hc *container.HostConfig) {{\n\thc.Binds = append(hc.Binds, \"{tmppath}:/bindings:ro\")\n}},\nEnv: map[string]string{{\"SERVICE_BINDING_ROOT\": \"/bindings\"}},\n"
+                )
+            );
+        });
+    }
 
-        assert!(res.is_ok(), "{}", res.unwrap_err());
-        assert!(tmpdir.path().join("testType/type").exists());
-        assert!(tmpdir.path().join("testType/key").exists());
+    #[test]
+    fn given_a_binding_dev_loop_formats_apply_the_secrets() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
 
-        let data = fs::read(tmpdir.path().join("testType/type"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"testType");
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
+            let bp =
+                BindingProcessor::new(&tmppath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
+
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                assert!(res.is_ok(), "args handler should succeed");
+                tb.string().unwrap().to_owned()
+            };
 
-        let data = fs::read(tmpdir.path().join("testType/key"));
-        assert!(data.is_ok());
-        assert_eq!(data.unwrap(), b"actual value");
+            assert_eq!(
+                run(vec!["bt", "args", "--format", "tilt", "-n", "some-type"]),
+                format!(
+                    "# add the output of `bt args --kubernetes --name some-type` to the Pod spec of the workloads\n\
+                    k8s_yaml(local(\"bt args --kubernetes --secrets --name some-type\", quiet=True, env={{\"SERVICE_BINDING_ROOT\": \"{tmppath}\"}}))\n\
+                    watch_file(\"{tmppath}\")\n"
+                )
+            );
+            let skaffold = run(vec!["bt", "args", "--format", "skaffold"]);
+            assert!(
+                skaffold.contains(&format!(
+                    "command: [\"sh\", \"-c\", \"SERVICE_BINDING_ROOT={tmppath} bt args --kubernetes --secrets | kubectl apply -f -\"]"
+                )),
+                "{}",
+                skaffold
+            );
+        });
     }
 
     #[test]
-    fn given_binding_it_deletes_the_binding() {
+    fn given_a_container_path_args_mount_the_bindings_there() {
         let tmpdir = tempfile::tempdir().unwrap();
         let tmppath = tmpdir.path().to_string_lossy();
 
-        let bp = BindingProcessor::new(
-            &tmppath,
-            Some("some-type"),
-            Some("diff-name"),
-            BindingConfirmers::Always,
+        temp_env::with_vars(
+            [
+                ("SERVICE_BINDING_ROOT", Some(tmppath.as_ref())),
+                ("BT_CONTAINER_PATH", Some("/env/bindings")),
+            ],
+            || {
+                let bp = BindingProcessor::new(
+                    &tmppath,
+                    Some("some-type"),
+                    None,
+                    BindingConfirmers::Never,
+                );
+                assert!(bp.add_binding("key1=val1").is_ok());
+
+                let run = |argv: Vec<&str>| {
+                    let args = args::Parser::new().parse_args(argv);
+                    let cmd = args.subcommand_matches("args").unwrap();
+                    let mut tb = TestBuffer::new();
+                    let res = ArgsCommandHandler {
+                        output: tb.writer(),
+                        config: Default::default(),
+                    }
+                    .handle(Some(cmd));
+                    res.map(|_| tb.string().unwrap().to_owned())
+                };
+
+                assert_eq!(
+                    run(vec!["bt", "args", "-p", "--container-path", "/custom/bindings"]).unwrap(),
+                    format!(
+                        "--volume {tmppath}:/custom/bindings:ro --env SERVICE_BINDING_ROOT=/custom/bindings"
+                    )
+                );
+                assert_eq!(
+                    run(vec!["bt", "args", "-d"]).unwrap(),
+                    format!(
+                        "--volume {tmppath}:/env/bindings:ro --env SERVICE_BINDING_ROOT=/env/bindings"
+                    )
+                );
+                assert!(run(vec!["bt", "args", "-d", "--container-path", "bindings"]).is_err());
+            },
         );
-        let res = bp.add_binding("key=val");
-
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("diff-name/type").exists());
-        assert!(tmpdir.path().join("diff-name/key").exists());
-
-        let tmp: Vec<&str> = vec![];
-        let res = bp.delete_bindings(tmp.into_iter());
-        assert!(res.is_ok());
-        assert!(!tmpdir.path().join("diff-name/type").exists());
-        assert!(!tmpdir.path().join("diff-name/key").exists());
     }
 
     #[test]
-    fn given_a_binding_and_user_declines_it_doesnt_delete_the_binding() {
+    fn given_a_binding_root_with_spaces_args_are_quoted() {
         let tmpdir = tempfile::tempdir().unwrap();
-        let tmppath = tmpdir.path().to_string_lossy();
+        let root = tmpdir.path().join("it's my bindings");
+        let rootpath = root.to_string_lossy();
+
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(&root), || {
+            let bp =
+                BindingProcessor::new(&rootpath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
+
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                assert!(res.is_ok(), "args handler should succeed");
+                tb.string().unwrap().to_owned()
+            };
 
-        let bp = BindingProcessor::new(
-            &tmppath,
-            Some("some-type"),
-            Some("diff-name"),
-            BindingConfirmers::Never,
-        );
-        let res = bp.add_binding("key=val");
+            let quoted = rootpath.replace('\'', r"'\''");
+            assert_eq!(
+                run(vec!["bt", "args", "--docker"]),
+                format!("--volume '{quoted}:/bindings:ro' --env SERVICE_BINDING_ROOT=/bindings")
+            );
+            let quoted = rootpath.replace('\'', "''");
+            assert_eq!(
+                run(vec!["bt", "args", "--pack", "--shell", "powershell"]),
+                format!("--volume '{quoted}:/platform/bindings'")
+            );
+        });
+    }
 
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("diff-name/type").exists());
-        assert!(tmpdir.path().join("diff-name/key").exists());
+    #[test]
+    fn given_a_binding_root_with_spaces_json_and_null_args_arent_quoted() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("it's my bindings");
+        let rootpath = root.to_string_lossy();
+
+        temp_env::with_var("SERVICE_BINDING_ROOT", Some(&root), || {
+            let bp =
+                BindingProcessor::new(&rootpath, Some("some-type"), None, BindingConfirmers::Never);
+            assert!(bp.add_binding("key1=val1").is_ok());
+
+            let run = |argv: Vec<&str>| {
+                let args = args::Parser::new().parse_args(argv);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                assert!(res.is_ok(), "args handler should succeed");
+                tb.string().unwrap().to_owned()
+            };
 
-        let tmp: Vec<&str> = vec![];
-        let res = bp.delete_bindings(tmp.into_iter());
-        assert!(res.is_err());
-        assert!(tmpdir.path().join("diff-name/type").exists());
-        assert!(tmpdir.path().join("diff-name/key").exists());
+            let json: Vec<String> =
+                serde_json::from_str(&run(vec!["bt", "args", "-d", "--json"])).unwrap();
+            assert_eq!(
+                json,
+                vec![
+                    "--volume".to_owned(),
+                    format!("{rootpath}:/bindings:ro"),
+                    "--env".to_owned(),
+                    "SERVICE_BINDING_ROOT=/bindings".to_owned(),
+                ]
+            );
+            assert_eq!(
+                run(vec!["bt", "args", "-p", "-z"]),
+                format!("--volume\0{rootpath}:/platform/bindings\0")
+            );
+        });
     }
 
     #[test]
-    fn given_binding_and_key_it_deletes_the_specific_binding_key_only() {
+    fn given_a_remote_daemon_args_still_mount_the_binding_root() {
         let tmpdir = tempfile::tempdir().unwrap();
         let tmppath = tmpdir.path().to_string_lossy();
 
-        let bp = BindingProcessor::new(
-            &tmppath,
-            Some("some-type"),
-            Some("diff-name"),
-            BindingConfirmers::Always,
+        temp_env::with_vars(
+            [
+                ("SERVICE_BINDING_ROOT", Some(tmppath.as_ref())),
+                ("DOCKER_HOST", Some("ssh://me@build-box")),
+            ],
+            || {
+                let bp = BindingProcessor::new(
+                    &tmppath,
+                    Some("some-type"),
+                    None,
+                    BindingConfirmers::Never,
+                );
+                assert!(bp.add_binding("key1=val1").is_ok());
+
+                let args = args::Parser::new().parse_args(vec!["bt", "args", "-d"]);
+                let cmd = args.subcommand_matches("args").unwrap();
+                let mut tb = TestBuffer::new();
+                let res = ArgsCommandHandler {
+                    output: tb.writer(),
+                    config: Default::default(),
+                }
+                .handle(Some(cmd));
+                assert!(res.is_ok(), "args handler should succeed");
+                assert_eq!(
+                    tb.string().unwrap(),
+                    format!("--volume {tmppath}:/bindings:ro --env SERVICE_BINDING_ROOT=/bindings")
+                );
+            },
         );
-        let res = bp.add_binding("key1=val1");
-        assert!(res.is_ok());
+    }
 
-        let res = bp.add_binding("key2=val2");
-        assert!(res.is_ok());
+    #[cfg(unix)]
+    #[test]
+    #[serial(requires_path)]
+    fn given_a_volume_name_args_mount_the_populated_volume() {
+        use std::os::unix::fs::PermissionsExt;
 
-        assert!(tmpdir.path().join("diff-name/type").exists());
-        assert!(tmpdir.path().join("diff-name/key1").exists());
-        assert!(tmpdir.path().join("diff-name/key2").exists());
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("bindings");
+        let rootpath = root.to_string_lossy();
+        let bin = tmpdir.path().join("bin");
+        fs::create_dir(&bin).unwrap();
+        let log = tmpdir.path().join("docker.log");
+        // the volume exists when there's a file with its labels
+        let labels = tmpdir.path().join("labels");
+        let docker = bin.join("docker");
+        fs::write(
+            &docker,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> '{}'\n\
+                [ \"$2\" = inspect ] && {{ cat '{}' || exit 1; }}\n\
+                [ \"$2\" = create ] && [ \"$1\" = container ] && echo helper-id\nexit 0\n",
+                log.to_string_lossy(),
+                labels.to_string_lossy()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&docker, fs::Permissions::from_mode(0o755)).unwrap();
+        let path = format!(
+            "{}:{}",
+            bin.to_string_lossy(),
+            env::var("PATH").unwrap_or_default()
+        );
 
-        let tmp: Vec<&str> = vec!["key1"];
-        let res = bp.delete_bindings(tmp.into_iter());
-        assert!(res.is_ok());
-        assert!(tmpdir.path().join("diff-name/type").exists());
-        assert!(!tmpdir.path().join("diff-name/key1").exists());
-        assert!(tmpdir.path().join("diff-name/key2").exists());
+        temp_env::with_vars(
+            [
+                ("SERVICE_BINDING_ROOT", Some(rootpath.as_ref())),
+                ("PATH", Some(path.as_str())),
+                ("BT_CONTAINER_PATH", None),
+            ],
+            || {
+                let bp = BindingProcessor::new(
+                    &rootpath,
+                    Some("some-type"),
+                    None,
+                    BindingConfirmers::Never,
+                );
+                assert!(bp.add_binding("key1=val1").is_ok());
+
+                let run = |extra: &[&str]| {
+                    let _ = fs::remove_file(&log);
+                    let mut argv = vec!["bt", "args", "-d", "--volume-name", "my-bindings"];
+                    argv.extend(extra);
+                    let args = args::Parser::new().parse_args(argv);
+                    let cmd = args.subcommand_matches("args").unwrap();
+                    let mut tb = TestBuffer::new();
+                    let res = ArgsCommandHandler {
+                        output: tb.writer(),
+                        config: Default::default(),
+                    }
+                    .handle(Some(cmd));
+                    (
+                        res,
+                        tb.string().unwrap().to_owned(),
+                        fs::read_to_string(&log).unwrap(),
+                    )
+                };
+                let inspect =
+                    "volume inspect --format {{range $k, $v := .Labels}}{{$k}} {{end}} my-bindings\n";
+                let copy = format!(
+                    "container create --volume my-bindings:/bindings busybox\n\
+                    cp {rootpath}/. helper-id:/bindings\n\
+                    container rm helper-id\n"
+                );
+                let create =
+                    "volume create --label com.github.dmikusa.binding-tool=bindings my-bindings\n";
+
+                // a new volume is created with the label
+                let (res, out, calls) = run(&[]);
+                assert!(res.is_ok(), "args handler should succeed: {:?}", res);
+                assert_eq!(
+                    out,
+                    "--volume my-bindings:/bindings:ro --env SERVICE_BINDING_ROOT=/bindings"
+                );
+                assert_eq!(calls, format!("{inspect}{create}{copy}"));
+
+                // one that bt created is replaced
+                fs::write(&labels, "com.github.dmikusa.binding-tool ").unwrap();
+                let (res, _, calls) = run(&[]);
+                assert!(res.is_ok(), "args handler should succeed: {:?}", res);
+                assert_eq!(
+                    calls,
+                    format!("{inspect}volume rm my-bindings\n{create}{copy}")
+                );
+
+                // any other one is kept, and only written to with --yes
+                fs::write(&labels, "").unwrap();
+                let (res, _, calls) = run(&[]);
+                assert!(res.is_err());
+                assert_eq!(calls, inspect);
+
+                let (res, _, calls) = run(&["--yes"]);
+                assert!(res.is_ok(), "args handler should succeed: {:?}", res);
+                assert_eq!(calls, format!("{inspect}{copy}"));
+            },
+        );
     }
 
+    #[cfg(unix)]
     #[test]
-    fn given_binding_and_key_and_user_declines_it_doesnt_delete_the_specific_binding_key() {
-        let tmpdir = tempfile::tempdir().unwrap();
-        let tmppath = tmpdir.path().to_string_lossy();
+    #[serial(requires_path)]
+    fn given_a_binding_inject_copies_it_into_the_container() {
+        use std::os::unix::fs::PermissionsExt;
 
-        let bp = BindingProcessor::new(
-            &tmppath,
-            Some("some-type"),
-            Some("diff-name"),
-            BindingConfirmers::Never,
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("bindings");
+        let rootpath = root.to_string_lossy();
+        let bin = tmpdir.path().join("bin");
+        fs::create_dir(&bin).unwrap();
+        let log = tmpdir.path().join("docker.log");
+        let docker = bin.join("docker");
+        fs::write(
+            &docker,
+            format!("#!/bin/sh\necho \"$@\" > '{}'\n", log.to_string_lossy()),
+        )
+        .unwrap();
+        fs::set_permissions(&docker, fs::Permissions::from_mode(0o755)).unwrap();
+        let path = format!(
+            "{}:{}",
+            bin.to_string_lossy(),
+            env::var("PATH").unwrap_or_default()
         );
-        let res = bp.add_binding("key1=val1");
-        assert!(res.is_ok());
 
-        let res = bp.add_binding("key2=val2");
-        assert!(res.is_ok());
+        temp_env::with_vars(
+            [
+                ("SERVICE_BINDING_ROOT", Some(rootpath.as_ref())),
+                ("PATH", Some(path.as_str())),
+                ("BT_CONTAINER_PATH", None),
+            ],
+            || {
+                let run = |argv: Vec<&str>| {
+                    let args = args::Parser::new().parse_args(argv);
+                    let cmd = args.subcommand_matches("inject").unwrap();
+                    let mut tb = TestBuffer::new();
+                    let res = InjectCommandHandler {
+                        output: tb.writer(),
+                        config: Default::default(),
+                    }
+                    .handle(Some(cmd));
+                    res.map(|_| tb.string().unwrap().to_owned())
+                };
+
+                assert!(
+                    run(vec!["bt", "inject", "my-app"]).is_err(),
+                    "should fail without bindings"
+                );
+
+                let bp = BindingProcessor::new(
+                    &rootpath,
+                    Some("some-type"),
+                    None,
+                    BindingConfirmers::Never,
+                );
+                assert!(bp.add_binding("key1=val1").is_ok());
+
+                let out = run(vec!["bt", "inject", "my-app"]).unwrap();
+                assert!(
+                    out.contains("docker exec --env SERVICE_BINDING_ROOT=/bindings my-app"),
+                    "{}",
+                    out
+                );
+                assert_eq!(
+                    fs::read_to_string(&log).unwrap(),
+                    format!("cp {rootpath}/. my-app:/bindings\n")
+                );
+            },
+        );
+    }
 
-        assert!(tmpdir.path().join("diff-name/type").exists());
-        assert!(tmpdir.path().join("diff-name/key1").exists());
-        assert!(tmpdir.path().join("diff-name/key2").exists());
+    #[test]
+    fn given_user_args_args_dont_repeat_them() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let tmppath = tmpdir.path().to_string_lossy();
 
-        let tmp: Vec<&str> = vec!["key1"];
-        let res = bp.delete_bindings(tmp.into_iter());
-        assert!(res.is_err());
-        assert!(tmpdir.path().join("diff-name/type").exists());
-        assert!(tmpdir.path().join("diff-name/key1").exists());
-        assert!(tmpdir.path().join("diff-name/key2").exists());
+        temp_env::with_vars(
+            [
+                ("SERVICE_BINDING_ROOT", Some(tmppath.as_ref())),
+                ("BT_CONTAINER_PATH", None),
+            ],
+            || {
+                let bp = BindingProcessor::new(
+                    &tmppath,
+                    Some("some-type"),
+                    None,
+                    BindingConfirmers::Never,
+                );
+                assert!(bp.add_binding("key1=val1").is_ok());
+
+                let run = |argv: Vec<&str>| {
+                    let args = args::Parser::new().parse_args(argv);
+                    let cmd = args.subcommand_matches("args").unwrap();
+                    let mut tb = TestBuffer::new();
+                    let res = ArgsCommandHandler {
+                        output: tb.writer(),
+                        config: Default::default(),
+                    }
+                    .handle(Some(cmd));
+                    assert!(res.is_ok(), "args handler should succeed");
+                    tb.string().unwrap().to_owned()
+                };
+
+                assert_eq!(
+                    run(vec![
+                        "bt",
+                        "args",
+                        "-d",
+                        "--",
+                        "--env=SERVICE_BINDING_ROOT=/custom",
+                        "my-app"
+                    ]),
+                    format!("--volume {tmppath}:/custom:ro")
+                );
+                assert_eq!(
+                    run(vec![
+                        "bt",
+                        "args",
+                        "-d",
+                        "--",
+                        "-v",
+                        "/elsewhere:/bindings",
+                        "my-app"
+                    ]),
+                    "--env SERVICE_BINDING_ROOT=/bindings"
+                );
+                assert_eq!(
+                    run(vec!["bt", "args", "-d", "--", "-p", "8080:8080", "my-app"]),
+                    format!("--volume {tmppath}:/bindings:ro --env SERVICE_BINDING_ROOT=/bindings")
+                );
+            },
+        );
     }
 
     #[test]
-    fn given_a_binding_init_outputs_fish_script() {
-        // check args
-        let args = args::Parser::new().parse_args(vec!["bt", "init", "fish"]);
-        let cmd = args.subcommand_matches("init").unwrap();
-        let mut tb = TestBuffer::new();
-        let res = InitCommandHandler {
-            output: tb.writer(),
-        }
-        .handle(Some(cmd));
-        assert!(res.is_ok(), "init handler should succeed");
+    fn flags_of_user_args() {
+        let words = |w: &[&str]| w.iter().map(|s| s.to_string()).collect::<Vec<_>>();
         assert_eq!(
-            tb.string().unwrap().trim_end(),
-            include_str!("scripts/fish.sh")
+            user_flags(&words(&[
+                "-e",
+                "SERVICE_BINDING_ROOT=/b",
+                "--volume",
+                r"C:\b:/c:ro",
+                "--mount",
+                "type=bind,src=/x,dst=/d",
+                "-v=/e:/f",
+            ])),
+            (
+                Some("/b".to_owned()),
+                vec!["/c".to_owned(), "/d".to_owned()]
+            )
         );
+        assert_eq!(user_flags(&words(&["-e", "OTHER=1"])), (None, vec![]));
     }
 
     #[test]
-    fn given_a_binding_init_outputs_bash_script() {
-        // check args
-        let args = args::Parser::new().parse_args(vec!["bt", "init", "bash"]);
-        let cmd = args.subcommand_matches("init").unwrap();
-        let mut tb = TestBuffer::new();
-        let res = InitCommandHandler {
-            output: tb.writer(),
+    fn given_a_der_certificate_ca_certs_adds_it_as_pem() {
+        use rustls::pki_types::{pem::PemObject, CertificateDer};
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("bindings");
+        let der = tmpdir.path().join("root.der");
+        let cert = CertificateDer::from_pem_slice(certs::tests::TEST_ROOT_CA.as_bytes()).unwrap();
+        fs::write(&der, cert.as_ref()).unwrap();
+
+        let args = args::Parser::new().parse_args(vec![
+            "bt",
+            "ca-certs",
+            "-r",
+            root.to_str().unwrap(),
+            "-c",
+            der.to_str().unwrap(),
+        ]);
+        let cmd = args.subcommand_matches("ca-certs").unwrap();
+        let res = CaCertsCommandHandler {
+            output: TestBuffer::new().writer(),
+            config: Default::default(),
         }
         .handle(Some(cmd));
-        assert!(res.is_ok(), "init handler should succeed");
-        assert_eq!(
-            tb.string().unwrap().trim_end(),
-            include_str!("scripts/bash.sh"),
-        );
+        assert!(res.is_ok(), "{:?}", res);
+
+        let added = fs::read_to_string(root.join("ca-certificates/root.der")).unwrap();
+        assert_eq!(certs::count(&added), 1);
+        temp_env::with_var_unset("BT_CA_BUNDLE", || {
+            let client = deps::configure_client(&root, Default::default());
+            assert!(client.is_ok(), "{:?}", client.err());
+        });
     }
 
     #[test]
-    fn given_a_binding_init_outputs_zsh_script() {
-        // check args
-        let args = args::Parser::new().parse_args(vec!["bt", "init", "zsh"]);
-        let cmd = args.subcommand_matches("init").unwrap();
-        let mut tb = TestBuffer::new();
-        let res = InitCommandHandler {
-            output: tb.writer(),
-        }
-        .handle(Some(cmd));
-        assert!(res.is_ok(), "init handler should succeed");
+    fn given_expiring_certificates_ca_certs_check_fails() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("bindings");
+        let binding = root.join("ca-certificates");
+        fs::create_dir_all(&binding).unwrap();
+        fs::write(binding.join("type"), "ca-certificates").unwrap();
+        fs::write(binding.join("root.pem"), certs::tests::TEST_ROOT_CA).unwrap();
+
+        let check = |extra: &[&str]| {
+            let mut argv = vec!["bt", "-r", root.to_str().unwrap(), "ca-certs", "check"];
+            argv.extend(extra);
+            let args = args::Parser::new().parse_args(argv);
+            let cmd = args.subcommand_matches("ca-certs").unwrap();
+            let mut tb = TestBuffer::new();
+            let res = CaCertsCommandHandler {
+                output: tb.writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd));
+            (res, tb.string().unwrap().to_owned())
+        };
+
+        let (res, out) = check(&[]);
+        assert!(res.is_ok(), "{:?}", res);
+        assert_eq!(
+            out,
+            "All 1 certificates are valid for at least 30 more days\n"
+        );
+
+        // the root is valid until 2126, so it's inside a window of 40000 days
+        fs::write(binding.join("expired.pem"), certs::tests::TEST_EXPIRED_CA).unwrap();
+        let (res, out) = check(&["--warn-days", "40000"]);
+        let err = res.unwrap_err().to_string();
         assert_eq!(
-            tb.string().unwrap().trim_end(),
-            include_str!("scripts/zsh.sh").trim_end()
+            err,
+            "2 of 2 certificates have expired or expire within 40000 days"
+        );
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", out);
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.starts_with("ca-certificates/expired.pem: ")
+                    && l.contains("Test Expired CA")
+                    && l.ends_with(" has expired")),
+            "{}",
+            out
+        );
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.starts_with("ca-certificates/root.pem: ")
+                    && l.contains("Test Root CA")
+                    && l.contains(" expires in ")),
+            "{}",
+            out
         );
+
+        // only the expired one is outside of the default window
+        let (res, out) = check(&[]);
+        assert!(res.is_err());
+        assert_eq!(out.lines().count(), 1, "{}", out);
     }
 
     #[test]
-    fn given_a_binding_args_outputs() {
+    fn given_a_fingerprint_ca_certs_remove_needs_one_match() {
         let tmpdir = tempfile::tempdir().unwrap();
-        let tmppath = tmpdir.path().to_string_lossy();
-
-        temp_env::with_var("SERVICE_BINDING_ROOT", Some(tmpdir.as_ref()), || {
-            // make some bindings, required
+        let root = tmpdir.path().join("bindings");
+        for binding in ["ca-certificates", "more-certs"] {
+            fs::create_dir_all(root.join(binding)).unwrap();
+            fs::write(root.join(binding).join("type"), "ca-certificates").unwrap();
+            fs::write(
+                root.join(binding).join("root.pem"),
+                certs::tests::TEST_ROOT_CA,
+            )
+            .unwrap();
+        }
+        let expired = root.join("ca-certificates/expired.pem");
+        fs::write(&expired, certs::tests::TEST_EXPIRED_CA).unwrap();
+
+        let remove = |extra: &[&str]| {
+            let mut argv = vec![
+                "bt",
+                "-r",
+                root.to_str().unwrap(),
+                "ca-certs",
+                "remove",
+                "-f",
+            ];
+            argv.extend(extra);
+            let args = args::Parser::new().parse_args(argv);
+            let cmd = args.subcommand_matches("ca-certs").unwrap();
+            CaCertsCommandHandler {
+                output: TestBuffer::new().writer(),
+                config: Default::default(),
+            }
+            .handle(Some(cmd))
+        };
 
-            let bp = BindingProcessor::new(
-                &tmppath,
-                Some("some-type"),
-                Some("diff-name"),
-                BindingConfirmers::Never,
-            );
-            let res = bp.add_binding("key1=val1");
-            assert!(res.is_ok());
+        for fingerprint in ["", ":", "2a9f", "zzzzzzzzzz"] {
+            let res = remove(&["--fingerprint", fingerprint]);
+            assert!(res.is_err(), "{} should be refused", fingerprint);
+        }
+        assert!(expired.exists());
+
+        let fingerprint = &certs::fingerprints(certs::tests::TEST_EXPIRED_CA.as_bytes())[0];
+        let res = remove(&["--fingerprint", &fingerprint[..8]]);
+        assert!(res.is_ok(), "{:?}", res);
+        assert!(!expired.exists());
+
+        // the root is in both bindings
+        let res = remove(&["--cn", "Test Root CA"]);
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains("2 certificates match"), "{}", err);
+        assert!(root.join("more-certs/root.pem").exists());
+
+        let res = remove(&["--cn", "Test Root CA", "--all"]);
+        assert!(res.is_ok(), "{:?}", res);
+        assert!(!root.join("ca-certificates/root.pem").exists());
+        assert!(!root.join("more-certs/root.pem").exists());
+    }
 
-            // check args
-            let args = args::Parser::new().parse_args(vec!["bt", "args", "--docker"]);
-            let cmd = args.subcommand_matches("args").unwrap();
+    #[test]
+    fn given_certificates_ca_certs_sync_replaces_the_rest() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("bindings");
+        let root_ca = tmpdir.path().join("root.pem");
+        fs::write(&root_ca, certs::tests::TEST_ROOT_CA).unwrap();
+        let expired = tmpdir.path().join("expired.pem");
+        fs::write(&expired, certs::tests::TEST_EXPIRED_CA).unwrap();
+
+        let sync = |cert: &path::Path| {
+            let args = args::Parser::new().parse_args(vec![
+                "bt",
+                "-r",
+                root.to_str().unwrap(),
+                "ca-certs",
+                "sync",
+                "-f",
+                "-c",
+                cert.to_str().unwrap(),
+            ]);
+            let cmd = args.subcommand_matches("ca-certs").unwrap();
             let mut tb = TestBuffer::new();
-            let res = ArgsCommandHandler {
+            let res = CaCertsCommandHandler {
                 output: tb.writer(),
+                config: Default::default(),
             }
             .handle(Some(cmd));
-            dbg!(&res);
-            assert!(res.is_ok(), "args handler should succeed");
-            assert_eq!(
-                tb.string().unwrap(),
-                format!(
-                    r#"--volume {}:/bindings --env SERVICE_BINDING_ROOT=/bindings"#,
-                    tmppath
-                )
-            );
-        });
+            assert!(res.is_ok(), "{:?}", res);
+            tb.string().unwrap().to_owned()
+        };
+        let binding = root.join("ca-certificates");
+
+        // an expired certificate is still added, it's only warned about
+        let out = sync(&expired);
+        assert!(out.contains("Added expired.pem"), "{}", out);
+        assert!(binding.join("expired.pem").exists());
+
+        let out = sync(&root_ca);
+        assert!(out.contains("Removing expired.pem"), "{}", out);
+        assert!(out.contains("Added root.pem"), "{}", out);
+        assert!(
+            out.contains("added 1 keys and removed 1 certificates"),
+            "{}",
+            out
+        );
+        assert!(!binding.join("expired.pem").exists());
+        assert!(binding.join("root.pem").exists());
+
+        let out = sync(&root_ca);
+        assert_eq!(out, "ca-certificates is already in sync\n");
+    }
+
+    #[test]
+    fn given_warn_days_ca_certs_sync_warns_within_them() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root_ca = tmpdir.path().join("root.pem");
+        fs::write(&root_ca, certs::tests::TEST_ROOT_CA).unwrap();
+        let expired = tmpdir.path().join("expired.pem");
+        fs::write(&expired, certs::tests::TEST_EXPIRED_CA).unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let warnings = |extra: &[&str]| {
+            let mut argv = vec![
+                "bt",
+                "ca-certs",
+                "sync",
+                "-c",
+                root_ca.to_str().unwrap(),
+                "-c",
+                expired.to_str().unwrap(),
+            ];
+            argv.extend(extra);
+            let args = args::Parser::new().parse_args(argv);
+            let sync = args
+                .subcommand_matches("ca-certs")
+                .and_then(|cmd| cmd.subcommand_matches("sync"))
+                .unwrap();
+            let warn_days = *sync.get_one::<u32>("WARN_DAYS").unwrap();
+            let mut warnings = vec![];
+            assert!(file_cert_args(sync, now, warn_days, &mut warnings).is_ok());
+            warnings
+        };
+
+        let found = warnings(&[]);
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert!(found[0].contains("expired on"), "{}", found[0]);
+
+        // the root is valid until 2126
+        let found = warnings(&["--warn-days", "40000"]);
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert!(found[0].contains("Test Root CA"), "{}", found[0]);
+    }
+
+    #[test]
+    fn shell_quoting() {
+        assert_eq!(shell_quote("posix", "/a/b-c_d.e:/f"), "/a/b-c_d.e:/f");
+        assert_eq!(shell_quote("posix", "a b"), "'a b'");
+        assert_eq!(shell_quote("posix", "it's"), r"'it'\''s'");
+        assert_eq!(shell_quote("posix", "$HOME"), "'$HOME'");
+        assert_eq!(shell_quote("posix", ""), "''");
+        assert_eq!(shell_quote("powershell", "it's"), "'it''s'");
+        assert_eq!(shell_quote("powershell", "a,b"), "'a,b'");
     }
 
     #[test]
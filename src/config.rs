@@ -0,0 +1,287 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use std::convert::TryFrom;
+use std::{fs, str};
+use toml::{map::Map, Value as Toml};
+
+use crate::{deps, hosts};
+
+/// What to do when a key being added to a binding already exists
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Conflict {
+    /// ask on the console
+    Ask,
+    /// replace the value, like `--force`
+    Overwrite,
+    /// stop with an error
+    Fail,
+}
+
+/// Defaults from `config.toml`, which environment variables and flags take precedence over
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(super) struct Config {
+    /// where bindings are kept when `SERVICE_BINDING_ROOT` isn't set
+    pub(super) binding_root: Option<String>,
+    /// how existing keys are handled when `--force` isn't passed
+    pub(super) on_conflict: Option<Conflict>,
+    /// maximum number of simultaneous downloads when `BT_MAX_SIMULTANEOUS` isn't set
+    pub(super) jobs: Option<usize>,
+    /// mirrors used when `BT_MIRROR` isn't set
+    pub(super) mirrors: Vec<deps::Mirror>,
+    /// where bindings are mounted in containers when `BT_CONTAINER_PATH` isn't set
+    pub(super) container_path: Option<String>,
+}
+
+/// Loads `config.toml` from the config directory, which is optional, with `profile` applied
+pub(super) fn load(profile: Option<&str>) -> Result<Config> {
+    let path = hosts::config_dir().map(|dir| dir.join("config.toml"));
+    let path = match path.filter(|p| p.exists()) {
+        Some(path) => path,
+        None => {
+            ensure!(
+                profile.is_none(),
+                "there's no profile {}, config.toml doesn't exist",
+                profile.unwrap_or_default()
+            );
+            return Ok(Config::default());
+        }
+    };
+
+    let input = fs::read_to_string(&path)
+        .with_context(|| format!("cannot read {}", path.to_string_lossy()))?;
+    parse(input.parse()?, profile)
+        .with_context(|| format!("invalid settings in {}", path.to_string_lossy()))
+}
+
+impl str::FromStr for Config {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Config, Self::Err> {
+        parse(input.parse()?, None)
+    }
+}
+
+/// The top-level settings, with those of `profile` over them when it's given. Every profile is
+/// checked, so a mistake doesn't go unnoticed until it's used.
+fn parse(input: Toml, profile: Option<&str>) -> Result<Config> {
+    let table = input
+        .as_table()
+        .ok_or_else(|| anyhow!("expected a table of settings"))?;
+
+    let mut config = Config::default();
+    let mut profiles = Map::new();
+    for (key, value) in table {
+        if key == "profiles" {
+            profiles = value
+                .as_table()
+                .ok_or_else(|| anyhow!("profiles should be a table"))?
+                .clone();
+        } else {
+            apply(&mut config, key, value)?;
+        }
+    }
+
+    let mut selected = None;
+    for (name, settings) in &profiles {
+        let mut with_profile = config.clone();
+        for (key, value) in settings
+            .as_table()
+            .ok_or_else(|| anyhow!("profile {name} should be a table"))?
+        {
+            apply(&mut with_profile, key, value)
+                .with_context(|| format!("invalid profile {name}"))?;
+        }
+        if profile == Some(name.as_str()) {
+            selected = Some(with_profile);
+        }
+    }
+
+    match profile {
+        Some(name) => selected.ok_or_else(|| anyhow!("there's no profile {name}")),
+        None => Ok(config),
+    }
+}
+
+fn apply(config: &mut Config, key: &str, value: &Toml) -> Result<()> {
+    let string = |key: &str, value: &Toml| -> Result<String> {
+        value
+            .as_str()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("{key} should be a non-empty string"))
+    };
+
+    match key {
+        "binding-root" => config.binding_root = Some(string(key, value)?),
+        "on-conflict" => {
+            config.on_conflict = Some(match string(key, value)?.as_str() {
+                "ask" => Conflict::Ask,
+                "overwrite" => Conflict::Overwrite,
+                "fail" => Conflict::Fail,
+                other => bail!("on-conflict {other} should be one of ask, overwrite or fail"),
+            })
+        }
+        "jobs" => {
+            config.jobs = Some(
+                value
+                    .as_integer()
+                    .filter(|j| *j > 0)
+                    .and_then(|j| usize::try_from(j).ok())
+                    .ok_or_else(|| anyhow!("jobs should be a positive number"))?,
+            )
+        }
+        "mirrors" => {
+            config.mirrors = value
+                .as_array()
+                .ok_or_else(|| anyhow!("mirrors should be an array"))?
+                .iter()
+                .map(|m| string(key, m)?.parse())
+                .collect::<Result<_>>()?
+        }
+        "container-path" => {
+            let path = string(key, value)?;
+            if !path.starts_with('/') || path.contains(':') {
+                bail!("container-path {path} must be absolute and without `:`");
+            }
+            config.container_path = Some(path)
+        }
+        other => bail!("unknown setting {other}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Config, Conflict};
+
+    #[test]
+    fn parse_config() {
+        let config: Config = r#"
+            binding-root = "/home/me/bindings"
+            on-conflict = "overwrite"
+            jobs = 10
+            mirrors = ["github.com=https://nexus.corp/github/"]
+            container-path = "/workspace/bindings"
+            "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(config.binding_root.as_deref(), Some("/home/me/bindings"));
+        assert_eq!(config.on_conflict, Some(Conflict::Overwrite));
+        assert_eq!(config.jobs, Some(10));
+        assert_eq!(
+            config.mirrors,
+            vec!["github.com=https://nexus.corp/github/".parse().unwrap()]
+        );
+        assert_eq!(
+            config.container_path.as_deref(),
+            Some("/workspace/bindings")
+        );
+
+        assert_eq!("".parse::<Config>().unwrap(), Config::default());
+    }
+
+    #[test]
+    fn parse_config_profiles() {
+        let input = r#"
+            binding-root = "/home/me/bindings"
+            jobs = 4
+
+            [profiles.work]
+            binding-root = "/home/me/work"
+            on-conflict = "fail"
+
+            [profiles.ci]
+            jobs = 16
+            "#
+        .parse::<toml::Value>()
+        .unwrap();
+
+        let config = parse(input.clone(), None).unwrap();
+        assert_eq!(config.binding_root.as_deref(), Some("/home/me/bindings"));
+        assert_eq!(config.on_conflict, None);
+
+        let work = parse(input.clone(), Some("work")).unwrap();
+        assert_eq!(work.binding_root.as_deref(), Some("/home/me/work"));
+        assert_eq!(work.on_conflict, Some(Conflict::Fail));
+        assert_eq!(work.jobs, Some(4));
+
+        let ci = parse(input.clone(), Some("ci")).unwrap();
+        assert_eq!(ci.binding_root.as_deref(), Some("/home/me/bindings"));
+        assert_eq!(ci.jobs, Some(16));
+
+        assert!(parse(input, Some("personal")).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid profile ci")]
+    fn parse_config_bad_profile() {
+        r#"
+            [profiles.ci]
+            jobs = "lots"
+            "#
+        .parse::<Config>()
+        .unwrap();
+    }
+
+    #[test]
+    fn load_profile_without_config() {
+        let config_home = tempfile::tempdir().unwrap();
+        temp_env::with_var("XDG_CONFIG_HOME", config_home.path().to_str(), || {
+            assert!(
+                super::load(Some("work")).is_err(),
+                "should not ignore the profile"
+            );
+            assert_eq!(super::load(None).unwrap(), Config::default());
+        });
+    }
+
+    #[test]
+    fn load_profile() {
+        let config_home = tempfile::tempdir().unwrap();
+        let dir = config_home.path().join("binding-tool");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("config.toml"),
+            "jobs = 4\n\n[profiles.ci]\njobs = 16\n",
+        )
+        .unwrap();
+
+        temp_env::with_var("XDG_CONFIG_HOME", config_home.path().to_str(), || {
+            assert_eq!(super::load(None).unwrap().jobs, Some(4));
+            assert_eq!(super::load(Some("ci")).unwrap().jobs, Some(16));
+            assert!(super::load(Some("work")).is_err());
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "on-conflict skip should be one of ask, overwrite or fail")]
+    fn parse_config_bad_conflict() {
+        r#"on-conflict = "skip""#.parse::<Config>().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "jobs should be a positive number")]
+    fn parse_config_bad_jobs() {
+        "jobs = 0".parse::<Config>().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown setting binding_root")]
+    fn parse_config_unknown_setting() {
+        r#"binding_root = "/bindings""#.parse::<Config>().unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, Context, Result};
+use base64::prelude::*;
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use std::{fs, path};
+
+/// Verifies signatures made by `cosign sign-blob` with an ECDSA P-256 key
+#[derive(Debug)]
+pub(super) struct Verifier {
+    key: VerifyingKey,
+}
+
+impl Verifier {
+    /// Loads a PEM encoded public key, like the `cosign.pub` of `cosign generate-key-pair`
+    pub(super) fn load(path: &path::Path) -> Result<Verifier> {
+        let pem = fs::read_to_string(path)
+            .with_context(|| format!("cannot read public key {}", path.display()))?;
+        pem.parse()
+            .with_context(|| format!("invalid public key {}", path.display()))
+    }
+
+    /// Checks a base64 encoded signature of the artifact with the given sha256. The digest is
+    /// all that's needed since the artifact itself is checked against it when downloaded.
+    pub(super) fn verify(&self, sha256: &str, signature: &str) -> Result<()> {
+        let digest = hex::decode(sha256).with_context(|| format!("invalid sha256 {sha256}"))?;
+        let der = BASE64_STANDARD
+            .decode(signature.trim())
+            .context("signature is not base64 encoded")?;
+        let signature = Signature::from_der(&der).map_err(|_| anyhow!("invalid signature"))?;
+
+        self.key
+            .verify_prehash(&digest, &signature)
+            .map_err(|_| anyhow!("signature was not made with the verification key"))
+    }
+}
+
+impl std::str::FromStr for Verifier {
+    type Err = anyhow::Error;
+
+    fn from_str(pem: &str) -> Result<Verifier, Self::Err> {
+        let key = VerifyingKey::from_public_key_pem(pem)
+            .map_err(|e| anyhow!("only ECDSA P-256 keys are supported: {e}"))?;
+        Ok(Verifier { key })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Verifier;
+    use base64::prelude::*;
+    use p256::ecdsa::signature::hazmat::PrehashSigner;
+    use p256::ecdsa::{Signature, SigningKey};
+    use p256::pkcs8::{EncodePublicKey, LineEnding};
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn verify_cosign_signature() {
+        let signing = SigningKey::from_slice(&[7; 32]).unwrap();
+        let pem = signing
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let verifier: Verifier = pem.parse().unwrap();
+
+        let digest = Sha256::digest(b"hello");
+        let signature: Signature = signing.sign_prehash(&digest).unwrap();
+        let signature = BASE64_STANDARD.encode(signature.to_der());
+        let sha256 = hex::encode(digest);
+
+        verifier.verify(&sha256, &signature).unwrap();
+        verifier.verify(&sha256, &format!("{signature}\n")).unwrap();
+
+        let other = hex::encode(Sha256::digest(b"bye"));
+        assert!(verifier.verify(&other, &signature).is_err());
+        assert!(verifier.verify(&sha256, "not base64!").is_err());
+        assert!("not a key".parse::<Verifier>().is_err());
+    }
+}
@@ -12,260 +12,4164 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use bytes::Bytes;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED, RANGE,
+};
+use reqwest::{Proxy, RequestBuilder, StatusCode};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use semver::VersionReq;
+use serde_json::{json, Value as Json};
 use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
 use std::io::{self, prelude::*};
 use std::sync::{Arc, Mutex};
-use std::thread::JoinHandle;
-use std::time::Duration;
-use std::{env, path, thread};
+use std::time::{Duration, Instant};
+use std::{env, fs, path, str};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use toml::Value as Toml;
-use ureq::Proxy;
 use url::Url;
 
-#[derive(Clone)]
+use crate::cache::ArtifactCache;
+use crate::hosts::{self, Hosts};
+use crate::{config, cosign, github, oci, pinning, registry, storage};
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub(super) struct Dependency {
+    pub(super) id: Option<String>,
+    pub(super) version: Option<String>,
     pub(super) sha256: String,
     pub(super) uri: String,
+    /// package URL of the dependency, when buildpack.toml has one
+    pub(super) purl: Option<String>,
+    /// where a cosign signature of the artifact is, when buildpack.toml says it's signed
+    pub(super) signature: Option<String>,
+    /// name of the binary in `binaries/`, when it isn't the last segment of the URI
+    pub(super) filename: Option<String>,
+}
+
+/// The dependencies of a buildpack, along with the buildpack's id and version when its
+/// buildpack.toml has them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(super) struct Buildpack {
+    pub(super) id: Option<String>,
+    pub(super) version: Option<String>,
+    pub(super) dependencies: Vec<Dependency>,
+}
+
+impl Dependency {
+    /// Name of the binary in `binaries/`, the last segment of the URI unless another artifact
+    /// already has that name
+    pub(super) fn filename(&self) -> Result<String> {
+        if let Some(filename) = &self.filename {
+            return Ok(filename.clone());
+        }
+
+        Url::parse(&self.uri)?
+            .path_segments()
+            .ok_or_else(|| anyhow!("no path segments for {}", &self.uri))
+            .map(|mut s| {
+                s.next_back()
+                    .map(|s| s.to_owned())
+                    .ok_or_else(|| anyhow!("no path for {}", &self.uri))
+            })?
+    }
+
+    /// Where the artifact lives on this machine, for `file://` URIs
+    fn local_path(&self) -> Option<path::PathBuf> {
+        Url::parse(&self.uri)
+            .ok()
+            .filter(|u| u.scheme() == "file")
+            .and_then(|u| u.to_file_path().ok())
+    }
+
+    fn file_matches(&self, path: &path::Path) -> Result<bool> {
+        if !path.exists() {
+            return Ok(false);
+        }
+
+        let hash = hex::encode(hash_file(path)?.finalize());
+        Ok(hash == self.sha256)
+    }
+
+    async fn verify(&self, path: path::PathBuf) -> Result<bool> {
+        let dep = self.clone();
+        tokio::task::spawn_blocking(move || dep.file_matches(&path)).await?
+    }
+
+    /// Checks the cosign signature of the artifact, read from the dependency's `signature` URI
+    /// or from `<uri>.sig` next to the artifact
+    async fn check_signature(
+        &self,
+        client: &reqwest::Client,
+        auth: &DownloadAuth,
+        verifier: &cosign::Verifier,
+    ) -> Result<()> {
+        let uri = self
+            .signature
+            .clone()
+            .unwrap_or_else(|| format!("{}.sig", self.uri));
+        let local = Url::parse(&uri)
+            .ok()
+            .filter(|u| u.scheme() == "file")
+            .and_then(|u| u.to_file_path().ok());
+
+        let signature: Result<String> = match local {
+            Some(path) => Ok(tokio::fs::read_to_string(&path).await?),
+            None => {
+                async {
+                    let res = auth.apply(client.get(&uri), &uri)?.send().await?;
+                    Ok(res.error_for_status()?.text().await?)
+                }
+                .await
+            }
+        };
+        let signature = signature.with_context(|| format!("cannot fetch signature {uri}"))?;
+
+        verifier
+            .verify(&self.sha256, &signature)
+            .with_context(|| format!("signature of {} does not verify", self.uri))
+    }
+
+    pub(super) async fn download(
+        &self,
+        client: &reqwest::Client,
+        auth: &DownloadAuth,
+        binding_path: &path::Path,
+        cache: Option<&Arc<ArtifactCache>>,
+        rate: Option<&RateLimit>,
+        progress: &MultiProgress,
+    ) -> Result<Fetched> {
+        let dest = binding_path.join("binaries").join(self.filename()?);
+        if self.verify(dest.clone()).await? {
+            return Ok(Fetched {
+                status: ArtifactStatus::Present,
+                ..Default::default()
+            });
+        }
+
+        // with a shared cache, artifacts are stored by checksum and linked into the binding
+        let target = match cache {
+            Some(cache) => {
+                ensure!(
+                    self.sha256.len() == 64 && self.sha256.chars().all(|c| c.is_ascii_hexdigit()),
+                    "invalid sha256 {} for {}",
+                    self.sha256,
+                    self.uri
+                );
+                cache.path(&self.sha256)
+            }
+            None => dest.clone(),
+        };
+
+        let cached = match cache {
+            Some(cache) => {
+                let (cache, sha256) = (Arc::clone(cache), self.sha256.clone());
+                tokio::task::spawn_blocking(move || cache.check(&sha256)).await??
+            }
+            None => self.verify(target.clone()).await?,
+        };
+        let fetched = if cached {
+            Fetched {
+                status: ArtifactStatus::Cached,
+                ..Default::default()
+            }
+        } else {
+            let fetched = self
+                .fetch_verified(client, auth, &target, rate, progress)
+                .await?;
+            if let Some(cache) = cache {
+                cache.record(&self.sha256)?;
+            }
+            fetched
+        };
+
+        if target != dest {
+            link_or_copy(&target, &dest).await?;
+        }
+
+        Ok(fetched)
+    }
+
+    async fn fetch_verified(
+        &self,
+        client: &reqwest::Client,
+        auth: &DownloadAuth,
+        target: &path::Path,
+        rate: Option<&RateLimit>,
+        progress: &MultiProgress,
+    ) -> Result<Fetched> {
+        // only verified artifacts are given their final name, so later runs can trust them
+        let mut part = target.as_os_str().to_owned();
+        part.push(".part");
+        let part = path::PathBuf::from(part);
+
+        // a partial file from an interrupted run is resumed instead of restarted
+        let existing = tokio::fs::metadata(&part)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0);
+        let (mut resumed, mut hash, mut bytes) = self
+            .fetch(client, auth, &part, existing, rate, progress)
+            .await?;
+        let mut retries = 0;
+
+        // the partial file may not have been a prefix of this artifact, try once from scratch
+        if hash != self.sha256 && resumed {
+            let retried;
+            (resumed, hash, retried) = self.fetch(client, auth, &part, 0, rate, progress).await?;
+            bytes += retried;
+            retries += 1;
+        }
+
+        if hash == self.sha256 {
+            tokio::fs::rename(&part, target)
+                .await
+                .with_context(|| format!("cannot rename {part:?} to {target:?}"))?;
+            return Ok(Fetched {
+                status: ArtifactStatus::Downloaded,
+                bytes,
+                retries,
+            });
+        }
+
+        tokio::fs::remove_file(&part)
+            .await
+            .with_context(|| format!("cannot remove file {part:?}"))?;
+        Err(anyhow!(
+            "checksum mismatch for {}, expected {} but got {}{}",
+            self.uri,
+            self.sha256,
+            hash,
+            if resumed { " (resumed)" } else { "" }
+        ))
+    }
+
+    /// Fetches the artifact into `dest`, asking the server to skip the first `offset` bytes
+    /// when `offset` is not zero. Returns true if the server honored the range request and
+    /// the download was appended to the existing file, along with the sha256 of the whole file
+    /// which is computed as the download is written, and the number of bytes fetched.
+    async fn fetch(
+        &self,
+        client: &reqwest::Client,
+        auth: &DownloadAuth,
+        dest: &path::Path,
+        offset: u64,
+        rate: Option<&RateLimit>,
+        progress: &MultiProgress,
+    ) -> Result<(bool, String, u64)> {
+        if let Some(src) = self.local_path() {
+            return self.copy_local(&src, dest, progress).await;
+        }
+
+        let (mut body, resumed) = if storage::is_object_uri(&self.uri) {
+            // downloads from cloud storage start over rather than resume
+            (Body::Object(storage::get(&self.uri).await?), false)
+        } else {
+            let mut req = auth.apply(client.get(&self.uri), &self.uri)?;
+            if offset > 0 {
+                req = req.header(RANGE, format!("bytes={offset}-"));
+            }
+
+            let mut res = req.send().await?;
+            if res.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+                // range starts past the end of the artifact, the partial file can't be trusted
+                res = auth.apply(client.get(&self.uri), &self.uri)?.send().await?;
+            }
+            let res = res.error_for_status()?;
+
+            let resumed = offset > 0 && res.status() == StatusCode::PARTIAL_CONTENT;
+            (Body::Http(res), resumed)
+        };
+        let mut hasher = if resumed {
+            let dest = dest.to_owned();
+            tokio::task::spawn_blocking(move || hash_file(&dest)).await??
+        } else {
+            Sha256::new()
+        };
+        let mut fp = if resumed {
+            tokio::fs::OpenOptions::new().append(true).open(dest).await
+        } else {
+            tokio::fs::File::create(dest).await
+        }
+        .with_context(|| format!("cannot open file {dest:?}"))?;
+
+        let pb = progress_bar(body.len())?;
+        if resumed {
+            pb.inc_length(offset);
+            pb.set_position(offset);
+        }
+        let pb = progress.add(pb.with_message(self.filename()?));
+
+        let mut bytes = 0;
+        while let Some(chunk) = body.chunk().await? {
+            if let Some(rate) = rate {
+                rate.throttle(chunk.len() as u64).await;
+            }
+            hasher.update(&chunk);
+            fp.write_all(&chunk).await.with_context(|| "copy failed")?;
+            pb.inc(chunk.len() as u64);
+            bytes += chunk.len() as u64;
+        }
+        fp.flush().await?;
+
+        pb.finish_and_clear();
+        Ok((resumed, hex::encode(hasher.finalize()), bytes))
+    }
+
+    /// Copies an artifact from the local filesystem into `dest`, computing its sha256 as it is
+    /// written. A copy is never resumed.
+    async fn copy_local(
+        &self,
+        src: &path::Path,
+        dest: &path::Path,
+        progress: &MultiProgress,
+    ) -> Result<(bool, String, u64)> {
+        let mut input = tokio::fs::File::open(src)
+            .await
+            .with_context(|| format!("cannot open file {src:?}"))?;
+        let len = input.metadata().await?.len();
+        let mut fp = tokio::fs::File::create(dest)
+            .await
+            .with_context(|| format!("cannot open file {dest:?}"))?;
+        let pb = progress.add(progress_bar(Some(len))?.with_message(self.filename()?));
+
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; 64 * 1024];
+        let mut bytes = 0;
+        loop {
+            let n = input
+                .read(&mut buf)
+                .await
+                .with_context(|| format!("cannot read file {src:?}"))?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            fp.write_all(&buf[..n])
+                .await
+                .with_context(|| "copy failed")?;
+            pb.inc(n as u64);
+            bytes += n as u64;
+        }
+        fp.flush().await?;
+
+        pb.finish_and_clear();
+        Ok((false, hex::encode(hasher.finalize()), bytes))
+    }
+}
+
+/// The body of a download, from a web server or cloud storage
+enum Body {
+    Http(reqwest::Response),
+    Object(storage::Object),
+}
+
+impl Body {
+    fn len(&self) -> Option<u64> {
+        match self {
+            Body::Http(res) => res.content_length(),
+            Body::Object(object) => Some(object.size),
+        }
+    }
+
+    async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        match self {
+            Body::Http(res) => Ok(res.chunk().await?),
+            Body::Object(object) => object.chunk().await,
+        }
+    }
+}
+
+/// A progress bar for a download of `len` bytes, or a spinner when the size isn't known
+fn progress_bar(len: Option<u64>) -> Result<ProgressBar> {
+    Ok(match len {
+        Some(len) => ProgressBar::new(len).with_style(
+            ProgressStyle::with_template(
+                "{msg:40!} [{bar:30}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+            )?
+            .progress_chars("=> "),
+        ),
+        None => ProgressBar::new_spinner().with_style(ProgressStyle::with_template(
+            "{msg:40!} {spinner} {bytes} ({bytes_per_sec})",
+        )?),
+    })
+}
+
+/// A `file://` URI for a dependency given as a path on this machine, relative paths are
+/// resolved against `base`. Returns `None` for anything that is already a URI.
+fn local_uri(uri: &str, base: &path::Path) -> Option<String> {
+    match Url::parse(uri) {
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            Url::from_file_path(base.join(uri)).ok().map(String::from)
+        }
+        _ => None,
+    }
+}
+
+pub(super) fn parse_buildpack_toml_from_disk(path: &path::Path) -> Result<Buildpack> {
+    let mut input = String::new();
+
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut input))
+        .with_context(|| format!("cannot read {}", path.to_string_lossy()))?;
+
+    let mut buildpack = transform_buildpack(
+        input
+            .parse()
+            .with_context(|| format!("cannot parse {}", path.to_string_lossy()))?,
+    )?;
+
+    // locally built buildpacks may point at artifacts next to their buildpack.toml
+    let base = path::absolute(path)?
+        .parent()
+        .map(|p| p.to_owned())
+        .unwrap_or_default();
+    for d in &mut buildpack.dependencies {
+        if let Some(uri) = local_uri(&d.uri, &base) {
+            d.uri = uri;
+        }
+    }
+    Ok(buildpack)
+}
+
+/// Reads buildpack.toml for a buildpack id or image. Composite buildpacks are resolved through
+/// their `order`, collecting the dependencies of every component, or only of `components` when
+/// given. When `cache_dir` is set, fetched files are kept there and revalidated instead of
+/// downloaded again.
+pub(super) fn parse_buildpack_toml_from_network(
+    buildpack: &str,
+    components: &[String],
+    cache_dir: Option<&path::Path>,
+    binding_root: &path::Path,
+    timeouts: Timeouts,
+) -> Result<Buildpack> {
+    runtime()?.block_on(async {
+        let client = configure_client(binding_root, timeouts)?;
+        resolve_buildpack(&client, buildpack, components, cache_dir, true).await
+    })
+}
+
+/// Resolves a buildpack and its components. Buildpacks without dependencies are an error unless
+/// `require_dependencies` is false.
+async fn resolve_buildpack(
+    client: &reqwest::Client,
+    buildpack: &str,
+    components: &[String],
+    cache_dir: Option<&path::Path>,
+    require_dependencies: bool,
+) -> Result<Buildpack> {
+    let toml: Toml = fetch_buildpack_toml(client, buildpack, cache_dir)
+        .await?
+        .parse()?;
+    let order = order_components(&toml)?;
+    if order.is_empty() {
+        ensure!(
+            components.is_empty(),
+            "{buildpack} is not a composite buildpack, it has no components to pick"
+        );
+        if !require_dependencies && !has_dependencies(&toml) {
+            return identify_buildpack(&toml);
+        }
+        return transform_buildpack(toml);
+    }
+
+    for c in components {
+        ensure!(
+            order.iter().any(|(id, _)| id == c),
+            "{c} is not a component of {buildpack}"
+        );
+    }
+
+    let mut bp = Buildpack {
+        dependencies: vec![],
+        ..identify_buildpack(&toml)?
+    };
+    let mut queue = order
+        .into_iter()
+        .filter(|(id, _)| components.is_empty() || components.contains(id))
+        .collect::<VecDeque<_>>();
+    let mut seen = HashSet::new();
+
+    while let Some((id, version)) = queue.pop_front() {
+        let component = match version {
+            Some(version) => format!("{id}@{version}"),
+            None => id,
+        };
+        if !seen.insert(component.clone()) {
+            continue;
+        }
+
+        let toml: Toml = fetch_buildpack_toml(client, &component, cache_dir)
+            .await
+            .and_then(|t| Ok(t.parse()?))
+            .with_context(|| format!("cannot read component buildpack {component}"))?;
+
+        // components may be composite themselves, and many have no dependencies at all
+        let nested = order_components(&toml)?;
+        if !nested.is_empty() {
+            queue.extend(nested);
+        } else if has_dependencies(&toml) {
+            for d in transform(toml)
+                .with_context(|| format!("invalid buildpack.toml for {component}"))?
+            {
+                if !bp.dependencies.iter().any(|e| e.sha256 == d.sha256) {
+                    bp.dependencies.push(d);
+                }
+            }
+        }
+    }
+
+    Ok(bp)
+}
+
+fn has_dependencies(toml: &Toml) -> bool {
+    toml.get("metadata")
+        .and_then(|m| m.get("dependencies"))
+        .is_some()
+}
+
+/// Fetches buildpack.toml for a buildpack id, with an optional version, or a buildpack image
+async fn fetch_buildpack_toml(
+    client: &reqwest::Client,
+    buildpack: &str,
+    cache_dir: Option<&path::Path>,
+) -> Result<String> {
+    if let Some(image) = buildpack.strip_prefix("docker://") {
+        return oci::fetch_buildpack_toml(client, &image.parse()?).await;
+    }
+
+    let parts = buildpack.splitn(2, '@').collect::<Vec<&str>>();
+    let (id, version) = match parts.as_slice() {
+        [b] => Ok((*b, None)),
+        [b, v] => Ok((*b, Some(*v))),
+        [..] => Err(anyhow!("parse of [{buildpack}], should have format `buildpack/id@version`, `@version` is optional")),
+    }?;
+
+    // prefer the registry, buildpacks it doesn't know are assumed to be on GitHub
+    match registry::lookup(client, id, version).await {
+        Ok(Some(bp)) => {
+            if version.is_none_or(|v| registry::version_range(v).is_some()) {
+                eprintln!("Using {id}@{}", bp.version);
+            }
+            fetch_registry_buildpack_toml(client, &bp, cache_dir).await
+        }
+        Ok(None) => {
+            let git_ref = github_ref(client, id, version).await?;
+            fetch_first(
+                client,
+                &[github_buildpack_toml_uri(id, git_ref.as_deref())],
+                cache_dir,
+            )
+            .await
+        }
+        Err(err) => {
+            eprintln!("Warning: buildpack registry lookup for {id} failed, falling back to GitHub: {err:#}");
+            let git_ref = github_ref(client, id, version).await?;
+            fetch_first(
+                client,
+                &[github_buildpack_toml_uri(id, git_ref.as_deref())],
+                cache_dir,
+            )
+            .await
+        }
+    }
+}
+
+/// The buildpacks in every group of a composite buildpack's `order`, as ids with optional
+/// versions. Empty for buildpacks that aren't composite.
+fn order_components(toml: &Toml) -> Result<Vec<(String, Option<String>)>> {
+    let order = match toml.get("order") {
+        Some(order) => order.as_array().context("order should be an array")?,
+        None => return Ok(vec![]),
+    };
+
+    let mut components: Vec<(String, Option<String>)> = vec![];
+    for group in order {
+        let group = match group.get("group") {
+            Some(group) => group.as_array().context("order group should be an array")?,
+            None => continue,
+        };
+        for entry in group {
+            let id = optional_string(entry.get("id"), "order group id")?
+                .context("order group entries need an id")?;
+            let version = optional_string(entry.get("version"), "order group version")?;
+            if !components.iter().any(|(i, v)| *i == id && *v == version) {
+                components.push((id, version));
+            }
+        }
+    }
+    Ok(components)
+}
+
+/// Reads buildpack.toml for every buildpack a CNB `project.toml` or `builder.toml` uses, those
+/// without dependencies are included with none
+pub(super) fn parse_project_toml(
+    path: &path::Path,
+    cache_dir: Option<&path::Path>,
+    binding_root: &path::Path,
+    timeouts: Timeouts,
+) -> Result<Vec<Buildpack>> {
+    let input = fs::read_to_string(path)
+        .with_context(|| format!("cannot read {}", path.to_string_lossy()))?;
+    let toml: Toml = input
+        .parse()
+        .with_context(|| format!("cannot parse {}", path.to_string_lossy()))?;
+
+    let references =
+        project_buildpacks(&toml).with_context(|| format!("invalid {}", path.to_string_lossy()))?;
+    ensure!(
+        !references.is_empty(),
+        "no buildpacks in {}",
+        path.to_string_lossy()
+    );
+
+    runtime()?.block_on(async {
+        let client = configure_client(binding_root, timeouts)?;
+        let mut buildpacks = vec![];
+        for r in &references {
+            buildpacks.push(resolve_buildpack(&client, r, &[], cache_dir, false).await?);
+        }
+        Ok(buildpacks)
+    })
+}
+
+fn project_buildpacks(toml: &Toml) -> Result<Vec<String>> {
+    let array = |value: Option<&Toml>, name: &str| -> Result<Vec<Toml>> {
+        match value {
+            Some(v) => Ok(v
+                .as_array()
+                .with_context(|| format!("{name} should be an array"))?
+                .clone()),
+            None => Ok(vec![]),
+        }
+    };
+
+    // project.toml, schema 0.2 and then 0.1
+    let io = toml.get("io").and_then(|io| io.get("buildpacks"));
+    let mut entries = vec![];
+    for (group, name) in [
+        (io.and_then(|b| b.get("pre")), "io.buildpacks.pre.group"),
+        (io, "io.buildpacks.group"),
+        (io.and_then(|b| b.get("post")), "io.buildpacks.post.group"),
+    ] {
+        entries.extend(array(group.and_then(|g| g.get("group")), name)?);
+    }
+    entries.extend(array(
+        toml.get("build").and_then(|b| b.get("buildpacks")),
+        "build.buildpacks",
+    )?);
+
+    // builder.toml lists its buildpacks, the order only refers to them
+    if toml.get("buildpacks").is_some() {
+        entries.extend(array(toml.get("buildpacks"), "buildpacks")?);
+    } else {
+        for group in array(toml.get("order"), "order")? {
+            entries.extend(array(group.get("group"), "order.group")?);
+        }
+    }
+
+    let mut references: Vec<String> = vec![];
+    for entry in &entries {
+        let reference = buildpack_reference(entry)?;
+        if !references.contains(&reference) {
+            references.push(reference);
+        }
+    }
+    Ok(references)
+}
+
+/// A buildpack entry of a project or builder, which has an id and version or a URI
+fn buildpack_reference(entry: &Toml) -> Result<String> {
+    let id = optional_string(entry.get("id"), "buildpack id")?;
+    let version = optional_string(entry.get("version"), "buildpack version")?;
+    let uri = optional_string(entry.get("uri"), "buildpack uri")?;
+
+    if let Some(uri) = &uri {
+        if uri.starts_with("docker://") {
+            return Ok(uri.clone());
+        }
+        if let Some(reference) = uri
+            .strip_prefix("urn:cnb:registry:")
+            .or_else(|| uri.strip_prefix("urn:cnb:builder:"))
+        {
+            return Ok(reference.to_owned());
+        }
+    }
+
+    match (id, version, uri) {
+        (Some(id), Some(version), _) => Ok(format!("{id}@{version}")),
+        (Some(id), None, _) => Ok(id),
+        (None, _, Some(uri)) => Err(anyhow!(
+            "cannot read buildpack {uri}, only ids, `docker://` and `urn:cnb:registry:` buildpacks are supported"
+        )),
+        (None, _, None) => Err(anyhow!("buildpacks need an id or uri")),
+    }
+}
+
+/// The dependencies of every buildpack, each sha256 only once
+pub(super) fn merge_dependencies(buildpacks: &[Buildpack]) -> Vec<Dependency> {
+    let mut deps: Vec<Dependency> = vec![];
+    for d in buildpacks.iter().flat_map(|b| &b.dependencies) {
+        if !deps.iter().any(|e| e.sha256 == d.sha256) {
+            deps.push(d.clone());
+        }
+    }
+    deps
+}
+
+/// Picks dependencies by glob patterns matched against their URI or id. `*` matches any run of
+/// characters and `?` a single character.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(super) struct DependencyFilter {
+    /// when not empty, only dependencies matching one of these are kept
+    pub(super) include: Vec<String>,
+    /// dependencies matching any of these are dropped
+    pub(super) exclude: Vec<String>,
+}
+
+impl DependencyFilter {
+    fn matches(patterns: &[String], dep: &Dependency) -> bool {
+        patterns.iter().any(|p| {
+            glob_matches(p, &dep.uri) || dep.id.as_deref().is_some_and(|id| glob_matches(p, id))
+        })
+    }
+
+    pub(super) fn keeps(&self, dep: &Dependency) -> bool {
+        (self.include.is_empty() || DependencyFilter::matches(&self.include, dep))
+            && !DependencyFilter::matches(&self.exclude, dep)
+    }
+
+    /// Drops the dependencies of `buildpacks` the patterns don't keep. Buildpacks without any
+    /// dependencies are fine, but patterns that leave nothing of them are a mistake.
+    pub(super) fn apply(&self, buildpacks: &mut [Buildpack]) -> Result<()> {
+        if self.include.is_empty() && self.exclude.is_empty() {
+            return Ok(());
+        }
+
+        let total = buildpacks
+            .iter()
+            .map(|b| b.dependencies.len())
+            .sum::<usize>();
+        for bp in buildpacks.iter_mut() {
+            bp.dependencies.retain(|d| self.keeps(d));
+        }
+        ensure!(
+            buildpacks.iter().any(|b| !b.dependencies.is_empty()),
+            "none of the {total} dependencies match the --include and --exclude patterns"
+        );
+        Ok(())
+    }
+}
+
+pub(super) fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    // iterative wildcard matching, backtracking to the last `*`
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Released versions of a buildpack, newest first, from the registry or the tags of its GitHub
+/// repository
+pub(super) fn list_versions(
+    id: &str,
+    binding_root: &path::Path,
+    timeouts: Timeouts,
+) -> Result<Vec<String>> {
+    ensure!(
+        !id.starts_with("docker://") && !id.contains('@'),
+        "expected a buildpack id like `buildpack/id`, not {id}"
+    );
+
+    runtime()?.block_on(async {
+        let client = configure_client(binding_root, timeouts)?;
+
+        let versions = match registry::versions(&client, id).await {
+            Ok(Some(versions)) => versions,
+            Ok(None) => github::tags(&client, id).await?,
+            Err(err) => {
+                eprintln!("Warning: buildpack registry lookup for {id} failed, falling back to GitHub: {err:#}");
+                github::tags(&client, id).await?
+            }
+        };
+        Ok(registry::sort_versions(versions))
+    })
+}
+
+/// Downloads a PEM encoded certificate over https, with the same proxies, trusted CAs and host
+/// credentials used for dependencies
+pub(super) fn fetch_certificate(uri: &str, binding_root: &path::Path) -> Result<String> {
+    let url = Url::parse(uri).with_context(|| format!("invalid certificate url {uri}"))?;
+    ensure!(
+        url.scheme() == "https",
+        "certificates are only downloaded over https, not {uri}"
+    );
+
+    let pem = runtime()?.block_on(async {
+        let client = configure_client(binding_root, Timeouts::default())?;
+        let auth = DownloadAuth {
+            hosts: hosts::load()?,
+            ..Default::default()
+        };
+        let res = auth
+            .apply(client.get(uri), uri)?
+            .send()
+            .await
+            .with_context(|| format!("cannot download certificate {uri}"))?
+            .error_for_status()?;
+        res.text().await.map_err(anyhow::Error::from)
+    })?;
+
+    ensure!(
+        pem.contains("-----BEGIN CERTIFICATE-----"),
+        "{uri} is not a PEM encoded certificate"
+    );
+    Ok(pem)
+}
+
+/// The certificate chain presented for `uri`, through the configured proxies, starting with the
+/// server's own certificate. The connection is dropped once the chain is seen.
+pub(super) fn peer_chain(uri: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let url = Url::parse(uri).with_context(|| format!("invalid url {uri}"))?;
+    ensure!(url.scheme() == "https", "{uri} is not an https url");
+
+    let chain = Arc::new(Mutex::new(vec![]));
+    let res = runtime()?.block_on(async {
+        let client = client_builder(Timeouts::default())?
+            .use_preconfigured_tls(pinning::recording_tls_config(chain.clone())?)
+            .build()
+            .context("unable to configure http client")?;
+        client.head(uri).send().await.map_err(anyhow::Error::from)
+    });
+
+    let chain = chain.lock().unwrap().clone();
+    match res {
+        _ if !chain.is_empty() => Ok(chain),
+        Err(err) => Err(err.context(format!("cannot connect to {uri}"))),
+        Ok(_) => bail!("{uri} didn't present a certificate"),
+    }
+}
+
+pub(super) fn parse_buildpack_toml_from_url(
+    uri: &str,
+    cache_dir: Option<&path::Path>,
+    binding_root: &path::Path,
+    timeouts: Timeouts,
+) -> Result<Buildpack> {
+    Url::parse(uri).with_context(|| format!("invalid buildpack.toml url {uri}"))?;
+
+    let res = runtime()?.block_on(async {
+        let client = configure_client(binding_root, timeouts)?;
+        fetch_first(&client, &[uri.to_owned()], cache_dir).await
+    })?;
+
+    transform_buildpack(
+        res.parse()
+            .with_context(|| format!("cannot parse buildpack.toml from {uri}"))?,
+    )
+}
+
+/// Reads buildpack.toml from the buildpack's GitHub repository, or from its image when the
+/// source isn't on GitHub or the release isn't tagged there
+async fn fetch_registry_buildpack_toml(
+    client: &reqwest::Client,
+    bp: &registry::RegistryBuildpack,
+    cache_dir: Option<&path::Path>,
+) -> Result<String> {
+    let from_github = match bp.buildpack_toml_uris() {
+        Ok(uris) => fetch_first(client, &uris, cache_dir).await,
+        Err(err) => Err(err),
+    };
+
+    match (from_github, &bp.addr) {
+        (Ok(toml), _) => Ok(toml),
+        (Err(_), Some(addr)) => oci::fetch_buildpack_toml(client, &addr.parse()?).await,
+        (Err(err), None) => Err(err),
+    }
+}
+
+/// Resolves a version range to the newest matching tag of the buildpack's GitHub repository,
+/// other versions are used as git refs as they are
+async fn github_ref(
+    client: &reqwest::Client,
+    id: &str,
+    version: Option<&str>,
+) -> Result<Option<String>> {
+    let (version, range) = match version {
+        Some(v) => match registry::version_range(v) {
+            Some(range) => (v, range),
+            None => return Ok(Some(v.to_owned())),
+        },
+        // the latest release, `*` doesn't match pre-releases
+        None => ("latest", VersionReq::STAR),
+    };
+
+    // like a repository without releases, one whose tags can't be listed is used as it is
+    let tags = match github::tags(client, id).await {
+        Ok(tags) => tags,
+        Err(err) => {
+            eprintln!("Warning: cannot list the tags of {id}, using main: {err:#}");
+            return Ok(None);
+        }
+    };
+    match registry::newest_in_range(tags.iter().map(|t| t.as_str()), &range) {
+        Some(tag) => {
+            eprintln!("Using {id}@{tag}");
+            Ok(Some(tag.to_owned()))
+        }
+        None if range == VersionReq::STAR => {
+            eprintln!("Warning: {id} has no released versions, using main");
+            Ok(None)
+        }
+        None => Err(anyhow!("no tag of {id} matches {version}")),
+    }
 }
 
-impl Dependency {
-    pub(super) fn filename(&self) -> Result<String> {
-        Url::parse(&self.uri)?
-            .path_segments()
-            .ok_or_else(|| anyhow!("no path segments for {}", &self.uri))
-            .map(|s| {
-                s.last()
-                    .map(|s| s.to_owned())
-                    .ok_or_else(|| anyhow!("no path for {}", &self.uri))
-            })?
+fn github_buildpack_toml_uri(id: &str, version: Option<&str>) -> String {
+    format!(
+        "https://raw.githubusercontent.com/{id}/{}/buildpack.toml",
+        version.unwrap_or("main")
+    )
+}
+
+/// Fetches the first of `uris` that exists, the last error is returned if none do. Responses are
+/// cached in `cache_dir` and revalidated with `If-None-Match` or `If-Modified-Since`.
+async fn fetch_first(
+    client: &reqwest::Client,
+    uris: &[String],
+    cache_dir: Option<&path::Path>,
+) -> Result<String> {
+    let mut last_err = anyhow!("no urls to fetch");
+
+    for uri in uris {
+        let cache_path = cache_dir.map(|c| CachedResponse::path(c, uri));
+        let cached = cache_path.as_deref().and_then(CachedResponse::load);
+
+        let mut req = github::authorize(client.get(uri), uri);
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                req = req.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                req = req.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let res = req
+            .send()
+            .await
+            .with_context(|| format!("failed on url {uri}"))?;
+        github::check_rate_limit(&res)?;
+
+        if res.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                return Ok(cached.body);
+            }
+        }
+
+        if res.status() == StatusCode::NOT_FOUND {
+            last_err = anyhow!("failed on url {uri}, not found");
+            continue;
+        }
+
+        let res = res.error_for_status()?;
+        let header = |name| {
+            res.headers()
+                .get(name)
+                .and_then(|h: &HeaderValue| h.to_str().ok())
+                .map(|h| h.to_owned())
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = res
+            .text()
+            .await
+            .with_context(|| format!("failed on url {uri}"))?;
+
+        if let Some(cache_path) = cache_path {
+            if etag.is_some() || last_modified.is_some() {
+                CachedResponse {
+                    etag,
+                    last_modified,
+                    body: body.clone(),
+                }
+                .save(&cache_path)?;
+            }
+        }
+        return Ok(body);
+    }
+
+    Err(last_err)
+}
+
+/// A fetched file kept in the cache, with the validators needed to check it's still current
+#[derive(Debug, PartialEq)]
+struct CachedResponse {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+impl CachedResponse {
+    /// Location of the cached response for `uri`, under `metadata` in the cache
+    fn path(cache_dir: &path::Path, uri: &str) -> path::PathBuf {
+        cache_dir
+            .join("metadata")
+            .join(hex::encode(Sha256::digest(uri.as_bytes())))
+    }
+
+    /// Loads a cached response, anything unreadable is treated as not cached
+    fn load(path: &path::Path) -> Option<CachedResponse> {
+        let toml: Toml = fs::read_to_string(path).ok()?.parse().ok()?;
+        let string = |key: &str| toml.get(key).and_then(|v| v.as_str()).map(|s| s.to_owned());
+
+        Some(CachedResponse {
+            etag: string("etag"),
+            last_modified: string("last-modified"),
+            body: string("body")?,
+        })
+    }
+
+    fn save(&self, path: &path::Path) -> Result<()> {
+        let mut table = toml::value::Table::new();
+        if let Some(etag) = &self.etag {
+            table.insert("etag".into(), Toml::String(etag.clone()));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            table.insert("last-modified".into(), Toml::String(last_modified.clone()));
+        }
+        table.insert("body".into(), Toml::String(self.body.clone()));
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("cannot create {}", parent.to_string_lossy()))?;
+        }
+        fs::write(path, toml::to_string(&Toml::Table(table))?)
+            .with_context(|| format!("cannot write {}", path.to_string_lossy()))
+    }
+}
+
+/// Settings that control how dependencies are downloaded
+#[derive(Default)]
+pub(super) struct DownloadOptions {
+    /// maximum number of simultaneous downloads, overrides `BT_MAX_SIMULTANEOUS` and config.toml
+    pub(super) jobs: Option<usize>,
+    /// hide download progress
+    pub(super) quiet: bool,
+    /// shared cache of downloaded artifacts, skipped when not set
+    pub(super) cache_dir: Option<path::PathBuf>,
+    /// mirrors that dependency URIs are rewritten to, overrides `BT_MIRROR` and config.toml
+    pub(super) mirrors: Vec<Mirror>,
+    /// headers sent with every download
+    pub(super) headers: Vec<Header>,
+    /// only use artifacts that are already in the binding or the cache
+    pub(super) offline: bool,
+    /// cap on the combined throughput of all downloads, in bytes per second
+    pub(super) limit_rate: Option<u64>,
+    /// public key that the cosign signatures of artifacts are checked with
+    pub(super) verify_key: Option<path::PathBuf>,
+    /// use signed artifacts without checking their signatures
+    pub(super) skip_verify: bool,
+    /// settings from config.toml, used when neither the options nor the environment set them
+    pub(super) config: config::Config,
+    /// where the bindings are, the CA certificates bound there are trusted for downloads
+    pub(super) binding_root: path::PathBuf,
+    /// timeouts of every request, the environment fills in those that aren't set
+    pub(super) timeouts: Timeouts,
+}
+
+impl DownloadOptions {
+    fn max_simultaneous(&self) -> Result<usize> {
+        let max_simult = match self.jobs {
+            Some(jobs) => jobs,
+            None => match env::var("BT_MAX_SIMULTANEOUS") {
+                Ok(jobs) => jobs
+                    .parse()
+                    .with_context(|| "BT_MAX_SIMULTANEOUS should be a positive number")?,
+                Err(_) => self.config.jobs.unwrap_or(5),
+            },
+        };
+
+        ensure!(max_simult > 0, "simultaneous downloads must be at least 1");
+        Ok(max_simult)
+    }
+
+    /// The URI a dependency is fetched from, after mirrors are applied
+    pub(super) fn mirrored_uri(&self, uri: &str) -> Result<String> {
+        apply_mirrors(&self.mirrors()?, uri)
+    }
+
+    fn mirrors(&self) -> Result<Vec<Mirror>> {
+        if !self.mirrors.is_empty() {
+            return Ok(self.mirrors.clone());
+        }
+
+        match env::var("BT_MIRROR") {
+            Ok(mirrors) => mirrors
+                .split(',')
+                .filter(|m| !m.trim().is_empty())
+                .map(|m| m.trim().parse())
+                .collect(),
+            Err(_) => Ok(self.config.mirrors.clone()),
+        }
+    }
+}
+
+/// A header sent with dependency downloads, given as `Name: value`
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Header {
+    name: HeaderName,
+    value: HeaderValue,
+}
+
+impl str::FromStr for Header {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Header, Self::Err> {
+        let (name, value) = input
+            .split_once(':')
+            .ok_or_else(|| anyhow!("invalid header {input}, should have format `Name: value`"))?;
+
+        let mut value = HeaderValue::from_str(value.trim())
+            .with_context(|| format!("invalid value for header {name}"))?;
+        value.set_sensitive(true);
+
+        Ok(Header {
+            name: HeaderName::from_str(name.trim())
+                .with_context(|| format!("invalid header name {name}"))?,
+            value,
+        })
+    }
+}
+
+/// Credentials and headers added to dependency downloads
+///
+/// Settings for the dependency's host in `hosts.toml` are used first, then `BT_DOWNLOAD_TOKEN`.
+/// Headers from `--header` are sent to every host and replace any credentials.
+#[derive(Clone, Default)]
+pub(super) struct DownloadAuth {
+    headers: Vec<Header>,
+    token: Option<String>,
+    hosts: Hosts,
+}
+
+impl DownloadAuth {
+    fn apply(&self, mut req: RequestBuilder, uri: &str) -> Result<RequestBuilder> {
+        let url = Url::parse(uri)?;
+
+        match url.host_str().and_then(|h| self.hosts.get(h)) {
+            Some(host) => {
+                if let Some(token) = &host.token {
+                    req = req.bearer_auth(token);
+                } else if let Some(username) = &host.username {
+                    req = req.basic_auth(username, host.password.as_ref());
+                }
+                for (name, value) in &host.headers {
+                    req = req.header(name, value);
+                }
+            }
+            None => {
+                if let Some(token) = &self.token {
+                    req = req.bearer_auth(token);
+                }
+            }
+        }
+
+        let mut headers = HeaderMap::new();
+        for header in &self.headers {
+            headers.append(header.name.clone(), header.value.clone());
+        }
+        Ok(req.headers(headers))
+    }
+}
+
+/// A mirror that dependency URIs are rewritten to before they are downloaded
+///
+/// Mirrors are given as `url` or `host=url`. A mirror with a host only applies to dependencies
+/// from that host and takes precedence over a mirror without one. The scheme and host of the
+/// dependency URI are replaced with the mirror URL, and `{originalHost}` in the mirror URL is
+/// replaced with the host of the dependency URI.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Mirror {
+    host: Option<String>,
+    base: String,
+}
+
+impl str::FromStr for Mirror {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Mirror, Self::Err> {
+        let (host, base) = match input.split_once('=') {
+            Some((host, base)) if !host.contains('/') => (Some(host.to_lowercase()), base),
+            _ => (None, input),
+        };
+
+        Url::parse(&base.replace("{originalHost}", "host"))
+            .with_context(|| format!("invalid mirror url {base}"))?;
+
+        Ok(Mirror {
+            host,
+            base: base.trim_end_matches('/').to_owned(),
+        })
+    }
+}
+
+fn apply_mirrors(mirrors: &[Mirror], uri: &str) -> Result<String> {
+    let url = Url::parse(uri)?;
+    let host = match url.host_str() {
+        Some(host) => host,
+        None => return Ok(uri.to_owned()),
+    };
+
+    let mirror = mirrors
+        .iter()
+        .find(|m| m.host.as_deref() == Some(host))
+        .or_else(|| mirrors.iter().find(|m| m.host.is_none()));
+
+    Ok(match mirror {
+        Some(mirror) => {
+            let mut rewritten = mirror.base.replace("{originalHost}", host);
+            rewritten.push_str(url.path());
+            if let Some(query) = url.query() {
+                rewritten.push('?');
+                rewritten.push_str(query);
+            }
+            rewritten
+        }
+        None => uri.to_owned(),
+    })
+}
+
+/// Caps the combined throughput of every download. Each chunk reserves its share of time on a
+/// shared clock and waits for the reservation to end, so concurrent downloads split the rate.
+#[derive(Debug)]
+pub(super) struct RateLimit {
+    bytes_per_sec: u64,
+    next: std::sync::Mutex<Instant>,
+}
+
+impl RateLimit {
+    fn new(bytes_per_sec: u64) -> RateLimit {
+        RateLimit {
+            bytes_per_sec,
+            next: std::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    /// How long to wait before `bytes` more may be transferred
+    fn reserve(&self, bytes: u64, now: Instant) -> Duration {
+        let mut next = self.next.lock().expect("rate limit lock poisoned");
+        let start = (*next).max(now);
+        *next = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        next.saturating_duration_since(now)
+    }
+
+    async fn throttle(&self, bytes: u64) {
+        let wait = self.reserve(bytes, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// How a dependency ended up in the binding
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(super) enum ArtifactStatus {
+    /// already in the binding
+    #[default]
+    Present,
+    /// linked from the download cache
+    Cached,
+    Downloaded,
+    Failed,
+    /// not attempted, or stopped, because another download failed or downloads were cancelled
+    Cancelled,
+}
+
+impl ArtifactStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ArtifactStatus::Present => "present",
+            ArtifactStatus::Cached => "cached",
+            ArtifactStatus::Downloaded => "downloaded",
+            ArtifactStatus::Failed => "failed",
+            ArtifactStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// What it took to put a dependency in the binding
+#[derive(Debug, Default)]
+pub(super) struct Fetched {
+    status: ArtifactStatus,
+    /// bytes fetched over the network
+    bytes: u64,
+    /// downloads restarted from scratch after a resumed download failed its checksum
+    retries: u32,
+}
+
+/// The outcome for one dependency of `download_dependencies`
+#[derive(Debug)]
+pub(super) struct ArtifactReport {
+    pub(super) uri: String,
+    pub(super) sha256: String,
+    pub(super) status: ArtifactStatus,
+    pub(super) bytes: u64,
+    pub(super) duration: Duration,
+    pub(super) retries: u32,
+    pub(super) error: Option<anyhow::Error>,
+}
+
+impl ArtifactReport {
+    fn new(d: &Dependency, status: ArtifactStatus) -> ArtifactReport {
+        ArtifactReport {
+            uri: d.uri.clone(),
+            sha256: d.sha256.clone(),
+            status,
+            bytes: 0,
+            duration: Duration::ZERO,
+            retries: 0,
+            error: None,
+        }
+    }
+}
+
+/// The outcome of `download_dependencies`, failed downloads are recorded rather than returned
+/// so they can be reported along with everything else
+#[derive(Debug, Default)]
+pub(super) struct DownloadReport {
+    pub(super) artifacts: Vec<ArtifactReport>,
+    pub(super) duration: Duration,
+    /// downloads were stopped with Ctrl-C
+    pub(super) interrupted: bool,
+}
+
+impl DownloadReport {
+    /// Fails with the first download error, or if downloads were interrupted
+    pub(super) fn check(self) -> Result<()> {
+        for a in self.artifacts {
+            if let Some(err) = a.error {
+                return Err(err.context(format!("Download of {} failed", a.uri)));
+            }
+        }
+        ensure!(!self.interrupted, "downloads cancelled");
+        Ok(())
+    }
+
+    pub(super) fn to_json(&self) -> Json {
+        let count = |status| self.artifacts.iter().filter(|a| a.status == status).count();
+
+        json!({
+            "duration_ms": self.duration.as_millis() as u64,
+            "interrupted": self.interrupted,
+            "summary": {
+                "total": self.artifacts.len(),
+                "present": count(ArtifactStatus::Present),
+                "cached": count(ArtifactStatus::Cached),
+                "downloaded": count(ArtifactStatus::Downloaded),
+                "failed": count(ArtifactStatus::Failed),
+                "cancelled": count(ArtifactStatus::Cancelled),
+                "bytes": self.artifacts.iter().map(|a| a.bytes).sum::<u64>(),
+            },
+            "artifacts": self.artifacts.iter().map(|a| json!({
+                "uri": a.uri,
+                "sha256": a.sha256,
+                "status": a.status.as_str(),
+                "bytes": a.bytes,
+                "duration_ms": a.duration.as_millis() as u64,
+                "retries": a.retries,
+                "error": a.error.as_ref().map(|e| format!("{e:#}")),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+pub(super) fn download_dependencies(
+    deps: Vec<Dependency>,
+    binding_path: path::PathBuf,
+    opts: &DownloadOptions,
+) -> Result<DownloadReport> {
+    // signed artifacts are refused rather than added unchecked
+    let verifier = match &opts.verify_key {
+        Some(key) if !opts.skip_verify => Some(Arc::new(cosign::Verifier::load(key)?)),
+        _ => None,
+    };
+    if !opts.skip_verify {
+        if let Some(signed) = deps.iter().find(|d| d.signature.is_some()) {
+            ensure!(
+                verifier.is_some(),
+                "{} is signed, pass --verify-key with the signer's public key or --insecure-skip-verify",
+                signed.uri
+            );
+        }
+        ensure!(
+            !opts.offline || verifier.is_none(),
+            "signatures can't be checked offline, pass --insecure-skip-verify to use the cache anyway"
+        );
+    }
+
+    if opts.offline {
+        let cache_dir = opts
+            .cache_dir
+            .as_ref()
+            .ok_or_else(|| anyhow!("offline mode requires the download cache"))?;
+        let started = Instant::now();
+        let cache = ArtifactCache::open(cache_dir);
+        let artifacts = runtime()?.block_on(link_cached(&deps, &binding_path, &cache));
+        cache.save()?;
+        return Ok(DownloadReport {
+            artifacts: artifacts?,
+            duration: started.elapsed(),
+            interrupted: false,
+        });
+    }
+
+    let max_simult = opts.max_simultaneous()?;
+    let mirrors = opts.mirrors()?;
+    let auth = Arc::new(DownloadAuth {
+        headers: opts.headers.clone(),
+        token: env::var("BT_DOWNLOAD_TOKEN").ok().filter(|t| !t.is_empty()),
+        hosts: hosts::load()?,
+    });
+    let rate = opts.limit_rate.map(|r| Arc::new(RateLimit::new(r)));
+
+    let progress = if opts.quiet {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    };
+    let total = progress.add(
+        ProgressBar::new(deps.len() as u64).with_style(
+            ProgressStyle::with_template("{prefix:40!} [{bar:30}] {pos}/{len} ({elapsed})")?
+                .progress_chars("=> "),
+        ),
+    );
+    total.set_prefix("Downloading dependencies");
+
+    let cache = match &opts.cache_dir {
+        Some(cache_dir) => {
+            let cache = ArtifactCache::open(cache_dir);
+            fs::create_dir_all(cache.dir())
+                .with_context(|| format!("cannot create cache directory {:?}", cache.dir()))?;
+            Some(Arc::new(cache))
+        }
+        None => None,
+    };
+
+    // the index is saved even when a download fails, so what was verified isn't hashed again
+    let index = cache.clone();
+    let report = runtime()?.block_on(async move {
+        let client = configure_client(&opts.binding_root, opts.timeouts)?;
+        let limit = Arc::new(Semaphore::new(max_simult));
+        let binding_path = Arc::new(binding_path);
+
+        // every dependency gets its own task, the semaphore bounds how many are on the wire
+        let mut downloads = JoinSet::new();
+        let started = Instant::now();
+        let mut reports = vec![];
+        for (i, mut d) in deps.into_iter().enumerate() {
+            d.uri = apply_mirrors(&mirrors, &d.uri)?;
+            d.signature = d
+                .signature
+                .map(|s| apply_mirrors(&mirrors, &s))
+                .transpose()?;
+            reports.push(ArtifactReport::new(&d, ArtifactStatus::Cancelled));
+
+            let client = client.clone();
+            let auth = Arc::clone(&auth);
+            let limit = Arc::clone(&limit);
+            let binding_path = Arc::clone(&binding_path);
+            let cache = cache.clone();
+            let rate = rate.clone();
+            let verifier = verifier.clone();
+            let progress = progress.clone();
+            let total = total.clone();
+
+            downloads.spawn(async move {
+                let _permit = limit.acquire_owned().await?;
+                let started = Instant::now();
+                let fetched = async {
+                    // the artifact is checked against the signed sha256 as it is downloaded
+                    if let Some(verifier) = &verifier {
+                        d.check_signature(&client, &auth, verifier).await?;
+                    }
+                    d.download(
+                        &client,
+                        &auth,
+                        &binding_path,
+                        cache.as_ref(),
+                        rate.as_deref(),
+                        &progress,
+                    )
+                    .await
+                }
+                .await;
+                total.inc(1);
+                Ok::<_, anyhow::Error>((i, fetched, started.elapsed()))
+            });
+        }
+
+        // dropping the join set aborts whatever is still running, partial files are kept
+        // so the next run can resume them
+        let cancel = tokio::signal::ctrl_c();
+        tokio::pin!(cancel);
+        let mut interrupted = false;
+        loop {
+            tokio::select! {
+                res = downloads.join_next() => match res {
+                    Some(res) => {
+                        let (i, fetched, duration) = res??;
+                        let report = &mut reports[i];
+                        report.duration = duration;
+                        match fetched {
+                            Ok(fetched) => {
+                                report.status = fetched.status;
+                                report.bytes = fetched.bytes;
+                                report.retries = fetched.retries;
+                            }
+                            Err(err) => {
+                                report.status = ArtifactStatus::Failed;
+                                report.error = Some(err);
+                                total.abandon();
+                                break;
+                            }
+                        }
+                    }
+                    None => {
+                        total.finish();
+                        break;
+                    }
+                },
+                _ = &mut cancel => {
+                    total.abandon();
+                    interrupted = true;
+                    break;
+                }
+            }
+        }
+
+        Ok(DownloadReport {
+            artifacts: reports,
+            duration: started.elapsed(),
+            interrupted,
+        })
+    });
+    if let Some(index) = index {
+        index.save()?;
+    }
+    report
+}
+
+/// A dependency as it would be downloaded
+#[derive(Debug)]
+pub(super) struct PlannedDownload {
+    /// URI the artifact is fetched from, after mirrors are applied
+    pub(super) uri: String,
+    pub(super) dest: path::PathBuf,
+    pub(super) size: Option<u64>,
+    /// true if the artifact is already in the binding or the cache
+    pub(super) cached: bool,
+    /// why the size couldn't be determined
+    pub(super) problem: Option<String>,
+}
+
+/// Works out what `download_dependencies` would fetch, without downloading anything. Sizes come
+/// from `HEAD` requests, or from disk for artifacts that are already available.
+pub(super) fn plan_downloads(
+    deps: &[Dependency],
+    binding_path: &path::Path,
+    opts: &DownloadOptions,
+) -> Result<Vec<PlannedDownload>> {
+    let max_simult = opts.max_simultaneous()?;
+    let mirrors = opts.mirrors()?;
+    let auth = Arc::new(DownloadAuth {
+        headers: opts.headers.clone(),
+        token: env::var("BT_DOWNLOAD_TOKEN").ok().filter(|t| !t.is_empty()),
+        hosts: hosts::load()?,
+    });
+    let cache_dir = opts.cache_dir.as_ref().map(|c| c.join("artifacts"));
+
+    runtime()?.block_on(async {
+        let client = configure_client(&opts.binding_root, opts.timeouts)?;
+        let limit = Arc::new(Semaphore::new(max_simult));
+
+        let mut plans = JoinSet::new();
+        for (i, d) in deps.iter().enumerate() {
+            let mut d = d.clone();
+            d.uri = apply_mirrors(&mirrors, &d.uri)?;
+            let dest = binding_path.join("binaries").join(d.filename()?);
+            let cached = cache_dir.as_ref().map(|c| c.join(&d.sha256));
+
+            let client = client.clone();
+            let auth = Arc::clone(&auth);
+            let limit = Arc::clone(&limit);
+
+            plans.spawn(async move {
+                for existing in std::iter::once(dest.clone()).chain(cached) {
+                    if d.verify(existing.clone()).await? {
+                        return Ok((
+                            i,
+                            PlannedDownload {
+                                uri: d.uri,
+                                dest,
+                                size: Some(tokio::fs::metadata(&existing).await?.len()),
+                                cached: true,
+                                problem: None,
+                            },
+                        ));
+                    }
+                }
+
+                let _permit = limit.acquire_owned().await?;
+                let (size, problem) = match d.local_path() {
+                    Some(src) => match tokio::fs::metadata(&src).await {
+                        Ok(meta) => (Some(meta.len()), None),
+                        Err(err) => (None, Some(format!("{err}"))),
+                    },
+                    None if storage::is_object_uri(&d.uri) => match storage::size(&d.uri).await {
+                        Ok(size) => (Some(size), None),
+                        Err(err) => (None, Some(format!("{err:#}"))),
+                    },
+                    None => match auth.apply(client.head(&d.uri), &d.uri)?.send().await {
+                        Ok(res) if res.status().is_success() => (
+                            res.headers()
+                                .get(CONTENT_LENGTH)
+                                .and_then(|l| l.to_str().ok())
+                                .and_then(|l| l.parse().ok()),
+                            None,
+                        ),
+                        Ok(res) => (None, Some(format!("HTTP {}", res.status()))),
+                        Err(err) => (None, Some(format!("{err:#}"))),
+                    },
+                };
+
+                Ok::<_, anyhow::Error>((
+                    i,
+                    PlannedDownload {
+                        uri: d.uri,
+                        dest,
+                        size,
+                        cached: false,
+                        problem,
+                    },
+                ))
+            });
+        }
+
+        let mut planned = vec![];
+        while let Some(res) = plans.join_next().await {
+            planned.push(res??);
+        }
+        planned.sort_by_key(|(i, _)| *i);
+        Ok(planned.into_iter().map(|(_, p)| p).collect())
+    })
+}
+
+/// A binary referenced by a dependency-mapping binding that is missing or doesn't match its key
+#[derive(Debug, PartialEq)]
+pub(super) struct DamagedBinary {
+    pub(super) sha256: String,
+    pub(super) path: path::PathBuf,
+    pub(super) missing: bool,
+}
+
+/// Re-verifies every binary referenced by the keys of a dependency-mapping binding, returning
+/// the ones that are missing or whose checksum no longer matches
+pub(super) fn damaged_binaries(binding_path: &path::Path) -> Result<Vec<DamagedBinary>> {
+    let mut damaged = vec![];
+
+    // mappings to binaries that are served from elsewhere are left alone
+    for m in mapped_binaries(binding_path)? {
+        if !m.local && !m.path.exists() {
+            continue;
+        }
+
+        let dep = Dependency {
+            sha256: m.sha256,
+            ..Default::default()
+        };
+        if !dep.file_matches(&m.path)? {
+            damaged.push(DamagedBinary {
+                missing: !m.path.exists(),
+                sha256: dep.sha256,
+                path: m.path,
+            });
+        }
+    }
+
+    damaged.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(damaged)
+}
+
+/// Pushes a dependency-mapping binding to an OCI registry as an artifact, returning the digest
+/// of the pushed manifest
+pub(super) fn push_binding(
+    binding_path: &path::Path,
+    image: &str,
+    binding_root: &path::Path,
+) -> Result<String> {
+    let damaged = damaged_binaries(binding_path)?;
+    ensure!(
+        damaged.is_empty(),
+        "binding {} has {} missing or damaged binaries, repair it with --refresh before pushing",
+        binding_path.display(),
+        damaged.len()
+    );
+
+    let files = binding_files(binding_path)?;
+    runtime()?.block_on(async {
+        let client = configure_client(binding_root, Timeouts::default())?;
+        oci::push_binding(&client, &image.parse()?, binding_path, &files).await
+    })
+}
+
+/// Pulls a dependency-mapping binding pushed to an OCI registry into `binding_path`. Files that
+/// are already there with other contents are only replaced when `replace` agrees, which is asked
+/// before anything is pulled. Returns the files of the binding, along with whether they were
+/// downloaded or already present.
+pub(super) fn pull_binding(
+    binding_path: &path::Path,
+    image: &str,
+    binding_root: &path::Path,
+    replace: impl Fn(&str) -> bool,
+) -> Result<Vec<(String, bool)>> {
+    let pulled = runtime()?.block_on(async {
+        let client = configure_client(binding_root, Timeouts::default())?;
+        let reference = image.parse()?;
+        let registry = oci::Registry::new(&client, &reference);
+
+        let mut pulled = vec![];
+        let mut missing = vec![];
+        for (file, digest) in oci::binding_layers(&registry).await? {
+            let dest = binding_path.join(&file);
+            let present = Dependency {
+                sha256: digest.trim_start_matches("sha256:").to_owned(),
+                ..Default::default()
+            };
+            if present.verify(dest.clone()).await? {
+                pulled.push((file, false));
+            } else {
+                missing.push((file, digest, dest));
+            }
+        }
+
+        for (file, _, dest) in &missing {
+            ensure!(
+                !dest.exists() || replace(file),
+                "{file} in {} is different from {image}, it was not replaced",
+                binding_path.display()
+            );
+        }
+
+        // blobs are streamed to disk and checked against their digest as they are fetched
+        for (file, digest, dest) in missing {
+            if let Some(parent) = dest.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            registry.blob_to_file(&digest, &dest).await?;
+            pulled.push((file, true));
+        }
+        Ok::<_, anyhow::Error>(pulled)
+    })?;
+
+    let damaged = damaged_binaries(binding_path)?;
+    ensure!(
+        damaged.is_empty(),
+        "binding pulled from {image} has {} missing or damaged binaries, starting with {}",
+        damaged.len(),
+        damaged[0].path.display()
+    );
+    Ok(pulled)
+}
+
+/// Whether `path` is a file of a binding, either a key or a file in `binaries/`. Anything that
+/// could end up outside of the binding is refused.
+pub(super) fn is_binding_file(path: &str) -> bool {
+    is_binding_name(path.strip_prefix("binaries/").unwrap_or(path))
+}
+
+/// Whether a binding name, which may come from an untrusted bundle, names a directory right
+/// under the binding root
+fn is_binding_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains('/')
+        && !name.contains('\\')
+        && !name.contains(':')
+}
+
+/// Name of the manifest that starts a bundle, listing the checksum of every file in it
+const BUNDLE_MANIFEST: &str = "bundle.toml";
+
+/// Writes a dependency-mapping binding to a gzipped tarball that can be carried to a
+/// disconnected network and imported there. Returns the number of files in the bundle.
+pub(super) fn write_bundle(binding_path: &path::Path, dest: &path::Path) -> Result<usize> {
+    let damaged = damaged_binaries(binding_path)?;
+    ensure!(
+        damaged.is_empty(),
+        "binding {} has {} missing or damaged binaries, repair it with --refresh before bundling",
+        binding_path.display(),
+        damaged.len()
+    );
+
+    let files = binding_files(binding_path)?;
+    let mut listed = toml::value::Array::new();
+    for file in &files {
+        let mut entry = toml::value::Table::new();
+        entry.insert("path".into(), Toml::String(file.clone()));
+        entry.insert(
+            "sha256".into(),
+            Toml::String(hex::encode(hash_file(&binding_path.join(file))?.finalize())),
+        );
+        listed.push(Toml::Table(entry));
+    }
+    let mut manifest = toml::value::Table::new();
+    if let Some(name) = binding_path.file_name() {
+        manifest.insert(
+            "binding".into(),
+            Toml::String(name.to_string_lossy().into_owned()),
+        );
+    }
+    manifest.insert("files".into(), Toml::Array(listed));
+    let manifest = toml::to_string(&Toml::Table(manifest))?;
+
+    let out = File::create(dest).with_context(|| format!("cannot create {}", dest.display()))?;
+    let mut tarball = tar::Builder::new(flate2::write::GzEncoder::new(
+        out,
+        flate2::Compression::default(),
+    ));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tarball.append_data(&mut header, BUNDLE_MANIFEST, manifest.as_bytes())?;
+    for file in &files {
+        tarball
+            .append_path_with_name(binding_path.join(file), file)
+            .with_context(|| format!("cannot add {file} to {}", dest.display()))?;
+    }
+    tarball.into_inner()?.finish()?;
+
+    Ok(files.len())
+}
+
+/// Unpacks a bundle made by [`write_bundle`] into a binding under `bindings_root`, checking
+/// every file against the bundle's manifest. The binding keeps the name it was bundled with
+/// unless `name` is given. Returns the path of the binding and the number of files imported.
+pub(super) fn import_bundle(
+    bundle: &path::Path,
+    bindings_root: &path::Path,
+    name: Option<&str>,
+) -> Result<(path::PathBuf, usize)> {
+    let input = File::open(bundle).with_context(|| format!("cannot open {}", bundle.display()))?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(input));
+    let mut entries = archive.entries()?;
+
+    // the manifest comes first, so files can be checked as they are unpacked
+    let mut first = entries
+        .next()
+        .ok_or_else(|| anyhow!("bundle {} is empty", bundle.display()))?
+        .with_context(|| format!("cannot read bundle {}", bundle.display()))?;
+    ensure!(
+        first.path()?.to_string_lossy() == BUNDLE_MANIFEST,
+        "{} is not a bundle, it doesn't start with {BUNDLE_MANIFEST}",
+        bundle.display()
+    );
+    let mut manifest = String::new();
+    first.read_to_string(&mut manifest)?;
+    let manifest: Toml = manifest
+        .parse()
+        .with_context(|| format!("cannot parse {BUNDLE_MANIFEST}"))?;
+
+    let mut expected = HashMap::new();
+    for file in manifest
+        .get("files")
+        .and_then(|f| f.as_array())
+        .ok_or_else(|| anyhow!("{BUNDLE_MANIFEST} has no files"))?
+    {
+        let path = optional_string(file.get("path"), "path")?
+            .ok_or_else(|| anyhow!("{BUNDLE_MANIFEST} lists a file without a path"))?;
+        let sha256 = optional_string(file.get("sha256"), "sha256")?
+            .ok_or_else(|| anyhow!("{BUNDLE_MANIFEST} has no sha256 for {path}"))?;
+        ensure!(
+            is_binding_file(&path),
+            "bundle file {path} is outside of the binding"
+        );
+        expected.insert(path, sha256);
+    }
+
+    let name = match name {
+        Some(name) => name.to_owned(),
+        None => optional_string(manifest.get("binding"), "binding")?
+            .unwrap_or_else(|| String::from("dependency-mapping")),
+    };
+    ensure!(
+        is_binding_name(&name),
+        "binding name {name} would be outside of the binding root"
+    );
+    let binding_path = bindings_root.join(name);
+    let existed = binding_path.exists();
+    fs::create_dir_all(binding_path.join("binaries"))?;
+
+    // a binding this created is removed again, rather than left half written or damaged
+    let imported = unpack_bundle(entries, &binding_path, expected).and_then(|imported| {
+        let damaged = damaged_binaries(&binding_path)?;
+        ensure!(
+            damaged.is_empty(),
+            "binding imported from {} has {} missing or damaged binaries, starting with {}",
+            bundle.display(),
+            damaged.len(),
+            damaged[0].path.display()
+        );
+        Ok(imported)
+    });
+    match imported {
+        Ok(imported) => Ok((binding_path, imported)),
+        Err(err) => {
+            if !existed {
+                fs::remove_dir_all(&binding_path)?;
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Unpacks the files after the manifest into the binding, checking each against its sha256.
+/// Returns the number of files unpacked.
+fn unpack_bundle<R: Read>(
+    entries: tar::Entries<'_, R>,
+    binding_path: &path::Path,
+    mut expected: HashMap<String, String>,
+) -> Result<usize> {
+    let mut imported = 0;
+    for entry in entries {
+        let mut entry = entry?;
+        let file = entry.path()?.to_string_lossy().into_owned();
+        let sha256 = expected
+            .remove(&file)
+            .ok_or_else(|| anyhow!("bundle file {file} is not in {BUNDLE_MANIFEST}"))?;
+
+        let dest = binding_path.join(&file);
+        let mut part = dest.as_os_str().to_owned();
+        part.push(".part");
+        let part = path::PathBuf::from(part);
+        io::copy(
+            &mut entry,
+            &mut File::create(&part).with_context(|| format!("cannot create {part:?}"))?,
+        )?;
+
+        let hash = hex::encode(hash_file(&part)?.finalize());
+        if hash != sha256 {
+            fs::remove_file(&part)?;
+            bail!("checksum mismatch for {file}, expected {sha256} but got {hash}");
+        }
+        fs::rename(&part, &dest).with_context(|| format!("cannot rename {part:?} to {dest:?}"))?;
+        imported += 1;
+    }
+    ensure!(
+        expected.is_empty(),
+        "bundle is missing {} files listed in {BUNDLE_MANIFEST}",
+        expected.len()
+    );
+    Ok(imported)
+}
+
+/// The files of a binding relative to it, leaving out hidden files and partial downloads
+fn binding_files(binding_path: &path::Path) -> Result<Vec<String>> {
+    let mut files = vec![];
+    let mut dirs = vec![String::new()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(binding_path.join(&dir))
+            .with_context(|| format!("cannot read binding {}", binding_path.display()))?
+        {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') || name.ends_with(".part") {
+                continue;
+            }
+
+            let relative = if dir.is_empty() {
+                name
+            } else {
+                format!("{dir}/{name}")
+            };
+            if entry.path().is_dir() {
+                dirs.push(relative);
+            } else {
+                files.push(relative);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// A sha256 key of a dependency-mapping binding and the binary it maps to
+struct MappedBinary {
+    sha256: String,
+    key: path::PathBuf,
+    /// where the binary is in the binding's `binaries/` directory
+    path: path::PathBuf,
+    /// true if the key has a `file://` URI, rather than pointing at a server
+    local: bool,
+}
+
+fn mapped_binaries(binding_path: &path::Path) -> Result<Vec<MappedBinary>> {
+    let mut mapped = vec![];
+
+    for entry in fs::read_dir(binding_path)
+        .with_context(|| format!("cannot read binding {}", binding_path.display()))?
+    {
+        let key = entry?.path();
+        let sha256 = key
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if !key.is_file() || sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+
+        // values look like `file:///bindings/<name>/binaries/<filename>`, or `<prefix><filename>`
+        let value = fs::read_to_string(&key).with_context(|| format!("cannot read {key:?}"))?;
+        let value = value.trim();
+        let filename = value
+            .rsplit('/')
+            .next()
+            .filter(|f| !f.is_empty())
+            .ok_or_else(|| anyhow!("no binary for key {sha256}"))?;
+
+        mapped.push(MappedBinary {
+            path: binding_path.join("binaries").join(filename),
+            local: value.starts_with("file://"),
+            sha256: sha256.to_lowercase(),
+            key,
+        });
+    }
+
+    Ok(mapped)
+}
+
+/// What was removed from a dependency-mapping binding by a purge
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct Purged {
+    /// binaries no key referred to, with their sizes
+    pub(super) binaries: Vec<(path::PathBuf, u64)>,
+    /// sha256 keys whose binaries were gone
+    pub(super) keys: Vec<String>,
+}
+
+/// Removes binaries that no key refers to and keys whose binaries are gone, along with their
+/// entries in the manifest
+pub(super) fn purge_binding(binding_path: &path::Path) -> Result<Purged> {
+    let mapped = mapped_binaries(binding_path)?;
+    let mut purged = Purged::default();
+
+    let binaries = binding_path.join("binaries");
+    if binaries.is_dir() {
+        for entry in fs::read_dir(&binaries)? {
+            let path = entry?.path();
+            if path.is_file() && !mapped.iter().any(|m| m.path == path) {
+                let size = fs::metadata(&path)?.len();
+                fs::remove_file(&path).with_context(|| format!("cannot remove {path:?}"))?;
+                purged.binaries.push((path, size));
+            }
+        }
+    }
+
+    for m in mapped.iter().filter(|m| m.local && !m.path.exists()) {
+        fs::remove_file(&m.key).with_context(|| format!("cannot remove {:?}", m.key))?;
+        purged.keys.push(m.sha256.clone());
+    }
+
+    let manifest_path = binding_path.join(MANIFEST_KEY);
+    if !purged.keys.is_empty() && manifest_path.exists() {
+        let mut manifest = fs::read_to_string(&manifest_path)?
+            .parse::<Toml>()
+            .with_context(|| format!("invalid {MANIFEST_KEY}"))?;
+        if let Some(manifest) = manifest.as_table_mut() {
+            for sha256 in &purged.keys {
+                manifest.remove(sha256);
+            }
+        }
+        fs::write(&manifest_path, toml::to_string(&manifest)?)
+            .with_context(|| format!("cannot write {manifest_path:?}"))?;
+    }
+
+    purged.binaries.sort();
+    purged.keys.sort();
+    Ok(purged)
+}
+
+/// Compares versions like `17.0.1+12` a part at a time, numerically where both parts are
+/// numbers
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |v: &str| {
+        v.split(|c: char| !c.is_ascii_alphanumeric())
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (parts(a), parts(b));
+
+    for (x, y) in a.iter().zip(&b) {
+        let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if order != std::cmp::Ordering::Equal {
+            return order;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+/// Removes all but the newest `keep` versions of each dependency id in a binding, going by the
+/// ids and versions in its manifest. Binaries still mapped by another key are kept. Returns the
+/// dependencies that were removed.
+pub(super) fn keep_latest(binding_path: &path::Path, keep: usize) -> Result<Vec<Dependency>> {
+    let manifest_path = binding_path.join(MANIFEST_KEY);
+    let mut manifest = read_manifest(binding_path)?;
+    let deps = manifest_dependencies(&manifest)?;
+
+    // dependencies without an id or version are never removed
+    let mut removed = vec![];
+    let mut versions: Vec<(&str, Vec<&str>)> = vec![];
+    for d in &deps {
+        if let (Some(id), Some(version)) = (&d.id, &d.version) {
+            match versions.iter_mut().find(|(i, _)| i == id) {
+                Some((_, v)) if !v.contains(&version.as_str()) => v.push(version),
+                Some(_) => {}
+                None => versions.push((id, vec![version])),
+            }
+        }
+    }
+    for (id, mut v) in versions {
+        v.sort_by(|a, b| compare_versions(b, a));
+        removed.extend(
+            deps.iter()
+                .filter(|d| d.id.as_deref() == Some(id))
+                .filter(|d| !v[..keep.min(v.len())].contains(&d.version.as_deref().unwrap_or("")))
+                .cloned(),
+        );
+    }
+
+    remove_mappings(binding_path, &mut manifest, &removed)?;
+    fs::write(&manifest_path, toml::to_string(&Toml::Table(manifest))?)
+        .with_context(|| format!("cannot write {manifest_path:?}"))?;
+    Ok(removed)
+}
+
+/// Removes the keys of `removed` and their manifest entries, along with their binaries unless
+/// another key still maps them
+fn remove_mappings(
+    binding_path: &path::Path,
+    manifest: &mut toml::value::Table,
+    removed: &[Dependency],
+) -> Result<()> {
+    let (dropped, kept): (Vec<_>, Vec<_>) = mapped_binaries(binding_path)?
+        .into_iter()
+        .partition(|m| removed.iter().any(|d| d.sha256 == m.sha256));
+    for m in &dropped {
+        fs::remove_file(&m.key).with_context(|| format!("cannot remove {:?}", m.key))?;
+        if m.path.exists() && !kept.iter().any(|k| k.path == m.path) {
+            fs::remove_file(&m.path).with_context(|| format!("cannot remove {:?}", m.path))?;
+        }
+    }
+    for d in removed {
+        manifest.remove(&d.sha256);
+    }
+    Ok(())
+}
+
+/// How a dependency-mapping binding differs from the dependencies its buildpacks have now
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct BindingUpdate {
+    /// dependencies the binding doesn't map yet, new ones or new versions of ones it has
+    pub(super) added: Vec<Dependency>,
+    /// dependencies the binding maps that none of the buildpacks have anymore
+    pub(super) retired: Vec<Dependency>,
+    /// how many of the dependencies the binding already maps
+    pub(super) unchanged: usize,
+}
+
+/// Compares the keys of a binding with `deps`, going by sha256. Only what's mapped but not in
+/// `current`, every dependency of the buildpacks before `--include` and `--exclude` picked from
+/// them, is retired. Retired dependencies are described by the manifest when the binding has one.
+pub(super) fn compare_binding(
+    binding_path: &path::Path,
+    deps: &[Dependency],
+    current: &[Dependency],
+) -> Result<BindingUpdate> {
+    if !binding_path.is_dir() {
+        return Ok(BindingUpdate {
+            added: deps.to_vec(),
+            ..Default::default()
+        });
+    }
+
+    let mapped = mapped_binaries(binding_path)?;
+    let described = if binding_path.join(MANIFEST_KEY).exists() {
+        manifest_dependencies(&read_manifest(binding_path)?)?
+    } else {
+        vec![]
+    };
+
+    let (unchanged, added): (Vec<_>, Vec<_>) = deps
+        .iter()
+        .cloned()
+        .partition(|d| mapped.iter().any(|m| m.sha256 == d.sha256));
+    let mut retired = mapped
+        .iter()
+        .filter(|m| !deps.iter().chain(current).any(|d| d.sha256 == m.sha256))
+        .map(|m| {
+            described
+                .iter()
+                .find(|d| d.sha256 == m.sha256)
+                .cloned()
+                .unwrap_or_else(|| Dependency {
+                    sha256: m.sha256.clone(),
+                    ..Default::default()
+                })
+        })
+        .collect::<Vec<_>>();
+    retired.sort_by(|a, b| a.sha256.cmp(&b.sha256));
+
+    Ok(BindingUpdate {
+        added,
+        retired,
+        unchanged: unchanged.len(),
+    })
+}
+
+/// Removes dependencies no buildpack has anymore from a binding, along with their binaries
+pub(super) fn remove_retired(binding_path: &path::Path, retired: &[Dependency]) -> Result<()> {
+    let manifest_path = binding_path.join(MANIFEST_KEY);
+    let mut manifest = if manifest_path.exists() {
+        read_manifest(binding_path)?
+    } else {
+        toml::value::Table::new()
+    };
+
+    remove_mappings(binding_path, &mut manifest, retired)?;
+    if manifest_path.exists() {
+        fs::write(&manifest_path, toml::to_string(&Toml::Table(manifest))?)
+            .with_context(|| format!("cannot write {manifest_path:?}"))?;
+    }
+    Ok(())
+}
+
+/// Reads the manifest of a binding, which must have one
+fn read_manifest(binding_path: &path::Path) -> Result<toml::value::Table> {
+    let manifest_path = binding_path.join(MANIFEST_KEY);
+    ensure!(
+        manifest_path.exists(),
+        "binding {} has no {MANIFEST_KEY}, add its dependencies again to create one",
+        binding_path.display()
+    );
+    fs::read_to_string(&manifest_path)?
+        .parse::<Toml>()
+        .with_context(|| format!("invalid {MANIFEST_KEY}"))?
+        .as_table()
+        .cloned()
+        .ok_or_else(|| anyhow!("{MANIFEST_KEY} should be a table"))
+}
+
+fn manifest_dependencies(manifest: &toml::value::Table) -> Result<Vec<Dependency>> {
+    manifest
+        .iter()
+        .map(|(sha256, entry)| {
+            Ok(Dependency {
+                id: optional_string(entry.get("id"), "id")?,
+                version: optional_string(entry.get("version"), "version")?,
+                sha256: sha256.clone(),
+                uri: optional_string(entry.get("uri"), "uri")?.unwrap_or_default(),
+                purl: optional_string(entry.get("purl"), "purl")?,
+                filename: optional_string(entry.get("filename"), "filename")?,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Names the binaries of `deps` so that different artifacts with the same filename don't
+/// overwrite each other in `binaries/`. The artifacts of the binding keep the names they were
+/// added with, while an artifact whose filename is taken is prefixed with its short sha256.
+pub(super) fn assign_filenames(deps: &mut [Dependency], binding_path: &path::Path) -> Result<()> {
+    let known = if binding_path.join(MANIFEST_KEY).exists() {
+        manifest_dependencies(&read_manifest(binding_path)?)?
+    } else {
+        vec![]
+    };
+    let mut taken: HashMap<String, String> = known
+        .iter()
+        .filter_map(|d| Some((d.filename().ok()?, d.sha256.clone())))
+        .collect();
+
+    for d in deps.iter_mut() {
+        if let Some(existing) = known.iter().find(|k| k.sha256 == d.sha256) {
+            d.filename = existing.filename.clone();
+        }
+        let Ok(filename) = d.filename() else {
+            continue;
+        };
+
+        let filename = match taken.get(&filename) {
+            Some(sha256) if *sha256 != d.sha256 => {
+                let renamed = format!("{}-{filename}", &d.sha256[..8]);
+                d.filename = Some(renamed.clone());
+                renamed
+            }
+            _ => filename,
+        };
+        taken.insert(filename, d.sha256.clone());
+    }
+    Ok(())
+}
+
+/// A CycloneDX SBOM of the binaries in a binding, going by its manifest. Dependencies whose
+/// buildpack.toml has no package URL are given a generic one.
+pub(super) fn sbom(binding_path: &path::Path) -> Result<Json> {
+    let binaries = binding_path.join("binaries");
+    let mut components = vec![];
+    for d in manifest_dependencies(&read_manifest(binding_path)?)? {
+        let filename = match d.filename() {
+            Ok(filename) if binaries.join(&filename).is_file() => filename,
+            _ => continue,
+        };
+        components.push(sbom_component(&d, &filename));
+    }
+    components.sort_by(|a, b| {
+        (a["name"].as_str(), a["version"].as_str())
+            .cmp(&(b["name"].as_str(), b["version"].as_str()))
+    });
+
+    Ok(json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "binding-tool",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": {
+                "type": "data",
+                "name": binding_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            },
+        },
+        "components": components,
+    }))
+}
+
+fn sbom_component(d: &Dependency, filename: &str) -> Json {
+    let name = d.id.as_deref().unwrap_or(filename);
+    let purl = d.purl.clone().unwrap_or_else(|| {
+        let encode =
+            |s: &str| url::form_urlencoded::byte_serialize(s.as_bytes()).collect::<String>();
+        format!(
+            "pkg:generic/{}{}?checksum=sha256:{}&download_url={}",
+            encode(name),
+            d.version
+                .as_deref()
+                .map(|v| format!("@{}", encode(v)))
+                .unwrap_or_default(),
+            d.sha256,
+            encode(&d.uri)
+        )
+    });
+
+    let mut component = json!({
+        "type": "library",
+        "bom-ref": d.sha256,
+        "name": name,
+        "purl": purl,
+        "hashes": [{ "alg": "SHA-256", "content": d.sha256 }],
+        "externalReferences": [{ "type": "distribution", "url": d.uri }],
+    });
+    if let Some(version) = &d.version {
+        component["version"] = json!(version);
+    }
+    component
+}
+
+/// Links dependencies into the binding from the download cache, without using the network.
+/// Nothing is linked unless every artifact is available.
+async fn link_cached(
+    deps: &[Dependency],
+    binding_path: &path::Path,
+    cache: &ArtifactCache,
+) -> Result<Vec<ArtifactReport>> {
+    let mut links = vec![];
+    let mut missing = vec![];
+    let mut reports = vec![];
+
+    for d in deps {
+        let dest = binding_path.join("binaries").join(d.filename()?);
+        if d.verify(dest.clone()).await? {
+            reports.push(ArtifactReport::new(d, ArtifactStatus::Present));
+            continue;
+        }
+
+        if cache.check(&d.sha256)? {
+            links.push((cache.path(&d.sha256), dest));
+            reports.push(ArtifactReport::new(d, ArtifactStatus::Cached));
+        } else {
+            missing.push(format!("  {} (sha256 {})", d.uri, d.sha256));
+        }
+    }
+
+    ensure!(
+        missing.is_empty(),
+        "{} of {} artifacts are missing from the download cache:\n{}",
+        missing.len(),
+        deps.len(),
+        missing.join("\n")
+    );
+
+    for (cached, dest) in links {
+        link_or_copy(&cached, &dest).await?;
+    }
+    Ok(reports)
+}
+
+pub(super) fn hash_file(path: &path::Path) -> Result<Sha256> {
+    let mut fp = File::open(path).with_context(|| format!("cannot open file {path:?}"))?;
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut fp, &mut hasher)?;
+    Ok(hasher)
+}
+
+/// Location of the shared download cache, `BT_CACHE_DIR` or `binding-tool` under the user's
+/// cache directory (i.e. `~/.cache/binding-tool` on Linux)
+pub(super) fn cache_dir() -> Option<path::PathBuf> {
+    match env::var("BT_CACHE_DIR") {
+        Ok(dir) => Some(path::PathBuf::from(dir)),
+        Err(_) => dirs::cache_dir().map(|d| d.join("binding-tool")),
+    }
+}
+
+async fn link_or_copy(src: &path::Path, dest: &path::Path) -> Result<()> {
+    let _ = tokio::fs::remove_file(dest).await;
+
+    // hard links fail across file systems, fall back to a copy
+    if tokio::fs::hard_link(src, dest).await.is_err() {
+        tokio::fs::copy(src, dest)
+            .await
+            .with_context(|| format!("failed to copy {src:?} to {dest:?}"))?;
+    }
+
+    Ok(())
+}
+
+fn runtime() -> Result<Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .with_context(|| "unable to start async runtime")
+}
+
+/// Files holding certificates from `ca-certificates` bindings under the binding root
+pub(super) fn ca_certificate_files(binding_root: &path::Path) -> Result<Vec<path::PathBuf>> {
+    if !binding_root.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut files = vec![];
+    for binding in fs::read_dir(binding_root)? {
+        let binding = binding?.path();
+        let binding_type = fs::read_to_string(binding.join("type")).unwrap_or_default();
+        if binding_type.trim() != "ca-certificates" {
+            continue;
+        }
+
+        for entry in fs::read_dir(&binding)? {
+            let entry = entry?.path();
+            let name = entry
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            // skip binding metadata and the hidden directories Kubernetes projects files through
+            if entry.is_file() && name != "type" && name != "provider" && !name.starts_with('.') {
+                files.push(entry);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// CA certificates to trust in addition to the system store, from `BT_CA_BUNDLE` and any
+/// `ca-certificates` bindings
+fn extra_certificates(binding_root: &path::Path) -> Result<Vec<CertificateDer<'static>>> {
+    // bindings may hold keys that aren't PEM certificates, like a truststore and its password
+    let mut certs = vec![];
+    for file in ca_certificate_files(binding_root)? {
+        certs.extend(pem_certificates(&file)?);
+    }
+
+    if let Some(bundle) = env::var_os("BT_CA_BUNDLE").filter(|b| !b.is_empty()) {
+        let file = path::PathBuf::from(bundle);
+        let bundle = pem_certificates(&file)?;
+        ensure!(!bundle.is_empty(), "no PEM certificates in {file:?}");
+        certs.extend(bundle);
+    }
+
+    Ok(certs)
+}
+
+fn pem_certificates(file: &path::Path) -> Result<Vec<CertificateDer<'static>>> {
+    let pem = fs::read(file).with_context(|| format!("cannot read {file:?}"))?;
+    CertificateDer::pem_slice_iter(&pem)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid certificates in {file:?}"))
+}
+
+/// Proxy settings from the environment
+///
+/// `PROXY` applies to every request and wins over the standard variables. Otherwise, like curl,
+/// `https_proxy`/`HTTPS_PROXY` is used for https requests, `http_proxy` for http requests (the
+/// upper case form is ignored as it can be set by CGI) and `all_proxy`/`ALL_PROXY` for either.
+/// Hosts in `no_proxy`/`NO_PROXY` are connected to directly.
+#[derive(Debug, Default, PartialEq)]
+struct ProxySettings {
+    http: Option<String>,
+    https: Option<String>,
+    no_proxy: Option<String>,
+    /// `user:password` for proxies that require it, when they aren't part of the proxy URL
+    credentials: Option<String>,
+}
+
+impl ProxySettings {
+    fn from_env() -> ProxySettings {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|n| env::var(n).ok().filter(|v| !v.trim().is_empty()))
+        };
+
+        let proxy = var(&["PROXY"]);
+        let all = var(&["all_proxy", "ALL_PROXY"]);
+
+        ProxySettings {
+            http: proxy
+                .clone()
+                .or_else(|| var(&["http_proxy"]))
+                .or_else(|| all.clone()),
+            https: proxy
+                .or_else(|| var(&["https_proxy", "HTTPS_PROXY"]))
+                .or(all),
+            no_proxy: var(&["no_proxy", "NO_PROXY"]),
+            credentials: var(&["BT_PROXY_USER"]),
+        }
+    }
+}
+
+fn configure_proxies(
+    mut client_builder: reqwest::ClientBuilder,
+    settings: &ProxySettings,
+) -> Result<reqwest::ClientBuilder> {
+    // proxies are only what we're told to use, reqwest won't go looking on its own
+    client_builder = client_builder.no_proxy();
+
+    let configure = |url: &str, proxy: reqwest::Result<Proxy>| -> Result<Proxy> {
+        let mut proxy = proxy.with_context(|| format!("unable to parse proxy url {url}"))?;
+        if let Some(credentials) = &settings.credentials {
+            let (user, password) = credentials
+                .split_once(':')
+                .ok_or_else(|| anyhow!("BT_PROXY_USER should have format `user:password`"))?;
+            proxy = proxy.basic_auth(user, password);
+        }
+        Ok(proxy.no_proxy(
+            settings
+                .no_proxy
+                .as_deref()
+                .and_then(reqwest::NoProxy::from_string),
+        ))
+    };
+
+    if let Some(url) = &settings.http {
+        client_builder = client_builder.proxy(configure(url, Proxy::http(url))?);
+    }
+    if let Some(url) = &settings.https {
+        client_builder = client_builder.proxy(configure(url, Proxy::https(url))?);
+    }
+
+    Ok(client_builder)
+}
+
+/// Timeouts for HTTP requests in seconds, as given on the command line. Those that aren't set
+/// fall back to `BT_CONN_TIMEOUT`, `BT_READ_TIMEOUT` and `BT_REQ_TIMEOUT`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(super) struct Timeouts {
+    pub(super) connect: Option<u64>,
+    pub(super) read: Option<u64>,
+    /// the whole request, including reading the response body
+    pub(super) request: Option<u64>,
+}
+
+impl Timeouts {
+    /// Fills in the timeouts that aren't set from the environment, or the defaults
+    fn or_env(self) -> Result<Timeouts> {
+        let var = |name: &str| -> Result<Option<u64>> {
+            env::var(name)
+                .ok()
+                .map(|v| {
+                    v.parse()
+                        .with_context(|| format!("{name} should be a number of seconds"))
+                })
+                .transpose()
+        };
+
+        Ok(Timeouts {
+            connect: self.connect.or(var("BT_CONN_TIMEOUT")?).or(Some(5)),
+            read: self.read.or(var("BT_READ_TIMEOUT")?).or(Some(5)),
+            request: self.request.or(var("BT_REQ_TIMEOUT")?),
+        })
+    }
+}
+
+/// A client builder with the given timeouts and the configured proxies, but TLS left as it is
+fn client_builder(timeouts: Timeouts) -> Result<reqwest::ClientBuilder> {
+    let timeouts = timeouts.or_env()?;
+
+    let mut client_builder = reqwest::Client::builder()
+        .cookie_store(true)
+        .user_agent(concat!("binding-tool/", env!("CARGO_PKG_VERSION")));
+    if let Some(connect) = timeouts.connect {
+        client_builder = client_builder.connect_timeout(Duration::from_secs(connect));
+    }
+    if let Some(read) = timeouts.read {
+        client_builder = client_builder.read_timeout(Duration::from_secs(read));
+    }
+    if let Some(request) = timeouts.request {
+        client_builder = client_builder.timeout(Duration::from_secs(request));
+    }
+
+    configure_proxies(client_builder, &ProxySettings::from_env())
+}
+
+pub(super) fn configure_client(
+    binding_root: &path::Path,
+    timeouts: Timeouts,
+) -> Result<reqwest::Client> {
+    let mut client_builder = client_builder(timeouts)?;
+
+    let extra_roots = extra_certificates(binding_root)?;
+    let pins = hosts::load()?.pins();
+    if pins.is_empty() {
+        for cert in &extra_roots {
+            client_builder = client_builder.add_root_certificate(
+                reqwest::Certificate::from_der(cert).context("invalid CA certificate")?,
+            );
+        }
+    } else {
+        // pinning needs its own certificate verifier, so the TLS config is built by hand
+        client_builder =
+            client_builder.use_preconfigured_tls(pinning::tls_config(pins, &extra_roots)?);
+    }
+
+    client_builder
+        .build()
+        .with_context(|| "unable to configure http client")
+}
+
+fn transform_buildpack(toml: Toml) -> Result<Buildpack> {
+    Ok(Buildpack {
+        dependencies: transform(toml.clone())?,
+        ..identify_buildpack(&toml)?
+    })
+}
+
+/// The id and version of a buildpack.toml, without its dependencies
+fn identify_buildpack(toml: &Toml) -> Result<Buildpack> {
+    let info = toml.get("buildpack");
+
+    Ok(Buildpack {
+        id: optional_string(info.and_then(|b| b.get("id")), "buildpack id")?,
+        version: optional_string(info.and_then(|b| b.get("version")), "buildpack version")?,
+        dependencies: vec![],
+    })
+}
+
+fn transform(toml: Toml) -> Result<Vec<Dependency>> {
+    let bp_toml = toml
+        .as_table()
+        .with_context(|| "buildpack.toml format is invalid")?;
+
+    let metadata = bp_toml
+        .get("metadata")
+        .with_context(|| "no metadata present in buildpack.toml")?
+        .as_table()
+        .with_context(|| "metadata should be a table")?;
+
+    let deps_metadata = metadata
+        .get("dependencies")
+        .with_context(|| "no dependencies present")?
+        .as_array()
+        .with_context(|| "dependencies should be an array")?;
+
+    deps_metadata
+        .iter()
+        .enumerate()
+        .map(|(i, d)| transform_dependency(d).with_context(|| format!("invalid dependency #{i}")))
+        .collect()
+}
+
+pub(super) fn transform_dependency(d: &Toml) -> Result<Dependency> {
+    let table = d
+        .as_table()
+        .with_context(|| "dependency should be a table")?;
+
+    let uri = expand_placeholders(
+        table
+            .get("uri")
+            .with_context(|| "uri field is required")?
+            .as_str()
+            .with_context(|| "uri should be a string")?,
+        table,
+    )?;
+
+    let sha256 = match (table.get("sha256"), table.get("checksum")) {
+        (Some(sha256), None) => sha256
+            .as_str()
+            .with_context(|| "sha256 field should be a string")?,
+        (None, Some(checksum)) => match checksum
+            .as_str()
+            .with_context(|| "checksum field should be a string")?
+            .split_once(':')
+        {
+            Some(("sha256", hash)) => hash,
+            _ => bail!("only sha256 algorithm is supported"),
+        },
+        _ => bail!("sha256 or checksum field is required"),
+    };
+
+    ensure!(
+        sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit()),
+        "sha256 of {uri} should be 64 hex characters, got {sha256}"
+    );
+
+    Ok(Dependency {
+        id: optional_string(table.get("id"), "id")?,
+        version: optional_string(table.get("version"), "version")?,
+        sha256: sha256.to_lowercase(),
+        uri,
+        purl: optional_string(table.get("purl"), "purl")?,
+        signature: optional_string(table.get("signature"), "signature")?
+            .map(|s| expand_placeholders(&s, table))
+            .transpose()?,
+        filename: None,
+    })
+}
+
+/// Fills in the `${field}` placeholders of a templated URI, like
+/// `https://example.com/jdk-${version}.tar.gz`, from the fields of the dependency
+fn expand_placeholders(uri: &str, dep: &toml::value::Table) -> Result<String> {
+    let mut expanded = String::with_capacity(uri.len());
+    let mut rest = uri;
+    while let Some(start) = rest.find("${") {
+        let len = rest[start..]
+            .find('}')
+            .with_context(|| format!("unterminated placeholder in {uri}"))?;
+        let name = &rest[start + 2..start + len];
+        let value = match dep.get(name) {
+            Some(Toml::String(s)) => s.clone(),
+            Some(Toml::Integer(i)) => i.to_string(),
+            _ => bail!("{uri} uses ${{{name}}}, but the dependency has no {name} field"),
+        };
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &rest[start + len + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Binding key describing the dependencies of a dependency-mapping binding, by sha256
+pub(super) const MANIFEST_KEY: &str = "dependencies.toml";
+
+/// Adds `deps` to an existing manifest, so it describes every dependency the binding has had
+/// added
+pub(super) fn update_manifest(existing: Option<&str>, deps: &[Dependency]) -> Result<String> {
+    let mut manifest = match existing {
+        Some(existing) => existing
+            .parse::<Toml>()
+            .with_context(|| format!("invalid {MANIFEST_KEY}"))?
+            .as_table()
+            .cloned()
+            .ok_or_else(|| anyhow!("{MANIFEST_KEY} should be a table"))?,
+        None => toml::value::Table::new(),
+    };
+
+    for d in deps {
+        let mut entry = toml::value::Table::new();
+        if let Some(id) = &d.id {
+            entry.insert("id".into(), Toml::String(id.clone()));
+        }
+        if let Some(version) = &d.version {
+            entry.insert("version".into(), Toml::String(version.clone()));
+        }
+        entry.insert("uri".into(), Toml::String(d.uri.clone()));
+        if let Ok(filename) = d.filename() {
+            entry.insert("filename".into(), Toml::String(filename));
+        }
+        if let Some(purl) = &d.purl {
+            entry.insert("purl".into(), Toml::String(purl.clone()));
+        }
+        manifest.insert(d.sha256.clone(), Toml::Table(entry));
+    }
+
+    Ok(toml::to_string(&Toml::Table(manifest))?)
+}
+
+fn optional_string(value: Option<&Toml>, name: &str) -> Result<Option<String>> {
+    value
+        .map(|v| {
+            v.as_str()
+                .map(|s| s.to_owned())
+                .with_context(|| format!("{name} field should be a string"))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_mirrors, assign_filenames, binding_files, ca_certificate_files, compare_binding,
+        compare_versions, configure_client, configure_proxies, damaged_binaries,
+        extra_certificates, fetch_certificate, github_ref, glob_matches, import_bundle,
+        is_binding_file, keep_latest, link_cached, local_uri, merge_dependencies, order_components,
+        peer_chain, plan_downloads, project_buildpacks, pull_binding, purge_binding,
+        remove_retired, runtime, sbom, transform, transform_dependency, update_manifest,
+        write_bundle, ArtifactReport, ArtifactStatus, Buildpack, CachedResponse, DamagedBinary,
+        Dependency, DependencyFilter, DownloadAuth, DownloadOptions, DownloadReport, Header,
+        Mirror, ProxySettings, Purged, RateLimit, Timeouts,
+    };
+    use crate::cache::ArtifactCache;
+    use crate::certs;
+    use sha2::{Digest, Sha256};
+    use std::{fs, path};
+    use toml::Value as Toml;
+
+    #[test]
+    fn dependency_filename() {
+        assert_eq!(
+            "filename",
+            Dependency {
+                sha256: "".into(),
+                uri: "https://example.com/filename".into(),
+                ..Default::default()
+            }
+            .filename()
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn binding_files_stay_in_binding() {
+        assert!(is_binding_file("type"));
+        assert!(is_binding_file("binaries/jdk.tar.gz"));
+        assert!(!is_binding_file("binaries/"));
+        assert!(!is_binding_file("../type"));
+        assert!(!is_binding_file("binaries/../../type"));
+        assert!(!is_binding_file("/etc/passwd"));
+        assert!(!is_binding_file("sub/type"));
+        assert!(!is_binding_file(".."));
+        assert!(!is_binding_file("C:\\type"));
+    }
+
+    #[test]
+    fn sbom_of_binding() {
+        let binding = tempfile::tempdir().unwrap();
+        fs::create_dir(binding.path().join("binaries")).unwrap();
+        fs::write(binding.path().join("binaries/jdk.tar.gz"), "jdk").unwrap();
+        let deps = [
+            Dependency {
+                id: Some("jdk".into()),
+                version: Some("17.0.5".into()),
+                sha256: "a".repeat(64),
+                uri: "https://example.com/jdk.tar.gz".into(),
+                purl: Some("pkg:generic/bellsoft-jdk@17.0.5?arch=amd64".into()),
+                signature: None,
+                filename: None,
+            },
+            Dependency {
+                sha256: "b".repeat(64),
+                uri: "https://example.com/jdk.tar.gz?v=2".into(),
+                ..Default::default()
+            },
+            // not downloaded, so not in the SBOM
+            Dependency {
+                id: Some("jre".into()),
+                sha256: "c".repeat(64),
+                uri: "https://example.com/jre.tar.gz".into(),
+                ..Default::default()
+            },
+        ];
+        fs::write(
+            binding.path().join("dependencies.toml"),
+            update_manifest(None, &deps).unwrap(),
+        )
+        .unwrap();
+
+        let sbom = sbom(binding.path()).unwrap();
+        assert_eq!(sbom["bomFormat"], "CycloneDX");
+        let components = sbom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0]["name"], "jdk");
+        assert_eq!(components[0]["version"], "17.0.5");
+        assert_eq!(
+            components[0]["purl"],
+            "pkg:generic/bellsoft-jdk@17.0.5?arch=amd64"
+        );
+        assert_eq!(components[0]["hashes"][0]["content"], "a".repeat(64));
+        assert_eq!(components[1]["name"], "jdk.tar.gz");
+        assert_eq!(
+            components[1]["purl"],
+            format!(
+                "pkg:generic/jdk.tar.gz?checksum=sha256:{}&download_url=https%3A%2F%2Fexample.com%2Fjdk.tar.gz%3Fv%3D2",
+                "b".repeat(64)
+            )
+        );
+        assert_eq!(
+            components[1]["externalReferences"][0]["url"],
+            "https://example.com/jdk.tar.gz?v=2"
+        );
+    }
+
+    #[test]
+    fn bundle_round_trip() {
+        let root = tempfile::tempdir().unwrap();
+        let binding = root.path().join("deps");
+        fs::create_dir_all(binding.join("binaries")).unwrap();
+        let sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        fs::write(binding.join("type"), "dependency-mapping").unwrap();
+        fs::write(
+            binding.join(sha256),
+            "file:///bindings/deps/binaries/hello.txt",
+        )
+        .unwrap();
+        fs::write(binding.join("binaries/hello.txt"), "hello").unwrap();
+
+        let bundle = root.path().join("deps.tar.gz");
+        assert_eq!(write_bundle(&binding, &bundle).unwrap(), 3);
+
+        let target = tempfile::tempdir().unwrap();
+        let (imported, count) = import_bundle(&bundle, target.path(), None).unwrap();
+        assert_eq!(imported, target.path().join("deps"));
+        assert_eq!(count, 3);
+        assert_eq!(
+            fs::read_to_string(imported.join("binaries/hello.txt")).unwrap(),
+            "hello"
+        );
+
+        let (renamed, _) = import_bundle(&bundle, target.path(), Some("other")).unwrap();
+        assert_eq!(renamed, target.path().join("other"));
+
+        // a damaged binary isn't bundled
+        fs::write(binding.join("binaries/hello.txt"), "bye").unwrap();
+        assert!(write_bundle(&binding, &bundle).is_err());
+    }
+
+    /// A bundle with the given manifest followed by the given files
+    fn raw_bundle(dest: &path::Path, manifest: &str, files: &[(&str, &str)]) {
+        let out = flate2::write::GzEncoder::new(
+            fs::File::create(dest).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(out);
+        for (name, contents) in [("bundle.toml", manifest)].iter().chain(files) {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            tar.append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "binding name ../../escape would be outside of the binding root")]
+    fn bundle_import_traversal_name() {
+        let root = tempfile::tempdir().unwrap();
+        let bundle = root.path().join("deps.tar.gz");
+        raw_bundle(&bundle, "binding = \"../../escape\"\nfiles = []\n", &[]);
+
+        let target = root.path().join("bindings");
+        fs::create_dir(&target).unwrap();
+        import_bundle(&bundle, &target, None).unwrap();
+    }
+
+    #[test]
+    fn bundle_import_rejects_bad_names() {
+        let root = tempfile::tempdir().unwrap();
+        let bundle = root.path().join("deps.tar.gz");
+        raw_bundle(&bundle, "files = []\n", &[]);
+
+        for name in ["", "/etc/x", ".ssh", "a/b", "a\\b", "C:x"] {
+            assert!(
+                import_bundle(&bundle, root.path(), Some(name)).is_err(),
+                "{} should be rejected",
+                name
+            );
+        }
+        assert!(!root.path().join("x").exists());
+    }
+
+    #[test]
+    fn bundle_import_checksum_mismatch_removes_binding() {
+        let root = tempfile::tempdir().unwrap();
+        let bundle = root.path().join("deps.tar.gz");
+        let sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        raw_bundle(
+            &bundle,
+            &format!(
+                "binding = \"deps\"\n\
+                 [[files]]\npath = \"type\"\nsha256 = \"{sha256}\"\n\
+                 [[files]]\npath = \"binaries/hello.txt\"\nsha256 = \"{sha256}\"\n"
+            ),
+            &[("type", "hello"), ("binaries/hello.txt", "bye")],
+        );
+
+        let err = import_bundle(&bundle, root.path(), None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("checksum mismatch for binaries/hello.txt"));
+        assert!(!root.path().join("deps").exists());
+
+        // a binding that was already there is left alone
+        fs::create_dir(root.path().join("deps")).unwrap();
+        assert!(import_bundle(&bundle, root.path(), None).is_err());
+        assert!(root.path().join("deps").exists());
+    }
+
+    #[test]
+    fn bundle_import_missing_binary_removes_binding() {
+        let root = tempfile::tempdir().unwrap();
+        let bundle = root.path().join("deps.tar.gz");
+        let key = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let value = "file:///bindings/deps/binaries/hello.txt";
+        let sha256 = hex::encode(Sha256::digest(value.as_bytes()));
+        raw_bundle(
+            &bundle,
+            &format!(
+                "binding = \"deps\"\n\
+                 [[files]]\npath = \"{key}\"\nsha256 = \"{sha256}\"\n"
+            ),
+            &[(key, value)],
+        );
+
+        let err = import_bundle(&bundle, root.path(), None).unwrap_err();
+        assert!(err.to_string().contains("1 missing or damaged binaries"));
+        assert!(!root.path().join("deps").exists());
+    }
+
+    #[test]
+    fn pull_binding_asks_before_replacing() {
+        let contents = "dependency-mapping";
+        let digest = format!(
+            "sha256:{}",
+            hex::encode(Sha256::digest(contents.as_bytes()))
+        );
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "artifactType": "application/vnd.binding-tool.dependency-mapping.v1",
+            "layers": [{
+                "digest": digest,
+                "annotations": { "org.opencontainers.image.title": "type" },
+            }],
+        })
+        .to_string();
+
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let blob = format!("/v2/deps/blobs/{digest}");
+        std::thread::spawn(move || {
+            for req in server.incoming_requests() {
+                let body = match req.url() {
+                    "/v2/deps/manifests/latest" => manifest.clone(),
+                    url if url == blob => contents.to_owned(),
+                    _ => {
+                        req.respond(tiny_http::Response::empty(404)).unwrap();
+                        continue;
+                    }
+                };
+                req.respond(tiny_http::Response::from_string(body)).unwrap();
+            }
+        });
+
+        let root = tempfile::tempdir().unwrap();
+        let binding = root.path().join("deps");
+        fs::create_dir(&binding).unwrap();
+        fs::write(binding.join("type"), "other").unwrap();
+        let image = format!("127.0.0.1:{port}/deps:latest");
+
+        let asked = std::cell::Cell::new(0);
+        let res = pull_binding(&binding, &image, root.path(), |file| {
+            assert_eq!(file, "type");
+            asked.set(asked.get() + 1);
+            false
+        });
+        assert!(res.is_err());
+        assert_eq!(asked.get(), 1);
+        assert_eq!(fs::read_to_string(binding.join("type")).unwrap(), "other");
+
+        let pulled = pull_binding(&binding, &image, root.path(), |_| true).unwrap();
+        assert_eq!(pulled, vec![(String::from("type"), true)]);
+        assert_eq!(fs::read_to_string(binding.join("type")).unwrap(), contents);
+        assert!(!binding.join("type.part").exists());
+
+        // files that are already the same aren't asked about
+        let pulled = pull_binding(&binding, &image, root.path(), |_| false).unwrap();
+        assert_eq!(pulled, vec![(String::from("type"), false)]);
+    }
+
+    #[test]
+    fn github_ref_falls_back_to_main_when_tags_fail() {
+        // nothing listens on port 1, so listing the tags fails
+        temp_env::with_var("BT_GITHUB_API_URL", Some("http://127.0.0.1:1"), || {
+            let client = reqwest::Client::new();
+            let rt = runtime().unwrap();
+            assert_eq!(
+                rt.block_on(github_ref(&client, "org/bp", None)).unwrap(),
+                None
+            );
+            assert_eq!(
+                rt.block_on(github_ref(&client, "org/bp", Some("^1.2")))
+                    .unwrap(),
+                None
+            );
+            assert_eq!(
+                rt.block_on(github_ref(&client, "org/bp", Some("v1.2.3")))
+                    .unwrap(),
+                Some(String::from("v1.2.3"))
+            );
+        });
+    }
+
+    #[test]
+    fn binding_files_skip_partial_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("binaries")).unwrap();
+        for file in [
+            "type",
+            "dependencies.toml",
+            "binaries/jdk.tar.gz",
+            "binaries/jre.tar.gz.part",
+            ".DS_Store",
+        ] {
+            fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        assert_eq!(
+            binding_files(dir.path()).unwrap(),
+            vec!["binaries/jdk.tar.gz", "dependencies.toml", "type"]
+        );
+    }
+
+    #[test]
+    fn dependency_local_paths() {
+        let dep = Dependency {
+            uri: "file:///opt/artifacts/jdk.tar.gz".into(),
+            ..Default::default()
+        };
+        assert_eq!(dep.filename().unwrap(), "jdk.tar.gz");
+        assert_eq!(
+            dep.local_path(),
+            Some(path::PathBuf::from("/opt/artifacts/jdk.tar.gz"))
+        );
+
+        let base = path::Path::new("/work/buildpack");
+        assert_eq!(
+            local_uri("dist/jdk.tar.gz", base).as_deref(),
+            Some("file:///work/buildpack/dist/jdk.tar.gz")
+        );
+        assert_eq!(
+            local_uri("/opt/jdk.tar.gz", base).as_deref(),
+            Some("file:///opt/jdk.tar.gz")
+        );
+        assert_eq!(local_uri("https://example.com/jdk.tar.gz", base), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "no path segments for")]
+    fn dependency_filename_no_path() {
+        assert_eq!(
+            "filename",
+            Dependency {
+                sha256: "".into(),
+                uri: "data:text/plain,HelloWorld".into(),
+                ..Default::default()
+            }
+            .filename()
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn dependency_file_matches() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("filename.part");
+
+        let dep = Dependency {
+            sha256: "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".into(),
+            uri: "https://example.com/filename".into(),
+            ..Default::default()
+        };
+        assert!(!dep.file_matches(&path).unwrap());
+
+        std::fs::write(&path, "hello").unwrap();
+        assert!(dep.file_matches(&path).unwrap());
+
+        std::fs::write(&path, "hello world").unwrap();
+        assert!(!dep.file_matches(&path).unwrap());
+    }
+
+    #[test]
+    fn download_options_max_simultaneous() {
+        temp_env::with_var("BT_MAX_SIMULTANEOUS", Some("12"), || {
+            let opts = DownloadOptions::default();
+            assert_eq!(opts.max_simultaneous().unwrap(), 12);
+
+            let opts = DownloadOptions {
+                jobs: Some(3),
+                ..Default::default()
+            };
+            assert_eq!(opts.max_simultaneous().unwrap(), 3);
+        });
+
+        let with_config = DownloadOptions {
+            config: "jobs = 8".parse().unwrap(),
+            ..Default::default()
+        };
+        temp_env::with_var_unset("BT_MAX_SIMULTANEOUS", || {
+            assert_eq!(DownloadOptions::default().max_simultaneous().unwrap(), 5);
+            assert_eq!(with_config.max_simultaneous().unwrap(), 8);
+        });
+        temp_env::with_var("BT_MAX_SIMULTANEOUS", Some("12"), || {
+            assert_eq!(with_config.max_simultaneous().unwrap(), 12);
+        });
+
+        temp_env::with_var("BT_MAX_SIMULTANEOUS", Some("0"), || {
+            assert!(DownloadOptions::default().max_simultaneous().is_err());
+        });
+
+        temp_env::with_var("BT_MAX_SIMULTANEOUS", Some("lots"), || {
+            assert!(DownloadOptions::default().max_simultaneous().is_err());
+        });
+    }
+
+    #[test]
+    fn mirror_parse() {
+        assert_eq!(
+            "https://nexus.corp/remote/".parse::<Mirror>().unwrap(),
+            Mirror {
+                host: None,
+                base: "https://nexus.corp/remote".into()
+            }
+        );
+        assert_eq!(
+            "GitHub.com=https://nexus.corp/{originalHost}"
+                .parse::<Mirror>()
+                .unwrap(),
+            Mirror {
+                host: Some("github.com".into()),
+                base: "https://nexus.corp/{originalHost}".into()
+            }
+        );
+        assert!("not a url".parse::<Mirror>().is_err());
+    }
+
+    #[test]
+    fn mirror_rewrites_uri() {
+        let mirrors: Vec<Mirror> = vec![
+            "https://nexus.corp/remote/".parse().unwrap(),
+            "download.bell-sw.com=https://nexus.corp/{originalHost}/"
+                .parse()
+                .unwrap(),
+        ];
+
+        assert_eq!(
+            apply_mirrors(&mirrors, "https://github.com/org/repo/file.tgz?x=1").unwrap(),
+            "https://nexus.corp/remote/org/repo/file.tgz?x=1"
+        );
+        assert_eq!(
+            apply_mirrors(&mirrors, "https://download.bell-sw.com/java/jdk.tar.gz").unwrap(),
+            "https://nexus.corp/download.bell-sw.com/java/jdk.tar.gz"
+        );
+        assert_eq!(
+            apply_mirrors(&[], "https://github.com/org/repo/file.tgz").unwrap(),
+            "https://github.com/org/repo/file.tgz"
+        );
+    }
+
+    #[test]
+    fn link_cached_requires_every_artifact() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = ArtifactCache::open(&root.path().join("cache"));
+        let binding = root.path().join("binding");
+        fs::create_dir_all(cache.dir()).unwrap();
+        fs::create_dir_all(binding.join("binaries")).unwrap();
+
+        // sha256 of "hello"
+        let cached = Dependency {
+            sha256: "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".into(),
+            uri: "https://example.com/hello.txt".into(),
+            ..Default::default()
+        };
+        let uncached = Dependency {
+            sha256: "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7".into(),
+            uri: "https://example.com/world.txt".into(),
+            ..Default::default()
+        };
+        fs::write(cache.path(&cached.sha256), "hello").unwrap();
+
+        let rt = runtime().unwrap();
+        let err = rt
+            .block_on(link_cached(
+                &[cached.clone(), uncached.clone()],
+                &binding,
+                &cache,
+            ))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("https://example.com/world.txt"),
+            "{}",
+            err
+        );
+        assert!(!binding.join("binaries").join("hello.txt").exists());
+
+        rt.block_on(link_cached(&[cached], &binding, &cache))
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(binding.join("binaries").join("hello.txt")).unwrap(),
+            "hello"
+        );
+    }
+
+    #[test]
+    fn plan_downloads_uses_cache() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = root.path().join("cache");
+        let binding = root.path().join("binding");
+        fs::create_dir_all(cache.join("artifacts")).unwrap();
+
+        let dep = Dependency {
+            sha256: "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".into(),
+            uri: "https://example.com/hello.txt".into(),
+            ..Default::default()
+        };
+        fs::write(cache.join("artifacts").join(&dep.sha256), "hello").unwrap();
+
+        let opts = DownloadOptions {
+            cache_dir: Some(cache),
+            mirrors: vec!["https://mirror.corp/".parse().unwrap()],
+            ..Default::default()
+        };
+        let planned = plan_downloads(&[dep], &binding, &opts).unwrap();
+
+        assert_eq!(planned.len(), 1);
+        assert_eq!(planned[0].uri, "https://mirror.corp/hello.txt");
+        assert_eq!(planned[0].dest, binding.join("binaries").join("hello.txt"));
+        assert_eq!(planned[0].size, Some(5));
+        assert!(planned[0].cached);
+    }
+
+    #[test]
+    fn damaged_binaries_in_binding() {
+        let binding = tempfile::tempdir().unwrap();
+        let binaries = binding.path().join("binaries");
+        fs::create_dir(&binaries).unwrap();
+        fs::write(binding.path().join("type"), "dependency-mapping").unwrap();
+
+        let good = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let changed = "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7";
+        let missing = "0000000000000000000000000000000000000000000000000000000000000000";
+        for (sha256, filename) in [(good, "a.txt"), (changed, "b.txt"), (missing, "c.txt")] {
+            fs::write(
+                binding.path().join(sha256),
+                format!("file:///bindings/dm/binaries/{filename}"),
+            )
+            .unwrap();
+        }
+        fs::write(binaries.join("a.txt"), "hello").unwrap();
+        fs::write(binaries.join("b.txt"), "not world").unwrap();
+
+        assert_eq!(
+            damaged_binaries(binding.path()).unwrap(),
+            vec![
+                DamagedBinary {
+                    sha256: changed.into(),
+                    path: binaries.join("b.txt"),
+                    missing: false,
+                },
+                DamagedBinary {
+                    sha256: missing.into(),
+                    path: binaries.join("c.txt"),
+                    missing: true,
+                },
+            ]
+        );
     }
 
-    pub(super) fn checksum_matches(&self, binding_path: &path::Path) -> Result<bool> {
-        let dest = binding_path.join("binaries").join(self.filename()?);
-        if !dest.exists() {
-            return Ok(false);
+    #[test]
+    fn purge_binding_removes_orphans() {
+        let binding = tempfile::tempdir().unwrap();
+        let binaries = binding.path().join("binaries");
+        fs::create_dir(&binaries).unwrap();
+        fs::write(binding.path().join("type"), "dependency-mapping").unwrap();
+
+        let kept = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let gone = "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7";
+        let served = "0000000000000000000000000000000000000000000000000000000000000000";
+        for (sha256, value) in [
+            (kept, "file:///bindings/dm/binaries/a.txt"),
+            (gone, "file:///bindings/dm/binaries/b.txt"),
+            (served, "https://artifacts.corp/c.txt"),
+        ] {
+            fs::write(binding.path().join(sha256), value).unwrap();
         }
+        fs::write(binaries.join("a.txt"), "hello").unwrap();
+        fs::write(binaries.join("old.txt"), "old").unwrap();
+        fs::write(
+            binding.path().join("dependencies.toml"),
+            format!("[{kept}]\nid = \"a\"\n\n[{gone}]\nid = \"b\"\n"),
+        )
+        .unwrap();
 
-        let mut fp = File::open(&dest).with_context(|| format!("cannot open file {dest:?}"))?;
+        assert_eq!(
+            purge_binding(binding.path()).unwrap(),
+            Purged {
+                binaries: vec![(binaries.join("old.txt"), 3)],
+                keys: vec![gone.into()],
+            }
+        );
+        assert!(binaries.join("a.txt").exists());
+        assert!(binding.path().join(kept).exists());
+        assert!(!binding.path().join(gone).exists());
+        assert!(binding.path().join(served).exists());
 
-        let mut hasher = Sha256::new();
-        io::copy(&mut fp, &mut hasher)?;
-        let hash = hex::encode(hasher.finalize());
+        let manifest: Toml = fs::read_to_string(binding.path().join("dependencies.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(manifest.get(kept).is_some());
+        assert!(manifest.get(gone).is_none());
 
-        Ok(hash == self.sha256)
+        assert_eq!(purge_binding(binding.path()).unwrap(), Purged::default());
     }
 
-    pub(super) fn download(&self, agent: &ureq::Agent, binding_path: &path::Path) -> Result<()> {
-        if self.checksum_matches(binding_path)? {
-            return Ok(());
+    #[test]
+    fn compare_versions_numerically() {
+        use std::cmp::Ordering;
+
+        assert_eq!(compare_versions("1.10.0", "1.9.2"), Ordering::Greater);
+        assert_eq!(compare_versions("17.0.1+12", "17.0.1+9"), Ordering::Greater);
+        assert_eq!(compare_versions("8.0.302", "8.0.302"), Ordering::Equal);
+        assert_eq!(compare_versions("2.0", "2.0.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn keep_latest_versions() {
+        let binding = tempfile::tempdir().unwrap();
+        let binaries = binding.path().join("binaries");
+        fs::create_dir(&binaries).unwrap();
+
+        let deps = [
+            ("a", "jdk", "17.0.1", "jdk-17.0.1.tgz"),
+            ("b", "jdk", "17.0.10", "jdk-17.0.10.tgz"),
+            ("c", "jdk", "17.0.9", "jdk-17.0.9.tgz"),
+            ("d", "jdk", "17.0.9", "jdk-17.0.9-arm64.tgz"),
+            ("e", "maven", "3.8.1", "maven.tgz"),
+        ];
+        let mut manifest = String::new();
+        for (c, id, version, filename) in deps {
+            let sha256 = c.repeat(64);
+            fs::write(
+                binding.path().join(&sha256),
+                format!("file:///bindings/dm/binaries/{filename}"),
+            )
+            .unwrap();
+            fs::write(binaries.join(filename), id).unwrap();
+            manifest.push_str(&format!(
+                "[{sha256}]\nid = \"{id}\"\nversion = \"{version}\"\n\n"
+            ));
         }
+        fs::write(binding.path().join("dependencies.toml"), manifest).unwrap();
 
-        let dest = binding_path.join("binaries").join(self.filename()?);
-        let mut fp = File::create(&dest).with_context(|| format!("cannot open file {dest:?}"))?;
+        let removed = keep_latest(binding.path(), 2).unwrap();
+        assert_eq!(
+            removed
+                .iter()
+                .map(|d| d.sha256.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a".repeat(64)]
+        );
+        assert!(!binding.path().join("a".repeat(64)).exists());
+        assert!(!binaries.join("jdk-17.0.1.tgz").exists());
+        assert!(binaries.join("jdk-17.0.9-arm64.tgz").exists());
+        assert!(binaries.join("maven.tgz").exists());
+
+        let removed = keep_latest(binding.path(), 1).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(binaries.join("jdk-17.0.10.tgz").exists());
+        assert!(!binaries.join("jdk-17.0.9.tgz").exists());
+        let manifest = fs::read_to_string(binding.path().join("dependencies.toml")).unwrap();
+        assert!(!manifest.contains("17.0.9"));
+    }
 
-        let mut reader = agent.get(&self.uri).call()?.into_reader();
+    #[test]
+    fn update_binding_with_new_and_retired() {
+        let binding = tempfile::tempdir().unwrap();
+        let binaries = binding.path().join("binaries");
+        fs::create_dir(&binaries).unwrap();
 
-        std::io::copy(&mut reader, &mut fp).with_context(|| "copy failed")?;
-        Ok(())
+        let mut manifest = String::new();
+        for (c, version, filename) in [
+            ("a", "17.0.1", "jdk-17.0.1.tgz"),
+            ("b", "17.0.2", "jdk-17.0.2.tgz"),
+        ] {
+            let sha256 = c.repeat(64);
+            fs::write(
+                binding.path().join(&sha256),
+                format!("file:///bindings/dm/binaries/{filename}"),
+            )
+            .unwrap();
+            fs::write(binaries.join(filename), version).unwrap();
+            manifest.push_str(&format!(
+                "[{sha256}]\nid = \"jdk\"\nversion = \"{version}\"\n\n"
+            ));
+        }
+        fs::write(binding.path().join("dependencies.toml"), manifest).unwrap();
+
+        let deps = ["b", "c"]
+            .iter()
+            .map(|c| Dependency {
+                id: Some("jdk".into()),
+                sha256: c.repeat(64),
+                uri: format!("https://example.com/{c}.tgz"),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let update = compare_binding(binding.path(), &deps, &deps).unwrap();
+        assert_eq!(update.added, deps[1..]);
+        assert_eq!(update.unchanged, 1);
+        assert_eq!(update.retired.len(), 1);
+        assert_eq!(update.retired[0].version.as_deref(), Some("17.0.1"));
+
+        remove_retired(binding.path(), &update.retired).unwrap();
+        assert!(!binding.path().join("a".repeat(64)).exists());
+        assert!(!binaries.join("jdk-17.0.1.tgz").exists());
+        assert!(binaries.join("jdk-17.0.2.tgz").exists());
+        let manifest = fs::read_to_string(binding.path().join("dependencies.toml")).unwrap();
+        assert!(!manifest.contains("17.0.1"));
+
+        let missing = binding.path().join("missing");
+        assert_eq!(compare_binding(&missing, &deps, &deps).unwrap().added, deps);
+
+        // what the patterns left out is still in the buildpacks, so it isn't retired
+        let update = compare_binding(binding.path(), &deps[1..], &deps).unwrap();
+        assert!(update.retired.is_empty());
+        assert_eq!(update.unchanged, 0);
     }
-}
 
-pub(super) fn parse_buildpack_toml_from_disk(path: &path::Path) -> Result<Vec<Dependency>> {
-    let mut input = String::new();
+    #[test]
+    fn composite_order_components() {
+        let toml: Toml = r#"
+            [buildpack]
+            id = "corp/java"
+            version = "1.0.0"
 
-    File::open(path)
-        .and_then(|mut f| f.read_to_string(&mut input))
+            [[order]]
+            [[order.group]]
+            id = "corp/jre"
+            version = "2.0.0"
+            [[order.group]]
+            id = "corp/maven"
+            optional = true
+
+            [[order]]
+            [[order.group]]
+            id = "corp/jre"
+            version = "2.0.0"
+            [[order.group]]
+            id = "corp/gradle"
+            version = "3.0.0"
+        "#
+        .parse()
         .unwrap();
 
-    transform(input.parse()?)
-}
+        assert_eq!(
+            order_components(&toml).unwrap(),
+            vec![
+                ("corp/jre".to_owned(), Some("2.0.0".to_owned())),
+                ("corp/maven".to_owned(), None),
+                ("corp/gradle".to_owned(), Some("3.0.0".to_owned())),
+            ]
+        );
 
-pub(super) fn parse_buildpack_toml_from_network(buildpack: &str) -> Result<Vec<Dependency>> {
-    let parts = buildpack.splitn(2, '@').collect::<Vec<&str>>();
+        let toml: Toml = "[buildpack]\nid = \"corp/jre\"".parse().unwrap();
+        assert!(order_components(&toml).unwrap().is_empty());
 
-    let uri = match parts.as_slice() {
-        [b] => Ok(format!("https://raw.githubusercontent.com/{b}/main/buildpack.toml")),
-        [b, v] => Ok(format!("https://raw.githubusercontent.com/{b}/{v}/buildpack.toml")),
-        [..] => Err(anyhow!("parse of [{buildpack}], should have format `buildpack/id@version`, `@version` is optional")),
-    }?;
+        let toml: Toml = "[[order]]\n[[order.group]]\nversion = \"1.0.0\""
+            .parse()
+            .unwrap();
+        assert!(order_components(&toml).is_err());
+    }
 
-    let agent = configure_agent()?;
-    let res = agent
-        .get(&uri)
-        .call()
-        .with_context(|| format!("failed on url {uri}"))?
-        .into_string()
-        .with_context(|| format!("failed on url {uri}"))?;
+    #[test]
+    fn project_and_builder_buildpacks() {
+        let project: Toml = r#"
+            [_]
+            schema-version = "0.2"
 
-    transform(res.parse()?)
-}
+            [[io.buildpacks.pre.group]]
+            uri = "urn:cnb:registry:corp/setup@1.0.0"
 
-pub(super) fn download_dependencies(
-    deps: Vec<Dependency>,
-    binding_path: path::PathBuf,
-) -> Result<()> {
-    let max_simult: usize = env::var("BT_MAX_SIMULTANEOUS")
-        .unwrap_or_else(|_| String::from("5"))
-        .parse()?;
+            [[io.buildpacks.group]]
+            id = "corp/java"
+            version = "2.0.0"
+
+            [[io.buildpacks.group]]
+            uri = "docker://gcr.io/corp/node:1.0.0"
+
+            [[io.buildpacks.group]]
+            id = "corp/java"
+            version = "2.0.0"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            project_buildpacks(&project).unwrap(),
+            vec![
+                "corp/setup@1.0.0",
+                "corp/java@2.0.0",
+                "docker://gcr.io/corp/node:1.0.0"
+            ]
+        );
 
-    let agent = Arc::new(configure_agent()?);
-    let binding_path = Arc::new(binding_path);
-    let deps = Arc::new(Mutex::new(deps));
+        let legacy: Toml = "[[build.buildpacks]]\nid = \"corp/java\"".parse().unwrap();
+        assert_eq!(project_buildpacks(&legacy).unwrap(), vec!["corp/java"]);
 
-    let mut join_handles: Vec<JoinHandle<_>> = vec![];
+        let builder: Toml = r#"
+            [[buildpacks]]
+            uri = "docker://gcr.io/corp/java:2.0.0"
 
-    for _i in 0..max_simult {
-        let agent = Arc::clone(&agent);
-        let binding_path = Arc::clone(&binding_path);
-        let deps = Arc::clone(&deps);
+            [[order]]
+            [[order.group]]
+            id = "corp/java"
+            version = "2.0.0"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            project_buildpacks(&builder).unwrap(),
+            vec!["docker://gcr.io/corp/java:2.0.0"]
+        );
 
-        join_handles.push(thread::spawn(move || {
-            while let Some(d) = deps.lock().expect("unable to get lock").pop() {
-                match d.download(&agent, &binding_path) {
-                    Ok(_) => (),
-                    Err(err) => panic!("Download of {} failed with error {}", d.uri, err),
-                }
-            }
-        }))
+        let builder: Toml = "[[order]]\n[[order.group]]\nid = \"corp/java\"\nversion = \"2.0.0\""
+            .parse()
+            .unwrap();
+        assert_eq!(
+            project_buildpacks(&builder).unwrap(),
+            vec!["corp/java@2.0.0"]
+        );
+
+        let local: Toml = "[[io.buildpacks.group]]\nuri = \"../my-buildpack\""
+            .parse()
+            .unwrap();
+        assert!(project_buildpacks(&local).is_err());
     }
 
-    for handle in join_handles {
-        if let Err(err) = handle.join() {
-            if let Ok(msg) = err.downcast::<String>() {
-                return Err(anyhow!("thread panic: {}", msg));
-            }
-        }
+    #[test]
+    fn timeouts_flags_override_env() {
+        temp_env::with_vars(
+            [
+                ("BT_CONN_TIMEOUT", Some("10")),
+                ("BT_READ_TIMEOUT", None),
+                ("BT_REQ_TIMEOUT", Some("60")),
+            ],
+            || {
+                assert_eq!(
+                    Timeouts::default().or_env().unwrap(),
+                    Timeouts {
+                        connect: Some(10),
+                        read: Some(5),
+                        request: Some(60),
+                    }
+                );
+
+                let flags = Timeouts {
+                    connect: Some(2),
+                    read: Some(30),
+                    request: None,
+                };
+                assert_eq!(
+                    flags.or_env().unwrap(),
+                    Timeouts {
+                        connect: Some(2),
+                        read: Some(30),
+                        request: Some(60),
+                    }
+                );
+            },
+        );
+
+        temp_env::with_var("BT_READ_TIMEOUT", Some("soon"), || {
+            assert!(Timeouts::default().or_env().is_err());
+        });
     }
 
-    Ok(())
-}
+    #[test]
+    fn rate_limit_shares_throughput() {
+        use std::time::{Duration, Instant};
 
-fn configure_agent() -> Result<ureq::Agent> {
-    let conn_timeout: u64 = env::var("BT_CONN_TIMEOUT")
-        .unwrap_or_else(|_| String::from("5"))
-        .parse()?;
+        let limit = RateLimit::new(1000);
+        let now = Instant::now();
 
-    let read_timeout: u64 = env::var("BT_READ_TIMEOUT")
-        .unwrap_or_else(|_| String::from("5"))
-        .parse()?;
+        // back to back chunks queue up behind each other, whichever download they're from
+        assert_eq!(limit.reserve(500, now), Duration::from_millis(500));
+        assert_eq!(limit.reserve(500, now), Duration::from_millis(1000));
+
+        // idle time isn't saved up for a burst later
+        let later = now + Duration::from_secs(5);
+        assert_eq!(limit.reserve(250, later), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn download_report_json() {
+        let dep = |uri: &str| Dependency {
+            uri: uri.into(),
+            sha256: "abc".into(),
+            ..Default::default()
+        };
+        let mut failed = ArtifactReport::new(&dep("https://a/2"), ArtifactStatus::Failed);
+        failed.error = Some(anyhow::anyhow!("checksum mismatch"));
+        let mut downloaded = ArtifactReport::new(&dep("https://a/1"), ArtifactStatus::Downloaded);
+        downloaded.bytes = 10;
+        downloaded.retries = 1;
+
+        let report = DownloadReport {
+            artifacts: vec![
+                downloaded,
+                failed,
+                ArtifactReport::new(&dep("https://a/3"), ArtifactStatus::Cancelled),
+            ],
+            ..Default::default()
+        };
 
-    let mut agent_builder = ureq::builder()
-        .timeout_connect(Duration::from_secs(conn_timeout))
-        .timeout_read(Duration::from_secs(read_timeout));
+        let json = report.to_json();
+        assert_eq!(json["summary"]["total"], 3);
+        assert_eq!(json["summary"]["downloaded"], 1);
+        assert_eq!(json["summary"]["failed"], 1);
+        assert_eq!(json["summary"]["bytes"], 10);
+        assert_eq!(json["artifacts"][0]["retries"], 1);
+        assert_eq!(json["artifacts"][1]["status"], "failed");
+        assert_eq!(json["artifacts"][1]["error"], "checksum mismatch");
+        assert!(json["artifacts"][2]["error"].is_null());
+
+        let err = report.check().unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Download of https://a/2 failed: checksum mismatch"
+        );
 
-    if let Ok(req_timeout) = env::var("BT_REQ_TIMEOUT") {
-        agent_builder = agent_builder.timeout(Duration::from_secs(req_timeout.parse::<u64>()?));
+        let interrupted = DownloadReport {
+            interrupted: true,
+            ..Default::default()
+        };
+        assert!(interrupted.check().is_err());
+        assert!(DownloadReport::default().check().is_ok());
     }
 
-    let proxy_url = env::var("PROXY");
-    if let Ok(proxy_url) = proxy_url {
-        let proxy = Proxy::new(&proxy_url)
-            .with_context(|| format!("unable to parse PROXY url {proxy_url}"))?;
-        agent_builder = agent_builder.proxy(proxy);
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches(
+            "*linux-amd64*",
+            "https://a/jdk-linux-amd64.tar.gz"
+        ));
+        assert!(!glob_matches(
+            "*linux-amd64*",
+            "https://a/jdk-linux-arm64.tar.gz"
+        ));
+        assert!(glob_matches("*.tar.?z", "https://a/jdk.tar.gz"));
+        assert!(glob_matches("jdk*11*", "jdk-11.0.2-11"));
+        assert!(!glob_matches("jdk", "jdk-11"));
+        assert!(!glob_matches("*.zip", "https://a/jdk.zip.tar.gz"));
     }
 
-    Ok(agent_builder.build())
-}
+    #[test]
+    fn dependency_filters() {
+        let dep = |id: &str, uri: &str| Dependency {
+            id: Some(id.into()),
+            uri: uri.into(),
+            ..Default::default()
+        };
+        let glibc = dep("jdk", "https://a/jdk-linux-amd64.tar.gz");
+        let musl = dep("jdk", "https://a/jdk-linux-amd64-musl.tar.gz");
+        let arm = dep("jre", "https://a/jre-linux-arm64.tar.gz");
 
-fn transform(toml: Toml) -> Result<Vec<Dependency>> {
-    let bp_toml = toml
-        .as_table()
-        .with_context(|| "buildpack.toml format is invalid")?;
+        let filter = DependencyFilter::default();
+        assert!(filter.keeps(&glibc) && filter.keeps(&musl) && filter.keeps(&arm));
 
-    let metadata = bp_toml
-        .get("metadata")
-        .with_context(|| "no metadata present in buildpack.toml")?
-        .as_table()
-        .with_context(|| "metadata should be a table")?;
+        let filter = DependencyFilter {
+            include: vec!["*linux-amd64*".into()],
+            exclude: vec!["*musl*".into()],
+        };
+        assert!(filter.keeps(&glibc));
+        assert!(!filter.keeps(&musl));
+        assert!(!filter.keeps(&arm));
 
-    let deps_metadata = metadata
-        .get("dependencies")
-        .with_context(|| "no dependencies present")?
-        .as_array()
-        .with_context(|| "dependencies should be an array")?;
+        let filter = DependencyFilter {
+            include: vec!["jre".into()],
+            exclude: vec![],
+        };
+        assert!(!filter.keeps(&glibc));
+        assert!(filter.keeps(&arm));
 
-    let mut deps = vec![];
+        let mut buildpacks = vec![Buildpack {
+            dependencies: vec![glibc, musl, arm],
+            ..Default::default()
+        }];
+        filter.apply(&mut buildpacks).unwrap();
+        assert_eq!(buildpacks[0].dependencies.len(), 1);
 
-    for d in deps_metadata {
-        let table = d
-            .as_table()
-            .with_context(|| "dependency should be a table")?;
+        let filter = DependencyFilter {
+            include: vec!["jdk".into()],
+            exclude: vec![],
+        };
+        assert!(filter.apply(&mut buildpacks).is_err());
 
-        let uri = table
-            .get("uri")
-            .with_context(|| "uri field is required")?
-            .as_str()
-            .with_context(|| "uri should be a string")?
-            .into();
+        let mut empty = vec![Buildpack::default()];
+        assert!(DependencyFilter::default().apply(&mut empty).is_ok());
+    }
 
-        let sha256 = table.get("sha256");
-        let checksum = table.get("checksum");
+    #[test]
+    fn merge_dependencies_by_sha256() {
+        let dep = |uri: &str, sha256: &str| Dependency {
+            uri: uri.into(),
+            sha256: sha256.into(),
+            ..Default::default()
+        };
+        let buildpacks = vec![
+            Buildpack {
+                dependencies: vec![dep("https://a/1", "1"), dep("https://a/2", "2")],
+                ..Default::default()
+            },
+            Buildpack {
+                dependencies: vec![dep("https://b/2", "2"), dep("https://b/3", "3")],
+                ..Default::default()
+            },
+        ];
 
-        if sha256.is_some() && checksum.is_some() || sha256.is_none() && checksum.is_none() {
-            panic!("sha256 or checksum field is required");
-        }
+        assert_eq!(
+            merge_dependencies(&buildpacks),
+            vec![
+                dep("https://a/1", "1"),
+                dep("https://a/2", "2"),
+                dep("https://b/3", "3")
+            ]
+        );
+    }
 
-        if let Some(sha256) = sha256 {
-            deps.push(Dependency {
-                sha256: sha256
-                    .as_str()
-                    .with_context(|| "sha256 field should be a string")?
-                    .into(),
-                uri,
-            });
-            continue;
-        }
+    #[test]
+    fn cached_response_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CachedResponse::path(dir.path(), "https://example.com/buildpack.toml");
+        assert!(path.starts_with(dir.path().join("metadata")));
+        assert_eq!(CachedResponse::load(&path), None);
 
-        if let Some(checksum) = checksum {
-            let parts = checksum
-                .as_str()
-                .with_context(|| "checksum field should be a string")?
-                .split_once(':');
-            if let Some(("sha256", hash)) = parts {
-                deps.push(Dependency {
-                    sha256: hash.into(),
-                    uri,
-                })
-            } else {
-                panic!("only sha256 algorithm is supported");
-            }
-        }
+        let cached = CachedResponse {
+            etag: Some(r#"W/"abc""#.into()),
+            last_modified: None,
+            body: "[buildpack]\nid = \"a\"\n".into(),
+        };
+        cached.save(&path).unwrap();
+        assert_eq!(CachedResponse::load(&path), Some(cached));
+
+        fs::write(&path, "not toml [").unwrap();
+        assert_eq!(CachedResponse::load(&path), None);
     }
 
-    Ok(deps)
-}
+    #[test]
+    fn update_manifest_merges() {
+        let jdk = Dependency {
+            id: Some("jdk".into()),
+            version: Some("17.0.1".into()),
+            sha256: "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".into(),
+            uri: "https://example.com/jdk-17.0.1.tar.gz".into(),
+            purl: None,
+            signature: None,
+            filename: None,
+        };
+        let jre = Dependency {
+            sha256: "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7".into(),
+            uri: "https://example.com/jre.tar.gz".into(),
+            ..Default::default()
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::{transform, Dependency};
+        let manifest = update_manifest(None, std::slice::from_ref(&jdk)).unwrap();
+        let manifest = update_manifest(Some(&manifest), &[jdk, jre]).unwrap();
+
+        let manifest: Toml = manifest.parse().unwrap();
+        assert_eq!(manifest.as_table().unwrap().len(), 2);
+        let jdk = &manifest["2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"];
+        assert_eq!(jdk["id"].as_str(), Some("jdk"));
+        assert_eq!(jdk["version"].as_str(), Some("17.0.1"));
+        assert_eq!(
+            jdk["uri"].as_str(),
+            Some("https://example.com/jdk-17.0.1.tar.gz")
+        );
+        assert_eq!(jdk["filename"].as_str(), Some("jdk-17.0.1.tar.gz"));
+        let jre = &manifest["486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7"];
+        assert!(jre.get("id").is_none());
+
+        assert!(update_manifest(Some("not toml"), &[]).is_err());
+    }
 
     #[test]
-    fn dependency_filename() {
+    fn ca_certificate_files_from_bindings() {
+        let root = tempfile::tempdir().unwrap();
+        let certs = root.path().join("my-certs");
+        let other = root.path().join("dependency-mapping");
+        fs::create_dir_all(certs.join("..data")).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(certs.join("type"), "ca-certificates\n").unwrap();
+        fs::write(certs.join("provider"), "corp").unwrap();
+        fs::write(certs.join("corp.pem"), "").unwrap();
+        fs::write(certs.join("..data").join("corp.pem"), "").unwrap();
+        fs::write(other.join("type"), "dependency-mapping").unwrap();
+        fs::write(other.join("abc"), "file:///bindings/x").unwrap();
+
         assert_eq!(
-            "filename",
-            Dependency {
-                sha256: "".into(),
-                uri: "https://example.com/filename".into(),
-            }
-            .filename()
+            ca_certificate_files(root.path()).unwrap(),
+            vec![certs.join("corp.pem")]
+        );
+        assert!(ca_certificate_files(&root.path().join("missing"))
             .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn configure_client_with_truststore_in_binding() {
+        let root = tempfile::tempdir().unwrap();
+        let binding = root.path().join("ca-certificates");
+        fs::create_dir_all(&binding).unwrap();
+        fs::write(binding.join("type"), "ca-certificates").unwrap();
+        fs::write(binding.join("root.pem"), certs::tests::TEST_ROOT_CA).unwrap();
+
+        let (store, _) = certs::truststore(&binding, "pkcs12", "changeit").unwrap();
+        fs::write(binding.join("truststore.p12"), store).unwrap();
+        fs::write(binding.join("truststore-password"), "changeit").unwrap();
+
+        temp_env::with_var_unset("BT_CA_BUNDLE", || {
+            assert_eq!(extra_certificates(root.path()).unwrap().len(), 1);
+            assert!(configure_client(root.path(), Timeouts::default()).is_ok());
+        });
+    }
+
+    #[test]
+    fn extra_certificates_requires_pem() {
+        let root = tempfile::tempdir().unwrap();
+        let bundle = root.path().join("bundle.pem");
+        fs::write(&bundle, "not a certificate").unwrap();
+
+        temp_env::with_var("BT_CA_BUNDLE", Some(&bundle), || {
+            let err = extra_certificates(root.path()).unwrap_err();
+            assert!(
+                format!("{err:#}").contains("no PEM certificates"),
+                "{:#}",
+                err
+            );
+        });
+    }
+
+    #[test]
+    fn fetch_certificate_requires_https() {
+        let err =
+            fetch_certificate("http://pki.corp/root.pem", path::Path::new("bindings")).unwrap_err();
+        assert!(
+            err.to_string().contains("only downloaded over https"),
+            "{}",
+            err
         );
+        assert!(fetch_certificate("not a url", path::Path::new("bindings")).is_err());
     }
 
     #[test]
-    #[should_panic(expected = "no path segments for")]
-    fn dependency_filename_no_path() {
-        assert_eq!(
-            "filename",
-            Dependency {
-                sha256: "".into(),
-                uri: "data:text/plain,HelloWorld".into(),
-            }
-            .filename()
+    fn peer_chain_requires_https() {
+        let err = peer_chain("http://example.com").unwrap_err();
+        assert!(err.to_string().contains("not an https url"), "{}", err);
+        assert!(peer_chain("not a url").is_err());
+    }
+
+    #[test]
+    fn proxy_settings_from_env() {
+        let unset = [
+            "PROXY",
+            "http_proxy",
+            "HTTP_PROXY",
+            "https_proxy",
+            "HTTPS_PROXY",
+            "all_proxy",
+            "ALL_PROXY",
+            "no_proxy",
+            "NO_PROXY",
+            "BT_PROXY_USER",
+        ]
+        .map(|v| (v, None::<&str>));
+
+        temp_env::with_vars(unset, || {
+            assert_eq!(ProxySettings::from_env(), ProxySettings::default());
+        });
+
+        let mut vars = unset;
+        vars[2] = ("HTTP_PROXY", Some("http://cgi:8080"));
+        vars[4] = ("HTTPS_PROXY", Some("http://secure:8080"));
+        vars[5] = ("all_proxy", Some("socks5://all:1080"));
+        vars[8] = ("no_proxy", Some("localhost,.corp"));
+        temp_env::with_vars(vars, || {
+            assert_eq!(
+                ProxySettings::from_env(),
+                ProxySettings {
+                    http: Some("socks5://all:1080".into()),
+                    https: Some("http://secure:8080".into()),
+                    no_proxy: Some("localhost,.corp".into()),
+                    credentials: None,
+                }
+            );
+        });
+
+        vars[0] = ("PROXY", Some("http://legacy:3128"));
+        temp_env::with_vars(vars, || {
+            let settings = ProxySettings::from_env();
+            assert_eq!(settings.http.as_deref(), Some("http://legacy:3128"));
+            assert_eq!(settings.https.as_deref(), Some("http://legacy:3128"));
+        });
+    }
+
+    #[test]
+    fn configure_proxies_validates() {
+        let settings = ProxySettings {
+            https: Some("http://proxy:3128".into()),
+            no_proxy: Some("localhost".into()),
+            credentials: Some("me:secret".into()),
+            ..Default::default()
+        };
+        assert!(configure_proxies(reqwest::Client::builder(), &settings).is_ok());
+
+        let settings = ProxySettings {
+            credentials: Some("me".into()),
+            ..settings
+        };
+        assert!(configure_proxies(reqwest::Client::builder(), &settings).is_err());
+    }
+
+    #[test]
+    fn header_parse() {
+        let header: Header = "X-JFrog-Art-Api: abc:def".parse().unwrap();
+        assert_eq!(header.name, "x-jfrog-art-api");
+        assert_eq!(header.value, "abc:def");
+
+        assert!("no-separator".parse::<Header>().is_err());
+        assert!("Bad Name: value".parse::<Header>().is_err());
+    }
+
+    #[test]
+    fn download_auth_apply() {
+        let client = reqwest::Client::new();
+        let auth = DownloadAuth {
+            headers: vec!["X-Extra: 1".parse().unwrap()],
+            token: Some("global".into()),
+            hosts: Default::default(),
+        };
+
+        let req = auth
+            .apply(
+                client.get("https://nexus.corp/a.tgz"),
+                "https://nexus.corp/a.tgz",
+            )
             .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["authorization"], "Bearer global");
+        assert_eq!(req.headers()["x-extra"], "1");
+
+        let auth = DownloadAuth {
+            headers: vec!["Authorization: Basic override".parse().unwrap()],
+            ..auth
+        };
+        let req = auth
+            .apply(
+                client.get("https://nexus.corp/a.tgz"),
+                "https://nexus.corp/a.tgz",
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            req.headers().get_all("authorization").iter().count(),
+            1,
+            "--header should replace credentials"
         );
+        assert_eq!(req.headers()["authorization"], "Basic override");
+
+        let auth = DownloadAuth {
+            headers: vec![],
+            token: Some("global".into()),
+            hosts: r#"
+                ["nexus.corp"]
+                username = "me"
+                password = "secret"
+                "#
+            .parse()
+            .unwrap(),
+        };
+        let req = auth
+            .apply(
+                client.get("https://nexus.corp/a.tgz"),
+                "https://nexus.corp/a.tgz",
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["authorization"], "Basic bWU6c2VjcmV0");
     }
 
     #[test]
@@ -390,6 +4294,143 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn transform_metadata_dependency_sha256_should_be_hex() {
+        let err = transform(
+            toml::from_str(
+                r#"[[metadata.dependencies]]
+                    uri = "https://example.com/filename"
+                    sha256 = "not-a-sha""#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err();
+
+        assert_eq!(format!("{err}"), "invalid dependency #0");
+        assert!(format!("{err:#}").contains("should be 64 hex characters, got not-a-sha"));
+    }
+
+    #[test]
+    fn transform_metadata_dependencies() {
+        let deps = transform(
+            toml::from_str(
+                r#"[[metadata.dependencies]]
+                    uri = "https://example.com/one"
+                    sha256 = "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824"
+
+                    [[metadata.dependencies]]
+                    uri = "https://example.com/two"
+                    checksum = "sha256:486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7""#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(deps.len(), 2);
+        assert_eq!(deps[0].uri, "https://example.com/one");
+        assert_eq!(
+            deps[0].sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(deps[1].uri, "https://example.com/two");
+        assert_eq!(
+            deps[1].sha256,
+            "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7"
+        );
+    }
+
+    #[test]
+    fn filename_collisions() {
+        let binding = tempfile::tempdir().unwrap();
+        let dep = |sha: char, uri: &str| Dependency {
+            sha256: sha.to_string().repeat(64),
+            uri: uri.into(),
+            ..Default::default()
+        };
+
+        // the binding already has an `a` artifact named `jdk.tar.gz`
+        fs::write(
+            binding.path().join("dependencies.toml"),
+            update_manifest(None, &[dep('a', "https://example.com/v1/jdk.tar.gz")]).unwrap(),
+        )
+        .unwrap();
+
+        let mut deps = vec![
+            dep('b', "https://example.com/v2/jdk.tar.gz"),
+            dep('a', "https://mirror.example.com/jdk.tar.gz"),
+            dep('c', "https://example.com/v3/jdk.tar.gz"),
+            dep('d', "https://example.com/jre.tar.gz"),
+        ];
+        assign_filenames(&mut deps, binding.path()).unwrap();
+        assert_eq!(
+            deps.iter()
+                .map(|d| d.filename().unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                "bbbbbbbb-jdk.tar.gz",
+                "jdk.tar.gz",
+                "cccccccc-jdk.tar.gz",
+                "jre.tar.gz"
+            ]
+        );
+
+        // renamed artifacts keep their name once they're in the manifest
+        fs::write(
+            binding.path().join("dependencies.toml"),
+            update_manifest(None, &deps).unwrap(),
+        )
+        .unwrap();
+        let mut again = vec![dep('c', "https://example.com/v3/jdk.tar.gz")];
+        assign_filenames(&mut again, binding.path()).unwrap();
+        assert_eq!(again[0].filename().unwrap(), "cccccccc-jdk.tar.gz");
+    }
+
+    #[test]
+    fn transform_metadata_dependency_uri_placeholders() {
+        let deps = transform(
+            toml::from_str(
+                r#"[[metadata.dependencies]]
+                    id = "jdk"
+                    version = "17.0.5"
+                    build = 8
+                    uri = "https://example.com/${id}/${version}+${build}/${id}-${version}.tar.gz"
+                    signature = "https://example.com/${id}-${version}.tar.gz.sig"
+                    sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824""#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            deps[0].uri,
+            "https://example.com/jdk/17.0.5+8/jdk-17.0.5.tar.gz"
+        );
+        assert_eq!(
+            deps[0].signature.as_deref(),
+            Some("https://example.com/jdk-17.0.5.tar.gz.sig")
+        );
+
+        for (uri, err) in [
+            (
+                "https://example.com/${arch}.tar.gz",
+                "https://example.com/${arch}.tar.gz uses ${arch}, but the dependency has no arch field",
+            ),
+            (
+                "https://example.com/${version",
+                "unterminated placeholder in https://example.com/${version",
+            ),
+        ] {
+            let mut dep = toml::value::Table::new();
+            dep.insert("uri".into(), uri.into());
+            dep.insert(
+                "sha256".into(),
+                "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".into(),
+            );
+            let res = transform_dependency(&Toml::Table(dep));
+            assert_eq!(res.unwrap_err().to_string(), err);
+        }
+    }
+
     #[test]
     #[should_panic(expected = "uri field is required")]
     fn transform_metadata_dependency_should_have_an_uri() {
@@ -0,0 +1,343 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::prelude::*;
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+use std::io::prelude::*;
+use std::process::{Command, Stdio};
+use std::{env, fs, path};
+
+/// Username the docker CLI stores when the secret is an identity token rather than a password
+pub(super) const IDENTITY_TOKEN_USERNAME: &str = "<token>";
+
+/// Registry credentials, as stored by `docker login`
+#[derive(Debug, PartialEq)]
+pub(super) struct Credentials {
+    pub(super) username: String,
+    pub(super) secret: String,
+}
+
+impl Credentials {
+    /// true if the secret is an identity token to exchange for a registry token
+    pub(super) fn is_identity_token(&self) -> bool {
+        self.username == IDENTITY_TOKEN_USERNAME
+    }
+}
+
+/// Location of the docker CLI config, `$DOCKER_CONFIG/config.json` or `~/.docker/config.json`
+fn config_path() -> Option<path::PathBuf> {
+    env::var_os("DOCKER_CONFIG")
+        .map(path::PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".docker")))
+        .map(|d| d.join("config.json"))
+}
+
+/// The server name docker uses when storing credentials for a registry
+fn server_name(registry: &str) -> &str {
+    match registry {
+        "docker.io" | "index.docker.io" | "registry-1.docker.io" => "https://index.docker.io/v1/",
+        registry => registry,
+    }
+}
+
+/// Strips the scheme and path from a key of the `auths` section, like the docker CLI does
+fn normalize(key: &str) -> &str {
+    let key = key
+        .strip_prefix("https://")
+        .or_else(|| key.strip_prefix("http://"))
+        .unwrap_or(key);
+    key.split('/').next().unwrap_or(key)
+}
+
+/// The host of the daemon the docker CLI talks to, `DOCKER_HOST` or the endpoint of the current
+/// context. `None` for the default context, which is the local socket.
+pub(super) fn daemon_host() -> Option<String> {
+    if let Some(host) = env::var("DOCKER_HOST").ok().filter(|h| !h.is_empty()) {
+        return Some(host);
+    }
+
+    let config_path = config_path()?;
+    let context = env::var("DOCKER_CONTEXT")
+        .ok()
+        .filter(|c| !c.is_empty())
+        .or_else(|| {
+            let config: Json =
+                serde_json::from_str(&fs::read_to_string(&config_path).ok()?).ok()?;
+            config["currentContext"].as_str().map(String::from)
+        })
+        .filter(|c| c != "default")?;
+    context_host(config_path.parent()?, &context)
+}
+
+/// The docker endpoint of a context, from its metadata which is stored by the sha256 of its name
+fn context_host(docker_dir: &path::Path, context: &str) -> Option<String> {
+    let meta = docker_dir
+        .join("contexts")
+        .join("meta")
+        .join(hex::encode(Sha256::digest(context.as_bytes())))
+        .join("meta.json");
+    let meta: Json = serde_json::from_str(&fs::read_to_string(meta).ok()?).ok()?;
+    meta["Endpoints"]["docker"]["Host"]
+        .as_str()
+        .map(String::from)
+}
+
+/// true if the daemon at `host` runs somewhere that may not see the paths of this host, sockets
+/// and the loopback address are local
+pub(super) fn is_remote(host: &str) -> bool {
+    match host.split_once("://") {
+        Some(("unix" | "npipe" | "fd", _)) => false,
+        Some(("tcp" | "http" | "https", address)) => {
+            let address = address.split('/').next().unwrap_or(address);
+            let name = match address.strip_prefix('[') {
+                Some(v6) => v6.split(']').next().unwrap_or(v6),
+                None => address.split(':').next().unwrap_or(address),
+            };
+            !matches!(name, "localhost" | "127.0.0.1" | "::1")
+        }
+        _ => true,
+    }
+}
+
+/// Looks up credentials for a registry in the docker CLI config, running credential helpers
+/// where configured. Returns `None` if there are no credentials for the registry.
+pub(super) fn credentials(registry: &str) -> Result<Option<Credentials>> {
+    let path = match config_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+
+    let config: Json = serde_json::from_str(
+        &fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {}", path.to_string_lossy()))?,
+    )
+    .with_context(|| format!("cannot parse {}", path.to_string_lossy()))?;
+
+    lookup(&config, registry, run_helper)
+}
+
+fn lookup(
+    config: &Json,
+    registry: &str,
+    helper: impl Fn(&str, &str) -> Result<Option<Credentials>>,
+) -> Result<Option<Credentials>> {
+    let server = server_name(registry);
+
+    // a registry specific helper wins over the credential store and stored auths
+    if let Some(name) = config["credHelpers"][registry]
+        .as_str()
+        .or_else(|| config["credHelpers"][normalize(server)].as_str())
+    {
+        return helper(name, server);
+    }
+
+    if let Some(name) = config["credsStore"].as_str() {
+        if let Some(creds) = helper(name, server)? {
+            return Ok(Some(creds));
+        }
+    }
+
+    let entry = config["auths"].as_object().and_then(|auths| {
+        auths
+            .iter()
+            .find(|(k, _)| normalize(k) == normalize(server))
+            .map(|(_, v)| v)
+    });
+
+    match entry {
+        Some(entry) => decode_auth(entry).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn decode_auth(entry: &Json) -> Result<Credentials> {
+    if let Some(token) = entry["identitytoken"].as_str() {
+        return Ok(Credentials {
+            username: IDENTITY_TOKEN_USERNAME.into(),
+            secret: token.into(),
+        });
+    }
+
+    let auth = entry["auth"]
+        .as_str()
+        .ok_or_else(|| anyhow!("docker config entry has no auth"))?;
+    let decoded = String::from_utf8(
+        BASE64_STANDARD
+            .decode(auth)
+            .context("invalid auth in docker config")?,
+    )
+    .context("invalid auth in docker config")?;
+    let (username, secret) = decoded
+        .split_once(':')
+        .ok_or_else(|| anyhow!("invalid auth in docker config, expected `user:password`"))?;
+
+    Ok(Credentials {
+        username: username.into(),
+        secret: secret.into(),
+    })
+}
+
+/// Runs `docker-credential-<name> get`, see
+/// https://github.com/docker/docker-credential-helpers for the protocol
+fn run_helper(name: &str, server: &str) -> Result<Option<Credentials>> {
+    let program = format!("docker-credential-{name}");
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("cannot run credential helper {program}"))?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("cannot write to credential helper {program}"))?
+        .write_all(server.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.contains("credentials not found") {
+            return Ok(None);
+        }
+        bail!(
+            "credential helper {program} failed: {}{}",
+            stdout.trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let res: Json = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("invalid response from credential helper {program}"))?;
+    match (res["Username"].as_str(), res["Secret"].as_str()) {
+        (Some(username), Some(secret)) => Ok(Some(Credentials {
+            username: username.into(),
+            secret: secret.into(),
+        })),
+        _ => bail!("credential helper {program} returned no credentials"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{context_host, is_remote, lookup, Credentials};
+    use anyhow::Result;
+    use sha2::{Digest, Sha256};
+    use std::fs;
+
+    fn no_helper(name: &str, _: &str) -> Result<Option<Credentials>> {
+        panic!("unexpected call to helper {}", name)
+    }
+
+    #[test]
+    fn lookup_auths() {
+        let config = serde_json::json!({
+            "auths": {
+                "https://index.docker.io/v1/": {"auth": "dXNlcjpwYXNz"},
+                "gcr.io": {"auth": "X2pzb25fa2V5Ont9"},
+                "ghcr.io": {"identitytoken": "refresh"},
+            }
+        });
+
+        assert_eq!(
+            lookup(&config, "docker.io", no_helper).unwrap(),
+            Some(Credentials {
+                username: "user".into(),
+                secret: "pass".into()
+            })
+        );
+        assert_eq!(
+            lookup(&config, "gcr.io", no_helper).unwrap(),
+            Some(Credentials {
+                username: "_json_key".into(),
+                secret: "{}".into()
+            })
+        );
+        assert!(lookup(&config, "ghcr.io", no_helper)
+            .unwrap()
+            .unwrap()
+            .is_identity_token());
+        assert_eq!(lookup(&config, "quay.io", no_helper).unwrap(), None);
+    }
+
+    #[test]
+    fn lookup_helpers() {
+        let config = serde_json::json!({
+            "auths": {"gcr.io": {}},
+            "credsStore": "desktop",
+            "credHelpers": {"gcr.io": "gcloud"},
+        });
+
+        let helper = |name: &str, server: &str| {
+            Ok(match (name, server) {
+                ("gcloud", "gcr.io") => Some(Credentials {
+                    username: "oauth2accesstoken".into(),
+                    secret: "gcloud-token".into(),
+                }),
+                ("desktop", "https://index.docker.io/v1/") => Some(Credentials {
+                    username: "user".into(),
+                    secret: "desktop-token".into(),
+                }),
+                _ => None,
+            })
+        };
+
+        assert_eq!(
+            lookup(&config, "gcr.io", helper).unwrap().unwrap().secret,
+            "gcloud-token"
+        );
+        assert_eq!(
+            lookup(&config, "docker.io", helper)
+                .unwrap()
+                .unwrap()
+                .secret,
+            "desktop-token"
+        );
+        assert_eq!(lookup(&config, "quay.io", helper).unwrap(), None);
+    }
+
+    #[test]
+    fn remote_daemon_hosts() {
+        assert!(!is_remote("unix:///var/run/docker.sock"));
+        assert!(!is_remote("npipe:////./pipe/docker_engine"));
+        assert!(!is_remote("tcp://localhost:2375"));
+        assert!(!is_remote("tcp://[::1]:2375"));
+        assert!(is_remote("tcp://192.168.49.2:2376"));
+        assert!(is_remote("ssh://me@build-box"));
+    }
+
+    #[test]
+    fn host_of_context() {
+        let docker_dir = tempfile::tempdir().unwrap();
+        let meta = docker_dir
+            .path()
+            .join("contexts/meta")
+            .join(hex::encode(Sha256::digest(b"remote")));
+        fs::create_dir_all(&meta).unwrap();
+        fs::write(
+            meta.join("meta.json"),
+            r#"{"Name":"remote","Endpoints":{"docker":{"Host":"ssh://me@build-box"}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            context_host(docker_dir.path(), "remote").as_deref(),
+            Some("ssh://me@build-box")
+        );
+        assert_eq!(context_host(docker_dir.path(), "missing"), None);
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::header::{HeaderMap, ACCEPT, LINK};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde_json::Value as Json;
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// Base URL of the GitHub API, `BT_GITHUB_API_URL` or `https://api.github.com`
+fn api_url() -> String {
+    env::var("BT_GITHUB_API_URL")
+        .unwrap_or_else(|_| String::from("https://api.github.com"))
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+/// Token for GitHub requests, `BT_GITHUB_TOKEN` or `GITHUB_TOKEN`
+fn token() -> Option<String> {
+    env::var("BT_GITHUB_TOKEN")
+        .or_else(|_| env::var("GITHUB_TOKEN"))
+        .ok()
+        .filter(|t| !t.is_empty())
+}
+
+/// Whether `uri` is served by GitHub, the API or raw file content
+fn is_github(uri: &str, api_url: &str) -> bool {
+    let host = |uri: &str| {
+        Url::parse(uri)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_owned()))
+    };
+    match host(uri) {
+        Some(h) => {
+            h == "api.github.com" || h == "raw.githubusercontent.com" || Some(h) == host(api_url)
+        }
+        None => false,
+    }
+}
+
+/// Sends the GitHub token with requests to GitHub, other hosts never see it
+pub(super) fn authorize(req: RequestBuilder, uri: &str) -> RequestBuilder {
+    match token() {
+        Some(token) if is_github(uri, &api_url()) => req.bearer_auth(token),
+        _ => req,
+    }
+}
+
+/// Fails with an explanation when GitHub rate limited the request
+pub(super) fn check_rate_limit(res: &Response) -> Result<()> {
+    match rate_limit_error(res.status(), res.headers(), token().is_some()) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+fn rate_limit_error(
+    status: StatusCode,
+    headers: &HeaderMap,
+    authenticated: bool,
+) -> Option<anyhow::Error> {
+    let remaining = headers
+        .get("x-ratelimit-remaining")
+        .and_then(|h| h.to_str().ok());
+    let limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN && remaining == Some("0"));
+    if !limited {
+        return None;
+    }
+
+    let reset = headers
+        .get("x-ratelimit-reset")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse::<u64>().ok())
+        .map(|reset| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            format!(
+                ", it resets in {} minutes",
+                reset.saturating_sub(now).div_ceil(60)
+            )
+        })
+        .unwrap_or_default();
+
+    Some(if authenticated {
+        anyhow!("GitHub rate limit exceeded{reset}")
+    } else {
+        anyhow!("GitHub rate limit exceeded for anonymous requests{reset}, set GITHUB_TOKEN or BT_GITHUB_TOKEN to raise the limit")
+    })
+}
+
+/// Names of the tags of a GitHub repository, following pagination
+pub(super) async fn tags(client: &reqwest::Client, repo: &str) -> Result<Vec<String>> {
+    let mut tags = vec![];
+    let mut next = Some(format!("{}/repos/{repo}/tags?per_page=100", api_url()));
+
+    while let Some(uri) = next.take() {
+        let res = authorize(client.get(&uri), &uri)
+            .header(ACCEPT, "application/vnd.github+json")
+            .send()
+            .await
+            .with_context(|| format!("failed on url {uri}"))?;
+        check_rate_limit(&res)?;
+        if res.status() == StatusCode::NOT_FOUND {
+            bail!("GitHub repository {repo} not found");
+        }
+        let res = res.error_for_status()?;
+        next = next_link(res.headers());
+
+        let body: Json = serde_json::from_str(&res.text().await?)
+            .with_context(|| format!("invalid response from {uri}"))?;
+        tags.extend(
+            body.as_array()
+                .ok_or_else(|| anyhow!("invalid response from {uri}, expected a list of tags"))?
+                .iter()
+                .filter_map(|t| t["name"].as_str().map(|n| n.to_owned())),
+        );
+    }
+
+    Ok(tags)
+}
+
+/// The `rel="next"` URL of a `Link` header, as GitHub uses for pagination
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .find(|link| link.split(';').skip(1).any(|p| p.trim() == r#"rel="next""#))
+        .and_then(|link| link.split(';').next())
+        .map(|uri| {
+            uri.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_owned()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_github, next_link, rate_limit_error};
+    use reqwest::header::{HeaderMap, HeaderValue, LINK};
+    use reqwest::StatusCode;
+
+    #[test]
+    fn github_hosts() {
+        let api = "https://api.github.com";
+        assert!(is_github("https://api.github.com/repos/a/b/tags", api));
+        assert!(is_github(
+            "https://raw.githubusercontent.com/a/b/main/buildpack.toml",
+            api
+        ));
+        assert!(!is_github("https://example.com/a/b", api));
+        assert!(!is_github("not a url", api));
+        assert!(is_github(
+            "https://github.corp/api/v3/repos/a/b/tags",
+            "https://github.corp/api/v3"
+        ));
+    }
+
+    #[test]
+    fn rate_limit_errors() {
+        let mut headers = HeaderMap::new();
+        assert!(rate_limit_error(StatusCode::FORBIDDEN, &headers, false).is_none());
+        assert!(rate_limit_error(StatusCode::OK, &headers, false).is_none());
+
+        let err = rate_limit_error(StatusCode::TOO_MANY_REQUESTS, &headers, false).unwrap();
+        assert!(err.to_string().contains("set GITHUB_TOKEN"));
+
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("0"));
+        let err = rate_limit_error(StatusCode::FORBIDDEN, &headers, true).unwrap();
+        assert_eq!(
+            err.to_string(),
+            "GitHub rate limit exceeded, it resets in 0 minutes"
+        );
+    }
+
+    #[test]
+    fn next_link_from_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(next_link(&headers), None);
+
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                r#"<https://api.github.com/repositories/1/tags?page=1>; rel="prev", <https://api.github.com/repositories/1/tags?page=3>; rel="next""#,
+            ),
+        );
+        assert_eq!(
+            next_link(&headers),
+            Some("https://api.github.com/repositories/1/tags?page=3".into())
+        );
+
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                r#"<https://api.github.com/repositories/1/tags?page=1>; rel="first""#,
+            ),
+        );
+        assert_eq!(next_link(&headers), None);
+    }
+}
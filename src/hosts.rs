@@ -0,0 +1,221 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, Context, Result};
+use base64::prelude::*;
+use std::collections::HashMap;
+use std::{env, fs, path, str};
+use toml::Value as Toml;
+
+/// Settings for requests to a single host
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(super) struct HostConfig {
+    /// sent as a bearer token
+    pub(super) token: Option<String>,
+    /// sent with basic auth, along with `password`
+    pub(super) username: Option<String>,
+    pub(super) password: Option<String>,
+    /// extra headers, like `X-JFrog-Art-Api`
+    pub(super) headers: Vec<(String, String)>,
+    /// `sha256/<base64>` hashes of public keys, one of which the host's certificate chain
+    /// must have
+    pub(super) pins: Vec<String>,
+}
+
+/// Per-host settings, keyed by host name
+#[derive(Clone, Debug, Default)]
+pub(super) struct Hosts(HashMap<String, HostConfig>);
+
+impl Hosts {
+    pub(super) fn get(&self, host: &str) -> Option<&HostConfig> {
+        self.0.get(&host.to_lowercase())
+    }
+
+    /// Public key pins by host, for hosts that have them
+    pub(super) fn pins(&self) -> HashMap<String, Vec<String>> {
+        self.0
+            .iter()
+            .filter(|(_, c)| !c.pins.is_empty())
+            .map(|(h, c)| (h.clone(), c.pins.clone()))
+            .collect()
+    }
+}
+
+/// Directory holding the binding-tool configuration, `$XDG_CONFIG_HOME/binding-tool` or
+/// `~/.config/binding-tool`
+pub(super) fn config_dir() -> Option<path::PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(path::PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".config")))
+        .map(|d| d.join("binding-tool"))
+}
+
+/// Loads `hosts.toml` from the config directory, which is optional
+pub(super) fn load() -> Result<Hosts> {
+    let path = match config_dir() {
+        Some(dir) => dir.join("hosts.toml"),
+        None => return Ok(Hosts::default()),
+    };
+    if !path.exists() {
+        return Ok(Hosts::default());
+    }
+
+    let input = fs::read_to_string(&path)
+        .with_context(|| format!("cannot read {}", path.to_string_lossy()))?;
+    input
+        .parse()
+        .with_context(|| format!("invalid host settings in {}", path.to_string_lossy()))
+}
+
+impl str::FromStr for Hosts {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Hosts, Self::Err> {
+        parse(input.parse()?)
+    }
+}
+
+fn parse(input: Toml) -> Result<Hosts> {
+    let table = input
+        .as_table()
+        .ok_or_else(|| anyhow!("expected a table of hosts"))?;
+
+    let string = |host: &str, entry: &Toml, key: &str| -> Result<Option<String>> {
+        match entry.get(key) {
+            Some(v) => v
+                .as_str()
+                .map(|s| Some(s.to_owned()))
+                .ok_or_else(|| anyhow!("{key} for {host} should be a string")),
+            None => Ok(None),
+        }
+    };
+
+    let mut hosts = HashMap::new();
+    for (host, entry) in table {
+        let headers = match entry.get("headers") {
+            Some(headers) => headers
+                .as_table()
+                .ok_or_else(|| anyhow!("headers for {host} should be a table"))?
+                .iter()
+                .map(|(k, v)| {
+                    v.as_str()
+                        .map(|v| (k.to_owned(), v.to_owned()))
+                        .ok_or_else(|| anyhow!("header {k} for {host} should be a string"))
+                })
+                .collect::<Result<_>>()?,
+            None => vec![],
+        };
+
+        let pins = match entry.get("pins") {
+            Some(pins) => pins
+                .as_array()
+                .ok_or_else(|| anyhow!("pins for {host} should be an array"))?
+                .iter()
+                .map(|p| {
+                    p.as_str()
+                        .filter(|p| valid_pin(p))
+                        .map(|p| p.to_owned())
+                        .ok_or_else(|| {
+                            anyhow!("pins for {host} should have format `sha256/<base64 hash>`")
+                        })
+                })
+                .collect::<Result<_>>()?,
+            None => vec![],
+        };
+
+        hosts.insert(
+            host.to_lowercase(),
+            HostConfig {
+                token: string(host, entry, "token")?,
+                username: string(host, entry, "username")?,
+                password: string(host, entry, "password")?,
+                headers,
+                pins,
+            },
+        );
+    }
+
+    Ok(Hosts(hosts))
+}
+
+fn valid_pin(pin: &str) -> bool {
+    pin.strip_prefix("sha256/")
+        .and_then(|hash| BASE64_STANDARD.decode(hash).ok())
+        .map(|hash| hash.len() == 32)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HostConfig, Hosts};
+
+    #[test]
+    fn parse_hosts() {
+        let hosts: Hosts = r#"
+            ["nexus.corp"]
+            username = "me"
+            password = "secret"
+
+            ["Artifactory.Corp"]
+            token = "abc"
+            headers = { X-JFrog-Art-Api = "key" }
+            pins = ["sha256/47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="]
+            "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            hosts.get("nexus.corp"),
+            Some(&HostConfig {
+                username: Some("me".into()),
+                password: Some("secret".into()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            hosts.get("artifactory.corp"),
+            Some(&HostConfig {
+                token: Some("abc".into()),
+                headers: vec![("X-JFrog-Art-Api".into(), "key".into())],
+                pins: vec!["sha256/47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=".into()],
+                ..Default::default()
+            })
+        );
+        assert_eq!(hosts.get("github.com"), None);
+        assert_eq!(hosts.pins().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "token for nexus.corp should be a string")]
+    fn parse_hosts_bad_token() {
+        r#"
+            ["nexus.corp"]
+            token = 1
+            "#
+        .parse::<Hosts>()
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "pins for nexus.corp should have format")]
+    fn parse_hosts_bad_pin() {
+        r#"
+            ["nexus.corp"]
+            pins = ["sha256/abc"]
+            "#
+        .parse::<Hosts>()
+        .unwrap();
+    }
+}
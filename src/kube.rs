@@ -0,0 +1,387 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use base64::prelude::*;
+use serde_json::Value as Json;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+use std::path;
+use std::process::{Command, Stdio};
+
+/// The most a Secret may hold
+const SECRET_LIMIT: u64 = 1024 * 1024;
+
+/// Reads the entries of a Secret or ConfigMap, given as `secret/<name>` or `configmap/<name>`,
+/// with `kubectl` so the current kubeconfig context and its credentials are used. Returns the
+/// key and decoded value of each entry.
+pub(super) fn resource_data(
+    resource: &str,
+    namespace: Option<&str>,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let (kind, name) = resource
+        .split_once('/')
+        .filter(|(_, name)| !name.is_empty())
+        .ok_or_else(|| anyhow!("expected secret/<name> or configmap/<name>, not {resource}"))?;
+    let kind = match kind.to_lowercase().as_str() {
+        "secret" | "secrets" => "secret",
+        "configmap" | "configmaps" | "cm" => "configmap",
+        _ => bail!("only secrets and configmaps hold certificates, not {kind}"),
+    };
+
+    let mut cmd = Command::new("kubectl");
+    cmd.args(["get", kind, name, "-o", "json"]);
+    if let Some(namespace) = namespace {
+        cmd.args(["--namespace", namespace]);
+    }
+    let output = cmd
+        .stdin(Stdio::null())
+        .output()
+        .context("cannot run kubectl, is it installed and on the PATH?")?;
+    ensure!(
+        output.status.success(),
+        "kubectl get {kind} {name} failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    let json: Json = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("invalid response from kubectl for {resource}"))?;
+    parse_data(&json)
+}
+
+/// Entries of a Secret or ConfigMap, decoding base64 `data` of secrets and `binaryData` of
+/// configmaps
+fn parse_data(json: &Json) -> Result<Vec<(String, Vec<u8>)>> {
+    let secret = json["kind"].as_str() == Some("Secret");
+    let decode = |key: &str, value: &Json| -> Result<Vec<u8>> {
+        let value = value
+            .as_str()
+            .ok_or_else(|| anyhow!("value of {key} should be a string"))?;
+        BASE64_STANDARD
+            .decode(value)
+            .with_context(|| format!("value of {key} is not base64 encoded"))
+    };
+
+    let mut entries = vec![];
+    for (section, encoded) in [("data", secret), ("binaryData", true)] {
+        if let Some(data) = json[section].as_object() {
+            for (key, value) in data {
+                let value = if encoded {
+                    decode(key, value)?
+                } else {
+                    value
+                        .as_str()
+                        .ok_or_else(|| anyhow!("value of {key} should be a string"))?
+                        .as_bytes()
+                        .to_vec()
+                };
+                entries.push((key.clone(), value));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// A binding stored in a Secret, with the key and the path relative to the binding of each of
+/// its files
+struct BindingSecret {
+    binding: String,
+    name: String,
+    files: Vec<(String, path::PathBuf)>,
+}
+
+/// Secrets for the bindings under `root`, or only the `selected` ones if there are any, ordered
+/// by binding
+fn binding_secrets(root: &path::Path, selected: &[String]) -> Result<Vec<BindingSecret>> {
+    let mut secrets = vec![];
+    for entry in fs::read_dir(root).with_context(|| format!("cannot read {root:?}"))? {
+        let binding = entry?.path();
+        if !binding.is_dir() || !binding.join("type").exists() {
+            continue;
+        }
+
+        let name = binding
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if !selected.is_empty() && !selected.contains(&name) {
+            continue;
+        }
+
+        let mut files = vec![];
+        binding_files(&binding, path::Path::new(""), &mut files)?;
+        files.sort();
+
+        let mut keys = HashSet::new();
+        for (key, _) in &files {
+            ensure!(
+                keys.insert(key),
+                "more than one file of binding {name} is stored as {key}"
+            );
+        }
+
+        secrets.push(BindingSecret {
+            name: secret_name(&name)?,
+            binding: name,
+            files,
+        });
+    }
+    secrets.sort_by(|a, b| a.binding.cmp(&b.binding));
+
+    let mut names = HashSet::new();
+    for secret in &secrets {
+        ensure!(
+            names.insert(&secret.name),
+            "more than one binding is stored in the Secret {}",
+            secret.name
+        );
+    }
+    Ok(secrets)
+}
+
+/// Adds the files under `dir` of a binding to `files`, skipping hidden ones. Secret keys can't
+/// have directories, so the components of nested paths are joined with `.`
+fn binding_files(
+    binding: &path::Path,
+    dir: &path::Path,
+    files: &mut Vec<(String, path::PathBuf)>,
+) -> Result<()> {
+    let full = binding.join(dir);
+    for entry in fs::read_dir(&full).with_context(|| format!("cannot read {full:?}"))? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        let relative = dir.join(&file_name);
+        if entry.path().is_dir() {
+            binding_files(binding, &relative, files)?;
+        } else {
+            let key = relative
+                .iter()
+                .map(|c| c.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(".")
+                .replace(
+                    |c: char| !c.is_ascii_alphanumeric() && !"-._".contains(c),
+                    "_",
+                );
+            files.push((key, relative));
+        }
+    }
+    Ok(())
+}
+
+/// Name of the Secret of a binding, lowercase letters, digits, `-` and `.` are all that's allowed
+fn secret_name(binding: &str) -> Result<String> {
+    let name = binding.to_lowercase().replace(
+        |c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-',
+        "-",
+    );
+    let name = name.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+    ensure!(
+        !name.is_empty() && name.len() <= 253,
+        "binding {binding} can't be stored in a Secret, rename it"
+    );
+    Ok(name.to_owned())
+}
+
+/// The `volumes`, `volumeMounts` and `env` of a Pod spec that project the Secrets of the
+/// bindings under `root`, or the `selected` ones, into `container_path`, keeping the layout of
+/// the bindings
+pub(super) fn pod_fragment(
+    root: &path::Path,
+    selected: &[String],
+    container_path: &str,
+) -> Result<String> {
+    // YAML strings are written as JSON strings, which are valid YAML too
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+
+    let mut out = String::from("volumes:\n  - name: bindings\n    projected:\n      sources:\n");
+    for secret in binding_secrets(root, selected)? {
+        writeln!(
+            out,
+            "        - secret:\n            name: {}",
+            quote(&secret.name)
+        )?;
+        writeln!(out, "            items:")?;
+        for (key, relative) in &secret.files {
+            let relative = relative
+                .iter()
+                .map(|c| c.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            writeln!(
+                out,
+                "              - key: {}\n                path: {}",
+                quote(key),
+                quote(&format!("{}/{relative}", secret.binding))
+            )?;
+        }
+    }
+    writeln!(
+        out,
+        "volumeMounts:\n  - name: bindings\n    mountPath: {}\n    readOnly: true",
+        quote(container_path)
+    )?;
+    writeln!(
+        out,
+        "env:\n  - name: SERVICE_BINDING_ROOT\n    value: {}",
+        quote(container_path)
+    )?;
+    Ok(out)
+}
+
+/// Manifests of the Secrets holding the bindings under `root`, or the `selected` ones, to be
+/// applied with `kubectl`
+pub(super) fn secret_manifests(root: &path::Path, selected: &[String]) -> Result<String> {
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+
+    let mut documents = vec![];
+    for secret in binding_secrets(root, selected)? {
+        let binding = root.join(&secret.binding);
+        let binding_type = fs::read_to_string(binding.join("type"))?;
+
+        let mut out = String::new();
+        writeln!(out, "apiVersion: v1\nkind: Secret\nmetadata:")?;
+        writeln!(out, "  name: {}", quote(&secret.name))?;
+        writeln!(
+            out,
+            "type: {}",
+            quote(&format!("servicebinding.io/{}", binding_type.trim()))
+        )?;
+        writeln!(out, "data:")?;
+
+        let mut size = 0;
+        for (key, relative) in &secret.files {
+            let file = binding.join(relative);
+            let contents = fs::read(&file).with_context(|| format!("cannot read {file:?}"))?;
+            size += contents.len() as u64;
+            writeln!(
+                out,
+                "  {}: {}",
+                quote(key),
+                quote(&BASE64_STANDARD.encode(contents))
+            )?;
+        }
+        ensure!(
+            size <= SECRET_LIMIT,
+            "binding {} has {size} bytes, more than a Secret can hold",
+            secret.binding
+        );
+        documents.push(out);
+    }
+    Ok(documents.join("---\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_data, pod_fragment, secret_manifests, secret_name};
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn parse_secret_data() {
+        let secret = json!({
+            "kind": "Secret",
+            "data": { "ca.crt": "Y2VydA==", "tls.key": "a2V5" },
+        });
+        assert_eq!(
+            parse_data(&secret).unwrap(),
+            vec![
+                ("ca.crt".to_owned(), b"cert".to_vec()),
+                ("tls.key".to_owned(), b"key".to_vec())
+            ]
+        );
+
+        let invalid = json!({ "kind": "Secret", "data": { "ca.crt": "not base64!" } });
+        assert!(parse_data(&invalid).is_err());
+    }
+
+    #[test]
+    fn parse_configmap_data() {
+        let configmap = json!({
+            "kind": "ConfigMap",
+            "data": { "ca.pem": "cert" },
+            "binaryData": { "ca.der": "ZGVy" },
+        });
+        assert_eq!(
+            parse_data(&configmap).unwrap(),
+            vec![
+                ("ca.pem".to_owned(), b"cert".to_vec()),
+                ("ca.der".to_owned(), b"der".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn secret_names_of_bindings() {
+        assert_eq!(secret_name("My_DB").unwrap(), "my-db");
+        assert_eq!(secret_name("-certs.").unwrap(), "certs");
+        assert!(secret_name("__").is_err());
+    }
+
+    #[test]
+    fn bindings_projected_from_secrets() {
+        let root = tempfile::tempdir().unwrap();
+        let binding = root.path().join("My_DB");
+        fs::create_dir_all(binding.join("binaries")).unwrap();
+        fs::create_dir(binding.join("..data")).unwrap();
+        fs::write(binding.join("type"), "mysql\n").unwrap();
+        fs::write(binding.join("username"), "admin").unwrap();
+        fs::write(binding.join("binaries").join("driver.jar"), "jar").unwrap();
+        fs::create_dir(root.path().join("not-a-binding")).unwrap();
+
+        assert_eq!(
+            pod_fragment(root.path(), &[], "/bindings").unwrap(),
+            r#"volumes:
+  - name: bindings
+    projected:
+      sources:
+        - secret:
+            name: "my-db"
+            items:
+              - key: "binaries.driver.jar"
+                path: "My_DB/binaries/driver.jar"
+              - key: "type"
+                path: "My_DB/type"
+              - key: "username"
+                path: "My_DB/username"
+volumeMounts:
+  - name: bindings
+    mountPath: "/bindings"
+    readOnly: true
+env:
+  - name: SERVICE_BINDING_ROOT
+    value: "/bindings"
+"#
+        );
+
+        assert_eq!(
+            secret_manifests(root.path(), &[]).unwrap(),
+            r#"apiVersion: v1
+kind: Secret
+metadata:
+  name: "my-db"
+type: "servicebinding.io/mysql"
+data:
+  "binaries.driver.jar": "amFy"
+  "type": "bXlzcWwK"
+  "username": "YWRtaW4="
+"#
+        );
+    }
+}
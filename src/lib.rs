@@ -13,8 +13,22 @@
 // limitations under the License.
 
 pub mod args;
+mod cache;
+mod certs;
 mod command;
+mod config;
+mod cosign;
 mod deps;
+mod docker_config;
+mod github;
+mod hosts;
+mod kube;
+mod lock;
+mod oci;
+mod pinning;
+mod registry;
+mod serve;
+mod storage;
 
 #[doc(hidden)]
 pub use command::BT;
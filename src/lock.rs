@@ -0,0 +1,280 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, Context, Result};
+use std::{fs, path, str};
+use toml::value::{Array, Table};
+use toml::Value as Toml;
+
+use crate::deps::{self, Buildpack, Dependency};
+
+/// Default location of the lockfile, in the current directory
+pub(super) const LOCKFILE: &str = "bt-deps.lock";
+
+const HEADER: &str =
+    "# Generated by `bt dependency-mapping`. Run `bt dependency-mapping --from-lock` \
+    to recreate the\n# bindings it describes.\n\n";
+
+/// A buildpack whose dependencies were added to a binding
+#[derive(Clone, Debug, PartialEq)]
+struct LockedBuildpack {
+    binding: String,
+    buildpack: Buildpack,
+}
+
+/// Records the buildpacks and dependencies that went into dependency-mapping bindings, so the
+/// bindings can be recreated exactly
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(super) struct Lockfile {
+    buildpacks: Vec<LockedBuildpack>,
+}
+
+impl Lockfile {
+    /// Loads a lockfile, a missing file is an empty lockfile
+    pub(super) fn load(path: &path::Path) -> Result<Lockfile> {
+        if !path.exists() {
+            return Ok(Lockfile::default());
+        }
+
+        fs::read_to_string(path)
+            .with_context(|| format!("cannot read {}", path.to_string_lossy()))?
+            .parse()
+            .with_context(|| format!("cannot parse {}", path.to_string_lossy()))
+    }
+
+    pub(super) fn save(&self, path: &path::Path) -> Result<()> {
+        fs::write(path, format!("{HEADER}{self}"))
+            .with_context(|| format!("cannot write {}", path.to_string_lossy()))
+    }
+
+    /// Records the dependencies of a buildpack added to `binding`, replacing what was recorded
+    /// for the same buildpack before. Buildpacks without an id can't be told apart, so they're
+    /// only recorded once.
+    pub(super) fn record(&mut self, binding: &str, buildpack: &Buildpack) {
+        let locked = LockedBuildpack {
+            binding: binding.to_owned(),
+            buildpack: buildpack.clone(),
+        };
+        if self.buildpacks.contains(&locked) {
+            return;
+        }
+
+        self.buildpacks.retain(|b| {
+            b.binding != binding || buildpack.id.is_none() || b.buildpack.id != buildpack.id
+        });
+        self.buildpacks.push(locked);
+    }
+
+    /// Every dependency locked for `binding`
+    pub(super) fn dependencies(&self, binding: &str) -> Vec<Dependency> {
+        let mut deps: Vec<Dependency> = vec![];
+        for d in self
+            .buildpacks
+            .iter()
+            .filter(|b| b.binding == binding)
+            .flat_map(|b| &b.buildpack.dependencies)
+        {
+            if !deps.iter().any(|e| e.sha256 == d.sha256) {
+                deps.push(d.clone());
+            }
+        }
+        deps
+    }
+}
+
+impl str::FromStr for Lockfile {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Lockfile, Self::Err> {
+        let toml: Toml = input.parse()?;
+
+        let buildpacks = match toml.get("buildpacks") {
+            Some(buildpacks) => buildpacks
+                .as_array()
+                .ok_or_else(|| anyhow!("buildpacks should be an array"))?
+                .iter()
+                .enumerate()
+                .map(|(i, b)| parse_buildpack(b).with_context(|| format!("invalid buildpack #{i}")))
+                .collect::<Result<_>>()?,
+            None => vec![],
+        };
+
+        Ok(Lockfile { buildpacks })
+    }
+}
+
+fn parse_buildpack(toml: &Toml) -> Result<LockedBuildpack> {
+    let string = |key: &str| -> Result<Option<String>> {
+        match toml.get(key) {
+            Some(v) => v
+                .as_str()
+                .map(|s| Some(s.to_owned()))
+                .ok_or_else(|| anyhow!("{key} should be a string")),
+            None => Ok(None),
+        }
+    };
+
+    let dependencies = match toml.get("dependencies") {
+        Some(deps) => deps
+            .as_array()
+            .ok_or_else(|| anyhow!("dependencies should be an array"))?
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                deps::transform_dependency(d).with_context(|| format!("invalid dependency #{i}"))
+            })
+            .collect::<Result<_>>()?,
+        None => vec![],
+    };
+
+    Ok(LockedBuildpack {
+        binding: string("binding")?.ok_or_else(|| anyhow!("binding is required"))?,
+        buildpack: Buildpack {
+            id: string("id")?,
+            version: string("version")?,
+            dependencies,
+        },
+    })
+}
+
+impl std::fmt::Display for Lockfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let insert = |table: &mut Table, key: &str, value: &Option<String>| {
+            if let Some(value) = value {
+                table.insert(key.into(), Toml::String(value.clone()));
+            }
+        };
+
+        let mut buildpacks = Array::new();
+        for b in &self.buildpacks {
+            let mut dependencies = Array::new();
+            for d in &b.buildpack.dependencies {
+                let mut dep = Table::new();
+                insert(&mut dep, "id", &d.id);
+                insert(&mut dep, "version", &d.version);
+                dep.insert("uri".into(), Toml::String(d.uri.clone()));
+                dep.insert("sha256".into(), Toml::String(d.sha256.clone()));
+                insert(&mut dep, "purl", &d.purl);
+                insert(&mut dep, "signature", &d.signature);
+                dependencies.push(Toml::Table(dep));
+            }
+
+            let mut buildpack = Table::new();
+            buildpack.insert("binding".into(), Toml::String(b.binding.clone()));
+            insert(&mut buildpack, "id", &b.buildpack.id);
+            insert(&mut buildpack, "version", &b.buildpack.version);
+            buildpack.insert("dependencies".into(), Toml::Array(dependencies));
+            buildpacks.push(Toml::Table(buildpack));
+        }
+
+        let mut root = Table::new();
+        root.insert("buildpacks".into(), Toml::Array(buildpacks));
+        let out = toml::to_string(&Toml::Table(root)).map_err(|_| std::fmt::Error)?;
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lockfile;
+    use crate::deps::{Buildpack, Dependency};
+
+    fn buildpack(id: &str, version: &str, deps: &[(&str, &str)]) -> Buildpack {
+        Buildpack {
+            id: Some(id.into()),
+            version: Some(version.into()),
+            dependencies: deps
+                .iter()
+                .map(|(uri, sha256)| Dependency {
+                    id: Some("dep".into()),
+                    version: Some("1.0.0".into()),
+                    uri: (*uri).into(),
+                    sha256: (*sha256).into(),
+                    purl: Some("pkg:generic/dep@1.0.0".into()),
+                    signature: Some(format!("{uri}.sig")),
+                    filename: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn lockfile_round_trip() {
+        let sha1 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let sha2 = "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7";
+
+        let mut lock = Lockfile::default();
+        lock.record(
+            "dependency-mapping",
+            &buildpack("org/java", "1.0.0", &[("https://example.com/a", sha1)]),
+        );
+        lock.record(
+            "dependency-mapping",
+            &buildpack("org/node", "2.0.0", &[("https://example.com/b", sha2)]),
+        );
+        lock.record(
+            "other",
+            &buildpack("org/java", "1.0.0", &[("https://example.com/a", sha1)]),
+        );
+        // a newer version of a buildpack replaces the old one
+        lock.record(
+            "dependency-mapping",
+            &buildpack(
+                "org/java",
+                "1.1.0",
+                &[
+                    ("https://example.com/a", sha1),
+                    ("https://example.com/b", sha2),
+                ],
+            ),
+        );
+
+        let parsed: Lockfile = lock.to_string().parse().unwrap();
+        assert_eq!(parsed, lock);
+
+        let deps = parsed.dependencies("dependency-mapping");
+        assert_eq!(
+            deps.iter().map(|d| d.uri.as_str()).collect::<Vec<_>>(),
+            vec!["https://example.com/b", "https://example.com/a"]
+        );
+        assert_eq!(parsed.dependencies("other").len(), 1);
+        assert!(parsed.dependencies("missing").is_empty());
+    }
+
+    #[test]
+    fn lockfile_keeps_buildpacks_without_id() {
+        let sha1 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let sha2 = "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7";
+        let mut first = buildpack("", "", &[("https://example.com/a", sha1)]);
+        first.id = None;
+        let mut second = buildpack("", "", &[("https://example.com/b", sha2)]);
+        second.id = None;
+
+        let mut lock = Lockfile::default();
+        lock.record("dependency-mapping", &first);
+        lock.record("dependency-mapping", &second);
+        lock.record("dependency-mapping", &first);
+
+        assert_eq!(lock.buildpacks.len(), 2);
+        assert_eq!(lock.dependencies("dependency-mapping").len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "binding is required")]
+    fn lockfile_requires_binding() {
+        "[[buildpacks]]\nid = \"org/java\""
+            .parse::<Lockfile>()
+            .unwrap();
+    }
+}
@@ -0,0 +1,848 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use base64::prelude::*;
+use flate2::read::GzDecoder;
+use reqwest::header::{
+    ACCEPT, AUTHORIZATION, CONTENT_RANGE, CONTENT_TYPE, LOCATION, WWW_AUTHENTICATE,
+};
+use reqwest::{RequestBuilder, StatusCode};
+use serde_json::{json, Value as Json};
+use sha2::{Digest, Sha256};
+use std::io::prelude::*;
+use std::{path, str};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::deps;
+use crate::docker_config::{self, Credentials};
+
+const MANIFEST_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+    application/vnd.oci.image.manifest.v1+json, \
+    application/vnd.docker.distribution.manifest.list.v2+json, \
+    application/vnd.docker.distribution.manifest.v2+json";
+
+const OCI_MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+
+/// Artifact type of dependency-mapping bindings pushed to a registry
+const BINDING_ARTIFACT: &str = "application/vnd.binding-tool.dependency-mapping.v1";
+
+/// Media type and contents of the empty config of an artifact
+const EMPTY_CONFIG: (&str, &[u8]) = ("application/vnd.oci.empty.v1+json", b"{}");
+
+/// Size of the chunks files are pushed in
+const UPLOAD_CHUNK: usize = 8 * 1024 * 1024;
+
+/// Annotation with the path of a file in an artifact, as used by ORAS
+const TITLE: &str = "org.opencontainers.image.title";
+
+/// A reference to an image in an OCI registry, like `gcr.io/paketo-buildpacks/java:10.0.0`
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Reference {
+    pub(super) registry: String,
+    pub(super) repository: String,
+    /// a tag or a digest
+    pub(super) reference: String,
+}
+
+impl str::FromStr for Reference {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Reference, Self::Err> {
+        let input = input.trim_start_matches("docker://");
+
+        let (name, reference) = match input.split_once('@') {
+            Some((name, digest)) => (name, digest.to_owned()),
+            None => match input.rsplit_once(':') {
+                Some((name, tag)) if !tag.contains('/') => (name, tag.to_owned()),
+                _ => (input, String::from("latest")),
+            },
+        };
+
+        // like docker, the first component is only a registry if it looks like a host
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, repo))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_owned(), repo.to_owned())
+            }
+            Some(_) => (String::from("docker.io"), name.to_owned()),
+            None => (String::from("docker.io"), format!("library/{name}")),
+        };
+
+        ensure!(
+            !repository.is_empty() && !reference.is_empty(),
+            "invalid image reference {input}"
+        );
+
+        Ok(Reference {
+            registry,
+            repository,
+            reference,
+        })
+    }
+}
+
+impl Reference {
+    fn base_url(&self) -> String {
+        let host = match self.registry.as_str() {
+            "docker.io" => "registry-1.docker.io",
+            host => host,
+        };
+
+        // local registries are commonly run without TLS
+        let scheme = if host.starts_with("localhost") || host.starts_with("127.0.0.1") {
+            "http"
+        } else {
+            "https"
+        };
+
+        format!("{scheme}://{host}/v2/{}", self.repository)
+    }
+}
+
+/// Client for reading images from a single repository of an OCI registry
+pub(super) struct Registry<'a> {
+    client: &'a reqwest::Client,
+    reference: &'a Reference,
+    /// `Authorization` header value, once the registry has asked for one
+    auth: Mutex<Option<String>>,
+}
+
+impl<'a> Registry<'a> {
+    pub(super) fn new(client: &'a reqwest::Client, reference: &'a Reference) -> Registry<'a> {
+        Registry {
+            client,
+            reference,
+            auth: Mutex::new(None),
+        }
+    }
+
+    /// Sends a GET, answering an auth challenge from the registry if there is one
+    async fn get(&self, url: &str, accept: Option<&str>) -> Result<reqwest::Response> {
+        self.send(url, || {
+            let req = self.client.get(url);
+            match accept {
+                Some(accept) => req.header(ACCEPT, accept),
+                None => req,
+            }
+        })
+        .await?
+        .error_for_status()
+        .with_context(|| format!("failed on url {url}"))
+    }
+
+    /// Sends the request made by `build`, answering an auth challenge from the registry if
+    /// there is one. Pushes are challenged again since they need more access than pulls.
+    async fn send(
+        &self,
+        url: &str,
+        build: impl Fn() -> RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        let send = |auth: Option<String>| {
+            let mut req = build();
+            if let Some(auth) = auth {
+                req = req.header(AUTHORIZATION, auth);
+            }
+            req.send()
+        };
+
+        let res = send(self.auth.lock().await.clone()).await?;
+        if res.status() != StatusCode::UNAUTHORIZED {
+            return Ok(res);
+        }
+
+        let challenge = res
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|h| h.to_str().ok())
+            .ok_or_else(|| anyhow!("unauthorized for {url} and no challenge was given"))?
+            .to_owned();
+        let auth = self.answer_challenge(&challenge).await?;
+        *self.auth.lock().await = Some(auth.clone());
+
+        send(Some(auth))
+            .await
+            .with_context(|| format!("failed on url {url}"))
+    }
+
+    /// Looks up credentials for the registry from the docker CLI config
+    async fn credentials(&self) -> Result<Option<Credentials>> {
+        let registry = self.reference.registry.clone();
+        tokio::task::spawn_blocking(move || docker_config::credentials(&registry)).await?
+    }
+
+    async fn answer_challenge(&self, challenge: &str) -> Result<String> {
+        let creds = self.credentials().await?;
+
+        if challenge.to_ascii_lowercase().starts_with("basic") {
+            let creds = creds.ok_or_else(|| {
+                anyhow!(
+                    "{} requires a login, run `docker login {}`",
+                    self.reference.registry,
+                    self.reference.registry
+                )
+            })?;
+            let encoded = BASE64_STANDARD.encode(format!("{}:{}", creds.username, creds.secret));
+            return Ok(format!("Basic {encoded}"));
+        }
+
+        let token = self.fetch_token(challenge, creds).await?;
+        Ok(format!("Bearer {token}"))
+    }
+
+    async fn fetch_token(&self, challenge: &str, creds: Option<Credentials>) -> Result<String> {
+        let params = parse_challenge(challenge)
+            .ok_or_else(|| anyhow!("unsupported registry auth challenge {challenge}"))?;
+
+        let realm = params
+            .iter()
+            .find(|(k, _)| k == "realm")
+            .map(|(_, v)| v.as_str())
+            .ok_or_else(|| anyhow!("no realm in registry auth challenge {challenge}"))?;
+        let mut query: Vec<(&str, &str)> = params
+            .iter()
+            .filter(|(k, _)| k == "service" || k == "scope")
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+
+        // identity tokens are exchanged through the OAuth2 flow, everything else is
+        // basic auth on the token request
+        let req = match &creds {
+            Some(creds) if creds.is_identity_token() => {
+                query.extend([
+                    ("grant_type", "refresh_token"),
+                    ("refresh_token", creds.secret.as_str()),
+                    ("client_id", "binding-tool"),
+                ]);
+                self.client.post(realm).form(&query)
+            }
+            Some(creds) => self
+                .client
+                .get(realm)
+                .query(&query)
+                .basic_auth(&creds.username, Some(&creds.secret)),
+            None => self.client.get(realm).query(&query),
+        };
+
+        let body = req
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("failed to get a registry token from {realm}"))?
+            .text()
+            .await?;
+        let res: Json = serde_json::from_str(&body)?;
+
+        res["token"]
+            .as_str()
+            .or_else(|| res["access_token"].as_str())
+            .map(|t| t.to_owned())
+            .ok_or_else(|| anyhow!("no token in response from {realm}"))
+    }
+
+    /// Fetches the image manifest, picking the linux/amd64 image from a multi-platform index
+    pub(super) async fn manifest(&self) -> Result<Json> {
+        let mut manifest = self.get_manifest(&self.reference.reference).await?;
+
+        if let Some(manifests) = manifest["manifests"].as_array() {
+            let platform = manifests
+                .iter()
+                .find(|m| {
+                    m["platform"]["os"] == "linux" && m["platform"]["architecture"] == "amd64"
+                })
+                .or_else(|| manifests.first())
+                .ok_or_else(|| anyhow!("image index {:?} is empty", self.reference))?;
+            let digest = platform["digest"]
+                .as_str()
+                .ok_or_else(|| anyhow!("image index entry has no digest"))?
+                .to_owned();
+
+            manifest = self.get_manifest(&digest).await?;
+        }
+
+        Ok(manifest)
+    }
+
+    async fn get_manifest(&self, reference: &str) -> Result<Json> {
+        let url = format!("{}/manifests/{reference}", self.reference.base_url());
+        let body = self.get(&url, Some(MANIFEST_TYPES)).await?.text().await?;
+        serde_json::from_str(&body).with_context(|| format!("invalid manifest from {url}"))
+    }
+
+    /// Fetches a blob and checks that it matches its digest
+    pub(super) async fn blob(&self, digest: &str) -> Result<Vec<u8>> {
+        let url = format!("{}/blobs/{digest}", self.reference.base_url());
+        let blob = self.get(&url, None).await?.bytes().await?.to_vec();
+
+        let expected = digest
+            .strip_prefix("sha256:")
+            .ok_or_else(|| anyhow!("unsupported digest {digest}"))?;
+        ensure!(
+            hex::encode(Sha256::digest(&blob)) == expected,
+            "blob {digest} does not match its digest"
+        );
+
+        Ok(blob)
+    }
+
+    /// Streams a blob into `dest`, which is only given its name once the blob matches its digest
+    pub(super) async fn blob_to_file(&self, digest: &str, dest: &path::Path) -> Result<()> {
+        let expected = digest
+            .strip_prefix("sha256:")
+            .ok_or_else(|| anyhow!("unsupported digest {digest}"))?;
+        let url = format!("{}/blobs/{digest}", self.reference.base_url());
+        let mut res = self.get(&url, None).await?;
+
+        let mut part = dest.as_os_str().to_owned();
+        part.push(".part");
+        let part = path::PathBuf::from(part);
+        let mut fp = tokio::fs::File::create(&part)
+            .await
+            .with_context(|| format!("cannot create {}", part.display()))?;
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = res.chunk().await? {
+            hasher.update(&chunk);
+            fp.write_all(&chunk)
+                .await
+                .with_context(|| format!("cannot write {}", part.display()))?;
+        }
+        fp.flush().await?;
+
+        if hex::encode(hasher.finalize()) != expected {
+            let _ = tokio::fs::remove_file(&part).await;
+            bail!("blob {digest} does not match its digest");
+        }
+        tokio::fs::rename(&part, dest)
+            .await
+            .with_context(|| format!("cannot write {}", dest.display()))
+    }
+
+    /// Uploads a blob unless the repository already has it, returning its digest
+    pub(super) async fn push_blob(&self, blob: Vec<u8>) -> Result<String> {
+        let digest = format!("sha256:{}", hex::encode(Sha256::digest(&blob)));
+        if let Some(location) = self.start_upload(&digest).await? {
+            self.finish_upload(location, &digest, bytes::Bytes::from(blob))
+                .await?;
+        }
+        Ok(digest)
+    }
+
+    /// Uploads a file unless the repository already has it, in chunks so it's never read into
+    /// memory whole. Returns its digest and size.
+    pub(super) async fn push_file(&self, file: &path::Path) -> Result<(String, u64)> {
+        let hashed = file.to_owned();
+        let hasher = tokio::task::spawn_blocking(move || deps::hash_file(&hashed)).await??;
+        let digest = format!("sha256:{}", hex::encode(hasher.finalize()));
+        let size = tokio::fs::metadata(file).await?.len();
+        let mut location = match self.start_upload(&digest).await? {
+            Some(location) => location,
+            None => return Ok((digest, size)),
+        };
+
+        let mut input = tokio::fs::File::open(file)
+            .await
+            .with_context(|| format!("cannot open {}", file.display()))?;
+        let mut offset = 0;
+        loop {
+            let mut chunk = Vec::with_capacity(UPLOAD_CHUNK);
+            (&mut input)
+                .take(UPLOAD_CHUNK as u64)
+                .read_to_end(&mut chunk)
+                .await?;
+            if chunk.is_empty() {
+                break;
+            }
+
+            let end = offset + chunk.len() as u64 - 1;
+            let chunk = bytes::Bytes::from(chunk);
+            let res = self
+                .send(location.as_str(), || {
+                    self.client
+                        .patch(location.as_str())
+                        .header(CONTENT_TYPE, "application/octet-stream")
+                        .header(CONTENT_RANGE, format!("{offset}-{end}"))
+                        .body(chunk.clone())
+                })
+                .await?
+                .error_for_status()
+                .with_context(|| format!("cannot upload {}", file.display()))?;
+            location = upload_location(&res, &location)?;
+            offset = end + 1;
+        }
+
+        self.finish_upload(location, &digest, bytes::Bytes::new())
+            .await?;
+        Ok((digest, size))
+    }
+
+    /// Starts an upload of the blob with `digest`, returning where to send it or `None` when the
+    /// repository already has it
+    async fn start_upload(&self, digest: &str) -> Result<Option<Url>> {
+        let base = self.reference.base_url();
+
+        let url = format!("{base}/blobs/{digest}");
+        if self
+            .send(&url, || self.client.head(&url))
+            .await?
+            .status()
+            .is_success()
+        {
+            return Ok(None);
+        }
+
+        let uploads = Url::parse(&format!("{base}/blobs/uploads/"))?;
+        let res = self
+            .send(uploads.as_str(), || self.client.post(uploads.as_str()))
+            .await?
+            .error_for_status()
+            .with_context(|| format!("cannot start upload to {uploads}"))?;
+        upload_location(&res, &uploads).map(Some)
+    }
+
+    /// Completes an upload with the last of the blob, which may be empty when it was uploaded
+    /// in chunks
+    async fn finish_upload(
+        &self,
+        mut location: Url,
+        digest: &str,
+        body: bytes::Bytes,
+    ) -> Result<()> {
+        location.query_pairs_mut().append_pair("digest", digest);
+        self.send(location.as_str(), || {
+            self.client
+                .put(location.as_str())
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .body(body.clone())
+        })
+        .await?
+        .error_for_status()
+        .with_context(|| format!("cannot upload blob {digest}"))?;
+        Ok(())
+    }
+
+    /// Uploads an image manifest under the tag or digest of the reference, returning the
+    /// manifest's digest
+    pub(super) async fn push_manifest(&self, manifest: &Json) -> Result<String> {
+        let body = serde_json::to_vec(manifest)?;
+        let digest = format!("sha256:{}", hex::encode(Sha256::digest(&body)));
+        let url = format!(
+            "{}/manifests/{}",
+            self.reference.base_url(),
+            self.reference.reference
+        );
+
+        self.send(&url, || {
+            self.client
+                .put(&url)
+                .header(CONTENT_TYPE, OCI_MANIFEST)
+                .body(body.clone())
+        })
+        .await?
+        .error_for_status()
+        .with_context(|| format!("cannot push manifest to {url}"))?;
+        Ok(digest)
+    }
+
+    /// Fetches the config of an image, given its manifest
+    pub(super) async fn config(&self, manifest: &Json) -> Result<Json> {
+        let digest = manifest["config"]["digest"]
+            .as_str()
+            .ok_or_else(|| anyhow!("manifest has no config"))?;
+        serde_json::from_slice(&self.blob(digest).await?)
+            .with_context(|| format!("invalid image config {digest}"))
+    }
+}
+
+/// Where the rest of an upload goes, the location may be relative to the registry
+fn upload_location(res: &reqwest::Response, url: &Url) -> Result<Url> {
+    let location = res
+        .headers()
+        .get(LOCATION)
+        .and_then(|l| l.to_str().ok())
+        .ok_or_else(|| anyhow!("no upload location from {url}"))?;
+    Ok(url.join(location)?)
+}
+
+/// Splits a `Bearer realm="...",service="..."` challenge into its parameters
+fn parse_challenge(challenge: &str) -> Option<Vec<(String, String)>> {
+    let (scheme, params) = challenge.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let mut parsed = vec![];
+    let mut rest = params.trim();
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=')?;
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let (value, after) = quoted.split_once('"')?;
+                (value, after)
+            }
+            None => after.split_once(',').unwrap_or((after, "")),
+        };
+        parsed.push((key.trim().to_owned(), value.to_owned()));
+        rest = after.trim_start_matches(',').trim();
+    }
+
+    Some(parsed)
+}
+
+/// Reads the buildpack.toml of the buildpack packaged in a buildpack image
+pub(super) async fn fetch_buildpack_toml(
+    client: &reqwest::Client,
+    reference: &Reference,
+) -> Result<String> {
+    let registry = Registry::new(client, reference);
+    let manifest = registry.manifest().await?;
+    let config = registry.config(&manifest).await?;
+
+    let labels = &config["config"]["Labels"];
+    let metadata: Json = serde_json::from_str(
+        labels["io.buildpacks.buildpackage.metadata"]
+            .as_str()
+            .ok_or_else(|| anyhow!("{reference:?} is not a buildpack image"))?,
+    )?;
+    let layers: Json = serde_json::from_str(
+        labels["io.buildpacks.buildpack.layers"]
+            .as_str()
+            .ok_or_else(|| anyhow!("{reference:?} has no buildpack layers"))?,
+    )?;
+
+    let id = metadata["id"]
+        .as_str()
+        .ok_or_else(|| anyhow!("buildpackage metadata has no id"))?;
+    let version = metadata["version"]
+        .as_str()
+        .ok_or_else(|| anyhow!("buildpackage metadata has no version"))?;
+
+    // layers are labelled by their uncompressed diff ID, which lines up with the
+    // compressed layers in the manifest by position
+    let diff_id = layers[id][version]["layerDiffID"]
+        .as_str()
+        .ok_or_else(|| anyhow!("no layer for {id}@{version} in {reference:?}"))?;
+    let position = config["rootfs"]["diff_ids"]
+        .as_array()
+        .and_then(|ids| ids.iter().position(|d| d == diff_id))
+        .ok_or_else(|| anyhow!("layer {diff_id} missing from image config"))?;
+    let digest = manifest["layers"][position]["digest"]
+        .as_str()
+        .ok_or_else(|| anyhow!("layer {diff_id} missing from image manifest"))?;
+
+    let layer = registry.blob(digest).await?;
+    read_buildpack_toml(&layer, id, version)
+}
+
+/// Pushes the files of a binding as an OCI artifact with a layer for each file, so artifacts
+/// shared by several pushes are only uploaded once. `files` are paths relative to the binding.
+/// Returns the digest of the artifact's manifest.
+pub(super) async fn push_binding(
+    client: &reqwest::Client,
+    reference: &Reference,
+    binding_path: &path::Path,
+    files: &[String],
+) -> Result<String> {
+    let registry = Registry::new(client, reference);
+
+    let mut layers = vec![];
+    for file in files {
+        let (digest, size) = registry
+            .push_file(&binding_path.join(file))
+            .await
+            .with_context(|| format!("cannot push {file}"))?;
+        layers.push((file.clone(), digest, size));
+    }
+    registry.push_blob(EMPTY_CONFIG.1.to_vec()).await?;
+
+    registry.push_manifest(&binding_manifest(&layers)).await
+}
+
+/// The files of a binding artifact as `(path, digest)`, paths are relative to the binding
+pub(super) async fn binding_layers(registry: &Registry<'_>) -> Result<Vec<(String, String)>> {
+    let manifest = registry.manifest().await?;
+    ensure!(
+        manifest["artifactType"] == BINDING_ARTIFACT,
+        "{:?} is not a dependency-mapping binding",
+        registry.reference
+    );
+
+    manifest["layers"]
+        .as_array()
+        .ok_or_else(|| anyhow!("binding artifact has no layers"))?
+        .iter()
+        .map(|layer| {
+            let title = layer["annotations"][TITLE]
+                .as_str()
+                .ok_or_else(|| anyhow!("binding artifact layer has no {TITLE} annotation"))?;
+            ensure!(
+                deps::is_binding_file(title),
+                "binding artifact layer {title} is outside of the binding"
+            );
+            let digest = layer["digest"]
+                .as_str()
+                .ok_or_else(|| anyhow!("binding artifact layer {title} has no digest"))?;
+            Ok((title.to_owned(), digest.to_owned()))
+        })
+        .collect()
+}
+
+/// The manifest of a binding artifact with the given `(path, digest, size)` layers
+fn binding_manifest(layers: &[(String, String, u64)]) -> Json {
+    json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_MANIFEST,
+        "artifactType": BINDING_ARTIFACT,
+        "config": {
+            "mediaType": EMPTY_CONFIG.0,
+            "digest": format!("sha256:{}", hex::encode(Sha256::digest(EMPTY_CONFIG.1))),
+            "size": EMPTY_CONFIG.1.len(),
+        },
+        "layers": layers
+            .iter()
+            .map(|(file, digest, size)| json!({
+                "mediaType": "application/octet-stream",
+                "digest": digest,
+                "size": size,
+                "annotations": { TITLE: file },
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Finds `/cnb/buildpacks/<escaped id>/<version>/buildpack.toml` in a gzipped layer
+fn read_buildpack_toml(layer: &[u8], id: &str, version: &str) -> Result<String> {
+    let wanted = format!(
+        "cnb/buildpacks/{}/{version}/buildpack.toml",
+        id.replace('/', "_")
+    );
+
+    let mut archive = tar::Archive::new(GzDecoder::new(layer));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+
+        if path.trim_start_matches("./").trim_start_matches('/') == wanted {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            return Ok(contents);
+        }
+    }
+
+    bail!("no buildpack.toml for {id}@{version} in buildpack layer")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        binding_manifest, parse_challenge, read_buildpack_toml, Reference, Registry, UPLOAD_CHUNK,
+    };
+    use std::fs;
+
+    #[test]
+    fn reference_parse() {
+        let cases = vec![
+            (
+                "docker://gcr.io/paketo-buildpacks/java:10.0.0",
+                ("gcr.io", "paketo-buildpacks/java", "10.0.0"),
+            ),
+            (
+                "paketobuildpacks/java",
+                ("docker.io", "paketobuildpacks/java", "latest"),
+            ),
+            ("ubuntu:jammy", ("docker.io", "library/ubuntu", "jammy")),
+            (
+                "localhost:5000/bp@sha256:abcd",
+                ("localhost:5000", "bp", "sha256:abcd"),
+            ),
+        ];
+
+        for (input, (registry, repository, reference)) in cases {
+            assert_eq!(
+                input.parse::<Reference>().unwrap(),
+                Reference {
+                    registry: registry.into(),
+                    repository: repository.into(),
+                    reference: reference.into(),
+                },
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn reference_base_url() {
+        let reference: Reference = "paketobuildpacks/java".parse().unwrap();
+        assert_eq!(
+            reference.base_url(),
+            "https://registry-1.docker.io/v2/paketobuildpacks/java"
+        );
+
+        let reference: Reference = "localhost:5000/bp".parse().unwrap();
+        assert_eq!(reference.base_url(), "http://localhost:5000/v2/bp");
+    }
+
+    #[test]
+    fn challenge_parse() {
+        assert_eq!(
+            parse_challenge(
+                r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/ubuntu:pull""#
+            )
+            .unwrap(),
+            vec![
+                ("realm".into(), "https://auth.docker.io/token".into()),
+                ("service".into(), "registry.docker.io".into()),
+                ("scope".into(), "repository:library/ubuntu:pull".into()),
+            ]
+        );
+        assert!(parse_challenge(r#"Basic realm="registry""#).is_none());
+    }
+
+    #[test]
+    fn buildpack_toml_from_layer() {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            vec![],
+            flate2::Compression::default(),
+        ));
+        let contents = b"[buildpack]\nid = \"org/bp\"\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                "cnb/buildpacks/org_bp/1.2.3/buildpack.toml",
+                &contents[..],
+            )
+            .unwrap();
+        let layer = builder.into_inner().unwrap().finish().unwrap();
+
+        assert_eq!(
+            read_buildpack_toml(&layer, "org/bp", "1.2.3").unwrap(),
+            "[buildpack]\nid = \"org/bp\"\n"
+        );
+        assert!(read_buildpack_toml(&layer, "org/bp", "1.2.4").is_err());
+    }
+
+    #[test]
+    fn binding_artifact_manifest() {
+        let manifest = binding_manifest(&[
+            ("type".into(), "sha256:1234".into(), 18),
+            ("binaries/jdk.tar.gz".into(), "sha256:abcd".into(), 1024),
+        ]);
+
+        assert_eq!(
+            manifest["artifactType"],
+            "application/vnd.binding-tool.dependency-mapping.v1"
+        );
+        assert_eq!(
+            manifest["config"]["digest"],
+            "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+        assert_eq!(manifest["layers"][1]["digest"], "sha256:abcd");
+        assert_eq!(manifest["layers"][1]["size"], 1024);
+        assert_eq!(
+            manifest["layers"][1]["annotations"]["org.opencontainers.image.title"],
+            "binaries/jdk.tar.gz"
+        );
+    }
+
+    #[test]
+    fn push_file_in_chunks() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let registry = std::thread::spawn(move || {
+            let mut requests = vec![];
+            for mut req in server.incoming_requests() {
+                let mut body = vec![];
+                req.as_reader().read_to_end(&mut body).unwrap();
+                let range = req
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Content-Range"))
+                    .map(|h| h.value.to_string());
+                let method = req.method().to_string();
+                requests.push((method.clone(), req.url().to_owned(), range, body.len()));
+
+                let response = match method.as_str() {
+                    "HEAD" => tiny_http::Response::empty(404),
+                    "PUT" => tiny_http::Response::empty(201),
+                    _ => tiny_http::Response::empty(202).with_header(
+                        tiny_http::Header::from_bytes(
+                            "Location",
+                            format!("/v2/deps/blobs/uploads/{}", requests.len()),
+                        )
+                        .unwrap(),
+                    ),
+                };
+                let done = method == "PUT";
+                req.respond(response).unwrap();
+                if done {
+                    return requests;
+                }
+            }
+            requests
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("jdk.tar.gz");
+        fs::write(&file, vec![b'x'; UPLOAD_CHUNK + 10]).unwrap();
+
+        let reference: Reference = format!("127.0.0.1:{port}/deps:latest").parse().unwrap();
+        let client = reqwest::Client::new();
+        let (digest, size) = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(Registry::new(&client, &reference).push_file(&file))
+            .unwrap();
+        assert_eq!(size, UPLOAD_CHUNK as u64 + 10);
+
+        let requests = registry.join().unwrap();
+        let requests: Vec<_> = requests
+            .iter()
+            .map(|(method, url, range, len)| (method.as_str(), url.as_str(), range.clone(), *len))
+            .collect();
+        assert_eq!(
+            requests,
+            vec![
+                ("HEAD", format!("/v2/deps/blobs/{digest}").as_str(), None, 0),
+                ("POST", "/v2/deps/blobs/uploads/", None, 0),
+                (
+                    "PATCH",
+                    "/v2/deps/blobs/uploads/2",
+                    Some(format!("0-{}", UPLOAD_CHUNK - 1)),
+                    UPLOAD_CHUNK
+                ),
+                (
+                    "PATCH",
+                    "/v2/deps/blobs/uploads/3",
+                    Some(format!("{}-{}", UPLOAD_CHUNK, UPLOAD_CHUNK + 9)),
+                    10
+                ),
+                (
+                    "PUT",
+                    format!(
+                        "/v2/deps/blobs/uploads/4?digest={}",
+                        digest.replace(':', "%3A")
+                    )
+                    .as_str(),
+                    None,
+                    0
+                ),
+            ]
+        );
+    }
+}
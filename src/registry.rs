@@ -0,0 +1,253 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, ensure, Context, Result};
+use reqwest::StatusCode;
+use semver::{Version, VersionReq};
+use serde_json::Value as Json;
+use std::env;
+use url::Url;
+
+/// A buildpack version resolved through the Buildpack Registry
+#[derive(Debug)]
+pub(super) struct RegistryBuildpack {
+    pub(super) id: String,
+    pub(super) version: String,
+    pub(super) homepage: Option<String>,
+    /// the buildpack image, pinned by digest
+    pub(super) addr: Option<String>,
+}
+
+impl RegistryBuildpack {
+    /// Candidate locations of buildpack.toml for this version, derived from the buildpack's
+    /// GitHub homepage. Tags are tried with and without a `v` prefix.
+    pub(super) fn buildpack_toml_uris(&self) -> Result<Vec<String>> {
+        let homepage = self
+            .homepage
+            .as_deref()
+            .ok_or_else(|| anyhow!("{} has no homepage in the buildpack registry", self.id))?;
+
+        let url = Url::parse(homepage)
+            .with_context(|| format!("invalid homepage {homepage} for {}", self.id))?;
+        ensure!(
+            url.host_str() == Some("github.com"),
+            "{} is not hosted on GitHub ({homepage})",
+            self.id
+        );
+
+        let repo = url.path().trim_matches('/').trim_end_matches(".git");
+        Ok(vec![
+            format!(
+                "https://raw.githubusercontent.com/{repo}/v{}/buildpack.toml",
+                self.version
+            ),
+            format!(
+                "https://raw.githubusercontent.com/{repo}/{}/buildpack.toml",
+                self.version
+            ),
+        ])
+    }
+}
+
+/// Parses a version range like `^10.3` or `>=1.2, <2`. Returns `None` for exact versions and
+/// anything else, which are used as they are.
+pub(super) fn version_range(version: &str) -> Option<VersionReq> {
+    if Version::parse(version.trim_start_matches('v')).is_ok() {
+        return None;
+    }
+    VersionReq::parse(version).ok()
+}
+
+/// The newest of `versions` in `range`, versions may have a `v` prefix like git tags
+pub(super) fn newest_in_range<'a>(
+    versions: impl IntoIterator<Item = &'a str>,
+    range: &VersionReq,
+) -> Option<&'a str> {
+    versions
+        .into_iter()
+        .filter_map(|v| {
+            Version::parse(v.trim_start_matches('v'))
+                .ok()
+                .filter(|parsed| range.matches(parsed))
+                .map(|parsed| (parsed, v))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, v)| v)
+}
+
+/// Base URL of the Buildpack Registry API, `BT_REGISTRY_URL` or `https://registry.buildpacks.io`
+fn registry_url() -> String {
+    env::var("BT_REGISTRY_URL")
+        .unwrap_or_else(|_| String::from("https://registry.buildpacks.io"))
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+async fn get_json(client: &reqwest::Client, uri: &str) -> Result<Option<Json>> {
+    let res = client.get(uri).send().await?;
+    if res.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let body = res.error_for_status()?.text().await?;
+    serde_json::from_str(&body)
+        .map(Some)
+        .with_context(|| format!("invalid response from {uri}"))
+}
+
+fn index_versions(index: &Json) -> Vec<&str> {
+    index["versions"]
+        .as_array()
+        .map(|v| v.iter().filter_map(|v| v["version"].as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// Every version of a buildpack in the registry, `None` if the registry doesn't know it
+pub(super) async fn versions(client: &reqwest::Client, id: &str) -> Result<Option<Vec<String>>> {
+    let index = get_json(
+        client,
+        &format!("{}/api/v1/buildpacks/{id}", registry_url()),
+    )
+    .await?;
+    Ok(index.map(|index| {
+        index_versions(&index)
+            .into_iter()
+            .map(|v| v.to_owned())
+            .collect()
+    }))
+}
+
+/// Sorts versions newest first, dropping anything that isn't a semantic version
+pub(super) fn sort_versions(versions: Vec<String>) -> Vec<String> {
+    let mut parsed = versions
+        .into_iter()
+        .filter_map(|v| {
+            Version::parse(v.trim_start_matches('v'))
+                .ok()
+                .map(|parsed| (parsed, v))
+        })
+        .collect::<Vec<_>>();
+    parsed.sort_by(|(a, _), (b, _)| b.cmp(a));
+    parsed.into_iter().map(|(_, v)| v).collect()
+}
+
+/// Looks up a buildpack in the registry. Returns `None` if the registry doesn't know the
+/// buildpack or the requested version, `version` defaults to the latest release.
+pub(super) async fn lookup(
+    client: &reqwest::Client,
+    id: &str,
+    version: Option<&str>,
+) -> Result<Option<RegistryBuildpack>> {
+    let base = format!("{}/api/v1/buildpacks/{id}", registry_url());
+
+    let index = match get_json(client, &base).await? {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+
+    let version = match version {
+        Some(version) => match version_range(version) {
+            Some(range) => match newest_in_range(index_versions(&index), &range) {
+                Some(version) => version.to_owned(),
+                None => return Ok(None),
+            },
+            None => version.trim_start_matches('v').to_owned(),
+        },
+        None => index["latest"]["version"]
+            .as_str()
+            .ok_or_else(|| anyhow!("registry has no latest version for {id}"))?
+            .to_owned(),
+    };
+
+    let info = match get_json(client, &format!("{base}/{version}")).await? {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+
+    Ok(Some(RegistryBuildpack {
+        id: id.to_owned(),
+        version,
+        homepage: info["homepage"].as_str().map(|s| s.to_owned()),
+        addr: info["addr"].as_str().map(|s| s.to_owned()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{newest_in_range, sort_versions, version_range, RegistryBuildpack};
+
+    #[test]
+    fn version_ranges() {
+        assert!(version_range("10.4.0").is_none());
+        assert!(version_range("v10.4.0").is_none());
+        assert!(version_range("main").is_none());
+
+        let range = version_range("^10.3").unwrap();
+        assert_eq!(
+            newest_in_range(["v10.2.9", "v10.3.0", "v10.4.2", "v11.0.0", "main"], &range),
+            Some("v10.4.2")
+        );
+        let range = version_range(">=1.2, <1.3").unwrap();
+        assert_eq!(
+            newest_in_range(["1.2.0", "1.2.10", "1.2.9"], &range),
+            Some("1.2.10")
+        );
+        assert_eq!(newest_in_range(["1.3.0"], &range), None);
+    }
+
+    #[test]
+    fn versions_sorted_newest_first() {
+        assert_eq!(
+            sort_versions(vec![
+                "v1.9.0".into(),
+                "main".into(),
+                "v1.10.0".into(),
+                "v1.10.0-rc.1".into(),
+                "v0.1.0".into(),
+            ]),
+            vec!["v1.10.0", "v1.10.0-rc.1", "v1.9.0", "v0.1.0"]
+        );
+    }
+
+    #[test]
+    fn registry_buildpack_toml_uris() {
+        let bp = RegistryBuildpack {
+            id: "paketo-buildpacks/bellsoft-liberica".into(),
+            version: "10.4.0".into(),
+            homepage: Some("https://github.com/paketo-buildpacks/bellsoft-liberica".into()),
+            addr: None,
+        };
+
+        assert_eq!(
+            bp.buildpack_toml_uris().unwrap(),
+            vec![
+                "https://raw.githubusercontent.com/paketo-buildpacks/bellsoft-liberica/v10.4.0/buildpack.toml",
+                "https://raw.githubusercontent.com/paketo-buildpacks/bellsoft-liberica/10.4.0/buildpack.toml",
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is not hosted on GitHub")]
+    fn registry_buildpack_toml_uris_not_github() {
+        RegistryBuildpack {
+            id: "corp/java".into(),
+            version: "1.0.0".into(),
+            homepage: Some("https://gitlab.corp/buildpacks/java".into()),
+            addr: None,
+        }
+        .buildpack_toml_uris()
+        .unwrap();
+    }
+}
@@ -0,0 +1,26 @@
+#!/bin/sh
+# Exports every key of the service bindings in $SERVICE_BINDING_ROOT as <TYPE>_<KEY>, then runs
+# the command it's given, e.g. ENTRYPOINT ["/entrypoint.sh", "java", "-jar", "app.jar"]
+# The type and provider files aren't exported. With two bindings of the same type, the one that
+# sorts last wins.
+set -e
+
+if [ -n "${SERVICE_BINDING_ROOT:-}" ]; then
+    for binding in "$SERVICE_BINDING_ROOT"/*/; do
+        [ -f "${binding}type" ] || continue
+        type=$(cat "${binding}type")
+        for key in "$binding"*; do
+            [ -f "$key" ] || continue
+            case "${key##*/}" in
+                type | provider) continue ;;
+            esac
+            name=$(printf '%s_%s' "$type" "${key##*/}" | LC_ALL=C tr '[:lower:]' '[:upper:]' | LC_ALL=C tr -c 'A-Z0-9' '_')
+            case "$name" in
+                [0-9]*) name="_$name" ;;
+            esac
+            export "$name=$(cat "$key")"
+        done
+    done
+fi
+
+exec "$@"
@@ -1,7 +1,7 @@
 function docker;
     set DOCKER (which docker);
     if test "$argv[1]" = "run";
-        $DOCKER run (bt args -d | string split -n ' ') $argv[2..];
+        eval (string escape -- $DOCKER) run (bt args -d -- $argv[2..]) (string escape -- $argv[2..]);
     else;
         $DOCKER $argv[1..];
     end;
@@ -10,8 +10,17 @@ end;
 function pack;
     set PACK (which pack);
     if test "$argv[1]" = "build";
-        $PACK build $argv[2..] (bt args -p | string split -n ' ');
+        eval (string escape -- $PACK) build (string escape -- $argv[2..]) (bt args -p -- $argv[2..]);
     else;
         $PACK $argv[1..];
     end;
+end;
+
+function podman;
+    set PODMAN (which podman);
+    if test "$argv[1]" = "run";
+        eval (string escape -- $PODMAN) run (bt args --podman -- $argv[2..]) (string escape -- $argv[2..]);
+    else;
+        $PODMAN $argv[1..];
+    end;
 end;
\ No newline at end of file
@@ -0,0 +1,35 @@
+function docker {
+    $DOCKER = (Get-Command docker -CommandType Application | Select-Object -First 1).Source
+
+    if ($args.Count -gt 0 -and $args[0] -eq "run") {
+        $rest = @($args | Select-Object -Skip 1)
+        $bindings = @(bt args -d --json '--' @rest | ConvertFrom-Json | ForEach-Object { $_ })
+        & $DOCKER run @bindings @rest
+    } else {
+        & $DOCKER @args
+    }
+}
+
+function podman {
+    $PODMAN = (Get-Command podman -CommandType Application | Select-Object -First 1).Source
+
+    if ($args.Count -gt 0 -and $args[0] -eq "run") {
+        $rest = @($args | Select-Object -Skip 1)
+        $bindings = @(bt args --podman --json '--' @rest | ConvertFrom-Json | ForEach-Object { $_ })
+        & $PODMAN run @bindings @rest
+    } else {
+        & $PODMAN @args
+    }
+}
+
+function pack {
+    $PACK = (Get-Command pack -CommandType Application | Select-Object -First 1).Source
+
+    if ($args.Count -gt 0 -and $args[0] -eq "build") {
+        $rest = @($args | Select-Object -Skip 1)
+        $bindings = @(bt args -p --json '--' @rest | ConvertFrom-Json | ForEach-Object { $_ })
+        & $PACK build @rest @bindings
+    } else {
+        & $PACK @args
+    }
+}
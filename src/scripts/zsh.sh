@@ -3,7 +3,8 @@ function docker() {
 
     if [[ "$1" == "run" ]]; then
         shift
-        $DOCKER run $(bt args -d) "$@"
+        eval "set -- $(bt args -d -- "$@") \"\$@\""
+        $DOCKER run "$@"
     else
         $DOCKER "$@"
     fi
@@ -14,8 +15,21 @@ function pack() {
 
     if [[ "$1" == "build" ]]; then
         shift
-        $PACK build $(bt args -p) "$@"
+        eval "set -- $(bt args -p -- "$@") \"\$@\""
+        $PACK build "$@"
     else
         $PACK "$@"
     fi
 }
+
+function podman() {
+    PODMAN=$(whence -p podman)
+
+    if [[ "$1" == "run" ]]; then
+        shift
+        eval "set -- $(bt args --podman -- "$@") \"\$@\""
+        $PODMAN run "$@"
+    else
+        $PODMAN "$@"
+    fi
+}
@@ -0,0 +1,139 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::{io, path, thread};
+use tiny_http::{Header, Method, Request, Response};
+
+/// Serves the binaries of a dependency-mapping binding over HTTP
+pub(super) struct Server {
+    inner: tiny_http::Server,
+    /// the `--uri-prefix` that points mappings at this server
+    pub(super) uri_prefix: String,
+}
+
+impl Server {
+    /// Listens on `addr`, given as `host:port` or `:port` for every interface
+    pub(super) fn bind(addr: &str) -> Result<Server> {
+        let inner = tiny_http::Server::http(listen_addr(addr))
+            .map_err(|e| anyhow!("cannot listen on {addr}: {e}"))?;
+        let bound = inner
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| anyhow!("cannot listen on {addr}: not an IP address"))?;
+
+        // when listening everywhere, advertise an address other machines can reach
+        let ip = if bound.ip().is_unspecified() {
+            local_ip().unwrap_or_else(|| bound.ip())
+        } else {
+            bound.ip()
+        };
+
+        Ok(Server {
+            inner,
+            uri_prefix: format!("http://{}/", SocketAddr::new(ip, bound.port())),
+        })
+    }
+
+    /// Serves the files in `dir` until the process is stopped
+    pub(super) fn serve(&self, dir: &path::Path) -> Result<()> {
+        for request in self.inner.incoming_requests() {
+            let dir = dir.to_owned();
+            thread::spawn(move || respond(request, &dir));
+        }
+        Ok(())
+    }
+}
+
+fn listen_addr(addr: &str) -> String {
+    if addr.starts_with(':') {
+        format!("0.0.0.0{addr}")
+    } else {
+        addr.to_owned()
+    }
+}
+
+/// The address of the interface used for outgoing traffic, no packets are sent to find it
+fn local_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    socket.local_addr().ok().map(|a| a.ip())
+}
+
+fn respond(request: Request, dir: &path::Path) -> io::Result<()> {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        return request.respond(Response::empty(405));
+    }
+
+    match binary_path(dir, request.url()).and_then(|p| File::open(p).ok()) {
+        Some(file) => {
+            let content_type =
+                Header::from_bytes(&b"Content-Type"[..], &b"application/octet-stream"[..])
+                    .expect("valid header");
+            // a content length lets clients show download progress
+            request.respond(
+                Response::from_file(file)
+                    .with_header(content_type)
+                    .with_chunked_threshold(usize::MAX),
+            )
+        }
+        None => request.respond(Response::empty(404)),
+    }
+}
+
+/// Maps a request URL to a file directly in `dir`, nothing outside of it is served
+fn binary_path(dir: &path::Path, url: &str) -> Option<path::PathBuf> {
+    let name = url.split('?').next()?.strip_prefix('/')?;
+    if name.is_empty() || name.starts_with('.') || name.contains('/') || name.contains('\\') {
+        return None;
+    }
+
+    let path = dir.join(name);
+    if path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{binary_path, listen_addr};
+    use std::fs;
+
+    #[test]
+    fn listen_addr_defaults_to_every_interface() {
+        assert_eq!(listen_addr(":8080"), "0.0.0.0:8080");
+        assert_eq!(listen_addr("127.0.0.1:8080"), "127.0.0.1:8080");
+    }
+
+    #[test]
+    fn binary_path_stays_in_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("jdk.tar.gz"), "jdk").unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+
+        assert_eq!(
+            binary_path(dir.path(), "/jdk.tar.gz?x=1"),
+            Some(dir.path().join("jdk.tar.gz"))
+        );
+        assert_eq!(binary_path(dir.path(), "/missing.tar.gz"), None);
+        assert_eq!(binary_path(dir.path(), "/sub"), None);
+        assert_eq!(binary_path(dir.path(), "/../jdk.tar.gz"), None);
+        assert_eq!(binary_path(dir.path(), "/sub/../jdk.tar.gz"), None);
+        assert_eq!(binary_path(dir.path(), "/"), None);
+    }
+}
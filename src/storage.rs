@@ -0,0 +1,169 @@
+// Copyright 2022-Present the original author or authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
+use url::Url;
+
+#[cfg(any(feature = "s3", feature = "gcs"))]
+use futures::stream::{BoxStream, TryStreamExt};
+#[cfg(any(feature = "s3", feature = "gcs"))]
+use object_store::{path::Path, ObjectStore};
+
+/// An object being read from cloud storage
+#[cfg_attr(not(any(feature = "s3", feature = "gcs")), allow(dead_code))]
+pub(super) struct Object {
+    pub(super) size: u64,
+    #[cfg(any(feature = "s3", feature = "gcs"))]
+    stream: BoxStream<'static, object_store::Result<Bytes>>,
+}
+
+impl Object {
+    /// The next chunk of the object, `None` at the end
+    pub(super) async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        #[cfg(any(feature = "s3", feature = "gcs"))]
+        return Ok(self.stream.try_next().await?);
+
+        #[cfg(not(any(feature = "s3", feature = "gcs")))]
+        Ok(None)
+    }
+}
+
+/// Whether `uri` points at cloud storage rather than a web server
+pub(super) fn is_object_uri(uri: &str) -> bool {
+    Url::parse(uri).is_ok_and(|u| matches!(u.scheme(), "s3" | "gs"))
+}
+
+/// The bucket and object path of a cloud storage URI like `s3://bucket/path/to/object` or
+/// `gs://bucket/path/to/object`
+fn bucket_and_path(url: &Url) -> Result<(&str, &str)> {
+    let bucket = url
+        .host_str()
+        .filter(|b| !b.is_empty())
+        .ok_or_else(|| anyhow!("no bucket in {url}"))?;
+    let path = url.path().trim_start_matches('/');
+    if path.is_empty() {
+        bail!("no object path in {url}");
+    }
+    Ok((bucket, path))
+}
+
+/// Client for the bucket of an `s3://` URI. Credentials and the region are taken from the
+/// standard `AWS_*` environment variables, falling back to the instance's credentials.
+#[cfg(feature = "s3")]
+fn s3(bucket: &str) -> Result<Box<dyn ObjectStore>> {
+    Ok(Box::new(
+        object_store::aws::AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()?,
+    ))
+}
+
+/// Client for the bucket of a `gs://` URI, using application default credentials
+#[cfg(feature = "gcs")]
+fn gcs(bucket: &str) -> Result<Box<dyn ObjectStore>> {
+    Ok(Box::new(
+        object_store::gcp::GoogleCloudStorageBuilder::from_env()
+            .with_bucket_name(bucket)
+            .build()?,
+    ))
+}
+
+/// The store and path of the object at a cloud storage URI
+#[cfg(any(feature = "s3", feature = "gcs"))]
+fn open(uri: &str) -> Result<(Box<dyn ObjectStore>, Path)> {
+    let url = Url::parse(uri)?;
+    let (bucket, path) = bucket_and_path(&url)?;
+    let store = match url.scheme() {
+        #[cfg(feature = "s3")]
+        "s3" => s3(bucket)?,
+        #[cfg(feature = "gcs")]
+        "gs" => gcs(bucket)?,
+        scheme => bail!(unsupported(scheme)),
+    };
+    Ok((store, Path::from_url_path(path)?))
+}
+
+#[cfg(not(any(feature = "s3", feature = "gcs")))]
+fn open(uri: &str) -> Result<std::convert::Infallible> {
+    let url = Url::parse(uri)?;
+    bucket_and_path(&url)?;
+    bail!(unsupported(url.scheme()))
+}
+
+fn unsupported(scheme: &str) -> String {
+    format!(
+        "{scheme}:// URIs are not supported by this build of bt, rebuild it with `--features {}`",
+        if scheme == "gs" { "gcs" } else { scheme }
+    )
+}
+
+/// Starts reading the object at a cloud storage URI
+#[cfg(any(feature = "s3", feature = "gcs"))]
+pub(super) async fn get(uri: &str) -> Result<Object> {
+    let (store, path) = open(uri)?;
+    let res = store.get(&path).await?;
+    Ok(Object {
+        size: res.meta.size,
+        stream: res.into_stream(),
+    })
+}
+
+#[cfg(not(any(feature = "s3", feature = "gcs")))]
+pub(super) async fn get(uri: &str) -> Result<Object> {
+    match open(uri)? {}
+}
+
+/// The size of the object at a cloud storage URI
+#[cfg(any(feature = "s3", feature = "gcs"))]
+pub(super) async fn size(uri: &str) -> Result<u64> {
+    let (store, path) = open(uri)?;
+    Ok(store.head(&path).await?.size)
+}
+
+#[cfg(not(any(feature = "s3", feature = "gcs")))]
+pub(super) async fn size(uri: &str) -> Result<u64> {
+    match open(uri)? {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bucket_and_path, is_object_uri, unsupported};
+    use url::Url;
+
+    #[test]
+    fn object_uris() {
+        assert!(is_object_uri("s3://artifacts/jdk/jdk-17.tar.gz"));
+        assert!(is_object_uri("gs://artifacts/jdk/jdk-17.tar.gz"));
+        assert!(!is_object_uri("https://example.com/jdk-17.tar.gz"));
+        assert!(!is_object_uri("jdk-17.tar.gz"));
+
+        let url = Url::parse("s3://artifacts/jdk/jdk-17.tar.gz").unwrap();
+        assert_eq!(
+            bucket_and_path(&url).unwrap(),
+            ("artifacts", "jdk/jdk-17.tar.gz")
+        );
+        let url = Url::parse("s3://artifacts/").unwrap();
+        assert!(bucket_and_path(&url).is_err());
+
+        assert_eq!(
+            unsupported("s3"),
+            "s3:// URIs are not supported by this build of bt, rebuild it with `--features s3`"
+        );
+        assert_eq!(
+            unsupported("gs"),
+            "gs:// URIs are not supported by this build of bt, rebuild it with `--features gcs`"
+        );
+    }
+}